@@ -0,0 +1,568 @@
+//! Adaptive Huffman coding, which needs no table of counts up front.
+//!
+//! The encoder and the decoder both start from an empty code tree and
+//! update it in the same way after each value, so that the tree is always a
+//! Huffman tree of the values seen so far. A value seen for the first time
+//! is written as the code of a special "not yet transmitted" (NYT) leaf
+//! followed by the value itself in fixed width.
+//!
+//! Two update rules are available, see [`Algorithm`]. The encoder and the
+//! decoder must use the same one.
+
+use std::io;
+
+use crate::bit_io::{BitSink, BitSource};
+
+/// The position of the root in `Model::nodes`.
+const ROOT: usize = 0;
+
+/// The rule by which the code tree is updated after each value.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Algorithm {
+    /// The FGK (Faller–Gallager–Knuth) algorithm, which keeps the tree a
+    /// Huffman tree by swapping each node on the path to the root with the
+    /// first node of the same weight.
+    #[default]
+    Fgk,
+    /// Vitter's algorithm Λ, which also keeps the leaves of each weight
+    /// after the internal nodes of that weight, and so minimizes the height
+    /// of the tree and the sum of the depths of its leaves among Huffman
+    /// trees. Its output is at most one bit per value longer than that of a
+    /// two-pass Huffman code, while that of FGK can be twice as long.
+    Vitter,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    /// The leaf of a value.
+    Leaf(usize),
+    /// The leaf standing for all values not seen yet.
+    Nyt,
+    /// An internal node, with the positions of its children for bits zero
+    /// and one.
+    Internal(usize, usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    weight: u64,
+    /// The position of the parent; meaningless for the root.
+    parent: usize,
+    kind: Kind,
+}
+
+/// The code tree, kept in step by the encoder and the decoder.
+///
+/// Nodes are stored in order of decreasing weight, which is the order of
+/// the sibling property: the two children of every node are adjacent, and
+/// the root comes first. Nodes only move by swapping or rotating subtrees,
+/// so the `parent` of a position stays put while the subtree hanging there
+/// moves.
+#[derive(Clone, Debug)]
+struct Model {
+    algorithm: Algorithm,
+    nodes: Vec<Node>,
+    /// The position of the leaf of every value seen so far.
+    leaves: Vec<Option<usize>>,
+    nyt: usize,
+    /// The width of values written after the NYT code.
+    value_bits: usize,
+}
+
+impl Model {
+    fn new(alphabet_size: usize, algorithm: Algorithm) -> Model {
+        assert!(alphabet_size > 0, "alphabet must not be empty");
+        let value_bits = (usize::BITS - (alphabet_size - 1).leading_zeros()) as usize;
+        Model {
+            algorithm,
+            nodes: vec![Node {
+                weight: 0,
+                parent: ROOT,
+                kind: Kind::Nyt,
+            }],
+            leaves: vec![None; alphabet_size],
+            nyt: ROOT,
+            value_bits,
+        }
+    }
+
+    /// Appends the code of the node at `pos` to `path`, last bit first.
+    fn path(&self, mut pos: usize, path: &mut Vec<bool>) {
+        path.clear();
+        while pos != ROOT {
+            let parent = self.nodes[pos].parent;
+            match self.nodes[parent].kind {
+                Kind::Internal(_, one) => path.push(one == pos),
+                _ => unreachable!(),
+            }
+            pos = parent;
+        }
+    }
+
+    /// Counts one more occurrence of `value`, adding a leaf for it if it is
+    /// new, and restores the invariants of the algorithm.
+    fn update(&mut self, value: usize) {
+        match self.algorithm {
+            Algorithm::Fgk => self.update_fgk(value),
+            Algorithm::Vitter => self.update_vitter(value),
+        }
+    }
+
+    fn update_fgk(&mut self, value: usize) {
+        let mut pos = match self.leaves[value] {
+            Some(pos) => pos,
+            None => self.split_nyt(value),
+        };
+        loop {
+            let weight = self.nodes[pos].weight;
+            let mut leader = pos;
+            while leader > ROOT && self.nodes[leader - 1].weight == weight {
+                leader -= 1;
+            }
+            if leader != pos && leader != self.nodes[pos].parent {
+                self.swap(pos, leader);
+                pos = leader;
+            }
+            self.nodes[pos].weight += 1;
+            if pos == ROOT {
+                break;
+            }
+            pos = self.nodes[pos].parent;
+        }
+    }
+
+    fn update_vitter(&mut self, value: usize) {
+        let mut leaf_to_increment = None;
+        let mut pos = match self.leaves[value] {
+            None => {
+                let leaf = self.split_nyt(value);
+                leaf_to_increment = Some(leaf);
+                self.nodes[leaf].parent
+            }
+            Some(mut pos) => {
+                let weight = self.nodes[pos].weight;
+                let mut leader = pos;
+                while leader > ROOT && self.is_leaf_of_weight(leader - 1, weight) {
+                    leader -= 1;
+                }
+                if leader != pos {
+                    self.swap(pos, leader);
+                    pos = leader;
+                }
+                // the parent of a sibling of the NYT leaf has the same weight
+                // and goes first, lest the leaf slide past it
+                let parent = self.nodes[pos].parent;
+                match self.nodes[parent].kind {
+                    Kind::Internal(zero, one) if zero == self.nyt || one == self.nyt => {
+                        leaf_to_increment = Some(pos);
+                        parent
+                    }
+                    _ => pos,
+                }
+            }
+        };
+        loop {
+            pos = match self.slide_and_increment(pos) {
+                Some(parent) => parent,
+                None => break,
+            }
+        }
+        if let Some(leaf) = leaf_to_increment {
+            self.slide_and_increment(leaf);
+        }
+    }
+
+    fn is_leaf_of_weight(&self, pos: usize, weight: u64) -> bool {
+        let node = &self.nodes[pos];
+        node.weight == weight && !matches!(node.kind, Kind::Internal(..))
+    }
+
+    /// Moves the node at `pos`, the first of its block, past the block that
+    /// comes before it once its weight is incremented, increments it, and
+    /// returns the position of the next node to increment, if any.
+    ///
+    /// Blocks are ordered by weight and, for equal weights, internal nodes
+    /// come before leaves. A leaf of weight `w` thus slides past the
+    /// internal nodes of weight `w`, and an internal node of weight `w` past
+    /// the leaves of weight `w + 1`.
+    fn slide_and_increment(&mut self, pos: usize) -> Option<usize> {
+        let weight = self.nodes[pos].weight;
+        let is_leaf = !matches!(self.nodes[pos].kind, Kind::Internal(..));
+        let (passed_weight, passed_leaves) = if is_leaf {
+            (weight, false)
+        } else {
+            (weight + 1, true)
+        };
+        let mut first = pos;
+        while first > ROOT {
+            let node = &self.nodes[first - 1];
+            let leaf = !matches!(node.kind, Kind::Internal(..));
+            if node.weight != passed_weight || leaf != passed_leaves {
+                break;
+            }
+            first -= 1;
+        }
+        let former_parent = self.nodes[pos].parent;
+        if first < pos {
+            let (weight, kind) = (self.nodes[pos].weight, self.nodes[pos].kind);
+            for i in (first..pos).rev() {
+                self.nodes[i + 1].weight = self.nodes[i].weight;
+                self.nodes[i + 1].kind = self.nodes[i].kind;
+            }
+            self.nodes[first].weight = weight;
+            self.nodes[first].kind = kind;
+            for i in first..=pos {
+                self.attach(i);
+            }
+        }
+        self.nodes[first].weight += 1;
+        if first == ROOT {
+            None
+        } else if is_leaf {
+            Some(self.nodes[first].parent)
+        } else {
+            Some(former_parent)
+        }
+    }
+
+    /// Replaces the NYT leaf by an internal node with a new NYT leaf and a
+    /// leaf for `value`, both of weight zero, and returns the latter.
+    fn split_nyt(&mut self, value: usize) -> usize {
+        let parent = self.nyt;
+        let leaf = self.nodes.len();
+        let nyt = leaf + 1;
+        self.nodes[parent].kind = Kind::Internal(nyt, leaf);
+        for &kind in [Kind::Leaf(value), Kind::Nyt].iter() {
+            self.nodes.push(Node {
+                weight: 0,
+                parent,
+                kind,
+            });
+        }
+        self.leaves[value] = Some(leaf);
+        self.nyt = nyt;
+        leaf
+    }
+
+    /// Exchanges the subtrees at positions `a` and `b`, neither of which is
+    /// an ancestor of the other.
+    fn swap(&mut self, a: usize, b: usize) {
+        let (weight, kind) = (self.nodes[a].weight, self.nodes[a].kind);
+        self.nodes[a].weight = self.nodes[b].weight;
+        self.nodes[a].kind = self.nodes[b].kind;
+        self.nodes[b].weight = weight;
+        self.nodes[b].kind = kind;
+        self.attach(a);
+        self.attach(b);
+    }
+
+    /// Points whatever refers to the node now at `pos` back to it.
+    fn attach(&mut self, pos: usize) {
+        match self.nodes[pos].kind {
+            Kind::Leaf(value) => self.leaves[value] = Some(pos),
+            Kind::Nyt => self.nyt = pos,
+            Kind::Internal(zero, one) => {
+                self.nodes[zero].parent = pos;
+                self.nodes[one].parent = pos;
+            }
+        }
+    }
+}
+
+/// An encoder of values from `0..alphabet_size` with an adaptive Huffman
+/// code.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::adaptive_huffman::{AdaptiveDecoder, AdaptiveEncoder};
+/// # use rust_compress::bit_vec::BitVec;
+/// let data = b"abracadabra";
+/// let mut encoder = AdaptiveEncoder::new(256);
+/// let mut bits = BitVec::new();
+/// for &byte in data.iter() {
+///     encoder.encode(byte as usize, &mut bits).unwrap();
+/// }
+/// let mut decoder = AdaptiveDecoder::new(256);
+/// let mut input = bits.iter();
+/// for &byte in data.iter() {
+///     assert_eq!(decoder.decode(&mut input).unwrap(), byte as usize);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AdaptiveEncoder {
+    model: Model,
+    path: Vec<bool>,
+}
+
+impl AdaptiveEncoder {
+    /// Creates an encoder of values less than `alphabet_size` that updates
+    /// its code with the FGK algorithm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alphabet_size` is zero.
+    pub fn new(alphabet_size: usize) -> AdaptiveEncoder {
+        AdaptiveEncoder::with_algorithm(alphabet_size, Algorithm::Fgk)
+    }
+
+    /// Creates an encoder of values less than `alphabet_size` that updates its
+    /// code with `algorithm`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alphabet_size` is zero.
+    pub fn with_algorithm(alphabet_size: usize, algorithm: Algorithm) -> AdaptiveEncoder {
+        AdaptiveEncoder {
+            model: Model::new(alphabet_size, algorithm),
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns the update rule of the code.
+    pub fn algorithm(&self) -> Algorithm {
+        self.model.algorithm
+    }
+
+    /// Returns the number of distinct values.
+    pub fn alphabet_size(&self) -> usize {
+        self.model.leaves.len()
+    }
+
+    /// Writes the code of `value` and counts it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not less than the alphabet size.
+    pub fn encode<S: BitSink>(&mut self, value: usize, sink: &mut S) -> io::Result<()> {
+        assert!(
+            value < self.alphabet_size(),
+            "value {} out of range for alphabet of size {}",
+            value,
+            self.alphabet_size()
+        );
+        let leaf = self.model.leaves[value];
+        self.model
+            .path(leaf.unwrap_or(self.model.nyt), &mut self.path);
+        for &bit in self.path.iter().rev() {
+            sink.write_bit(bit)?;
+        }
+        if leaf.is_none() {
+            sink.write_bits(value as u64, self.model.value_bits)?;
+        }
+        self.model.update(value);
+        Ok(())
+    }
+}
+
+/// A decoder of values written by an [`AdaptiveEncoder`] of the same
+/// alphabet size.
+#[derive(Clone, Debug)]
+pub struct AdaptiveDecoder {
+    model: Model,
+}
+
+impl AdaptiveDecoder {
+    /// Creates a decoder of values less than `alphabet_size` that updates
+    /// its code with the FGK algorithm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alphabet_size` is zero.
+    pub fn new(alphabet_size: usize) -> AdaptiveDecoder {
+        AdaptiveDecoder::with_algorithm(alphabet_size, Algorithm::Fgk)
+    }
+
+    /// Creates a decoder of values less than `alphabet_size` that updates its
+    /// code with `algorithm`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alphabet_size` is zero.
+    pub fn with_algorithm(alphabet_size: usize, algorithm: Algorithm) -> AdaptiveDecoder {
+        AdaptiveDecoder {
+            model: Model::new(alphabet_size, algorithm),
+        }
+    }
+
+    /// Returns the update rule of the code.
+    pub fn algorithm(&self) -> Algorithm {
+        self.model.algorithm
+    }
+
+    /// Returns the number of distinct values.
+    pub fn alphabet_size(&self) -> usize {
+        self.model.leaves.len()
+    }
+
+    /// Reads a value and counts it.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the input ends first, and
+    /// with `ErrorKind::InvalidData` if it does not encode a new value where
+    /// one is expected.
+    pub fn decode<S: BitSource>(&mut self, source: &mut S) -> io::Result<usize> {
+        let mut pos = ROOT;
+        let value = loop {
+            match self.model.nodes[pos].kind {
+                Kind::Internal(zero, one) => pos = if source.read_bit()? { one } else { zero },
+                Kind::Leaf(value) => break value,
+                Kind::Nyt => {
+                    let value = source.read_bits(self.model.value_bits)? as usize;
+                    if value >= self.alphabet_size() || self.model.leaves[value].is_some() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "invalid new value in adaptive Huffman code",
+                        ));
+                    }
+                    break value;
+                }
+            }
+        };
+        self.model.update(value);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_io::{BitReader, BitWriter};
+    use crate::bit_vec::BitVec;
+    use crate::huffman::HuffmanTree;
+
+    const ALGORITHMS: [Algorithm; 2] = [Algorithm::Fgk, Algorithm::Vitter];
+
+    /// Checks the sibling property, the links of the tree and, for Vitter's
+    /// algorithm, the order of leaves and internal nodes.
+    fn check_model(model: &Model) {
+        let is_leaf = |node: &Node| !matches!(node.kind, Kind::Internal(..));
+        for (pos, node) in model.nodes.iter().enumerate() {
+            if pos > ROOT {
+                let prev = &model.nodes[pos - 1];
+                assert!(prev.weight >= node.weight);
+                if model.algorithm == Algorithm::Vitter && prev.weight == node.weight {
+                    assert!(!is_leaf(prev) || is_leaf(node));
+                }
+            }
+            match node.kind {
+                Kind::Leaf(value) => assert_eq!(model.leaves[value], Some(pos)),
+                Kind::Nyt => assert_eq!(model.nyt, pos),
+                Kind::Internal(zero, one) => {
+                    assert_eq!(zero.max(one), zero.min(one) + 1);
+                    assert_eq!(model.nodes[zero].parent, pos);
+                    assert_eq!(model.nodes[one].parent, pos);
+                    let children = model.nodes[zero].weight + model.nodes[one].weight;
+                    assert_eq!(node.weight, children);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for &algorithm in ALGORITHMS.iter() {
+            check_round_trip(algorithm);
+        }
+    }
+
+    fn check_round_trip(algorithm: Algorithm) {
+        let values: Vec<usize> = (0..2000u64)
+            .map(|i| (((i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) % 200) >> (i % 4)) as usize)
+            .collect();
+        let mut encoder = AdaptiveEncoder::with_algorithm(200, algorithm);
+        let mut writer = BitWriter::new(Vec::new());
+        for &value in values.iter() {
+            encoder.encode(value, &mut writer).unwrap();
+            check_model(&encoder.model);
+        }
+        let bytes = writer.finish().unwrap();
+        let mut decoder = AdaptiveDecoder::with_algorithm(200, algorithm);
+        let mut reader = BitReader::new(&bytes[..]);
+        for &value in values.iter() {
+            assert_eq!(decoder.decode(&mut reader).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn huffman_lengths() {
+        for &algorithm in ALGORITHMS.iter() {
+            check_huffman_lengths(algorithm);
+        }
+    }
+
+    fn check_huffman_lengths(algorithm: Algorithm) {
+        let mut encoder = AdaptiveEncoder::with_algorithm(8, algorithm);
+        let mut counts = [0; 8];
+        let mut bits = BitVec::new();
+        for i in 0..500 {
+            let value = (i * i + 3 * i + i / 7) % 8;
+            encoder.encode(value, &mut bits).unwrap();
+            counts[value] += 1;
+        }
+        // the leaves sit at the depths of some Huffman code of the counts,
+        // with the NYT leaf as an extra value of count zero
+        let model = &encoder.model;
+        let mut path = Vec::new();
+        let seen: Vec<usize> = (0..8).filter(|&value| counts[value] > 0).collect();
+        let cost: u128 = seen
+            .iter()
+            .map(|&value| {
+                model.path(model.leaves[value].unwrap(), &mut path);
+                counts[value] * path.len() as u128
+            })
+            .sum();
+        let mut with_nyt: Vec<u128> = seen.iter().map(|&value| counts[value]).collect();
+        with_nyt.push(0);
+        let lengths = HuffmanTree::new(&with_nyt).to_lengths();
+        let optimal: u128 = with_nyt
+            .iter()
+            .zip(lengths)
+            .map(|(&c, l)| c * l as u128)
+            .sum();
+        assert_eq!(cost, optimal);
+    }
+
+    #[test]
+    fn vitter_is_shallowest() {
+        // both trees are Huffman trees of the same counts, and Vitter's has
+        // the least height and sum of leaf depths among those
+        let depths = |model: &Model| {
+            let mut path = Vec::new();
+            let mut sum = 0;
+            let mut height = 0;
+            for (pos, node) in model.nodes.iter().enumerate() {
+                if !matches!(node.kind, Kind::Internal(..)) {
+                    model.path(pos, &mut path);
+                    sum += path.len();
+                    height = height.max(path.len());
+                }
+            }
+            (sum, height)
+        };
+        let mut fgk = AdaptiveEncoder::new(30);
+        let mut vitter = AdaptiveEncoder::with_algorithm(30, Algorithm::Vitter);
+        let mut bits = BitVec::new();
+        for i in 0..3000u64 {
+            let value = ((i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 59) as usize) % 30;
+            fgk.encode(value, &mut bits).unwrap();
+            vitter.encode(value, &mut bits).unwrap();
+            let (fgk_sum, fgk_height) = depths(&fgk.model);
+            let (vitter_sum, vitter_height) = depths(&vitter.model);
+            assert!(vitter_sum <= fgk_sum);
+            assert!(vitter_height <= fgk_height);
+        }
+    }
+
+    #[test]
+    fn invalid_input() {
+        let mut decoder = AdaptiveDecoder::new(5);
+        let mut bits = BitVec::new();
+        bits.push_bits(6, 3);
+        let err = decoder.decode(&mut bits.iter()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = decoder.decode(&mut BitVec::new().iter()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let mut decoder = AdaptiveDecoder::new(1);
+        assert_eq!(decoder.decode(&mut BitVec::new().iter()).unwrap(), 0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Statistics of byte strings, to tell how well they compress and which
+//! codec suits them.
+//!
+//! The empirical entropy of order `k` is the cost in bits per byte of
+//! coding every byte with the frequencies it has after the `k` bytes before
+//! it, which the whole data gives. It bounds what a static model of order
+//! `k` achieves, not counting the cost of sending the model, so it is a
+//! lower bound to compare codecs with rather than a size they reach.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rust_compress::analysis::{self, Codec};
+//! let data = b"abababababababababababababababab";
+//! assert_eq!(analysis::entropy(data), 1.0);
+//! assert_eq!(analysis::conditional_entropy(data, 1), 0.0);
+//! let (codec, size) = analysis::best_codec(data);
+//! assert_eq!(codec.compress(data).len(), size);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{cm, huffman, ppm};
+
+/// Returns the number of occurrences of every byte value in `data`.
+pub fn histogram(data: &[u8]) -> [u64; 256] {
+    let mut counts = [0; 256];
+    for &byte in data.iter() {
+        counts[byte as usize] += 1;
+    }
+    counts
+}
+
+/// Returns the entropy in bits of the distribution of `counts`, or 0 if
+/// there are none.
+fn entropy_of(counts: impl Iterator<Item = u64> + Clone) -> f64 {
+    let total: u64 = counts.clone().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    counts
+        .filter(|&count| count > 0)
+        .map(|count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Returns the empirical entropy of order 0 of `data` in bits per byte,
+/// between 0 and 8, or 0 if it is empty.
+pub fn entropy(data: &[u8]) -> f64 {
+    entropy_of(histogram(data).iter().copied())
+}
+
+/// Returns the empirical entropy of order `k` of `data` in bits per byte:
+/// the entropy of every byte given the `k` bytes before it. The first `k`
+/// bytes, which have no such context, are left out.
+///
+/// It is 0 if `data` is not longer than `k`, and never more than the
+/// entropy of a lower order.
+///
+/// # Panics
+///
+/// Panics if `k` is greater than 8.
+pub fn conditional_entropy(data: &[u8], k: usize) -> f64 {
+    assert!(k <= 8, "order {} greater than 8", k);
+    if data.len() <= k {
+        return 0.0;
+    }
+    if k == 0 {
+        return entropy(data);
+    }
+    // the counts of the contexts and of the bytes after them, stored only
+    // for those that occur, as most contexts see few distinct bytes
+    let mut contexts: HashMap<&[u8], u64> = HashMap::new();
+    let mut pairs: HashMap<(&[u8], u8), u64> = HashMap::new();
+    for window in data.windows(k + 1) {
+        *contexts.entry(&window[..k]).or_insert(0) += 1;
+        *pairs.entry((&window[..k], window[k])).or_insert(0) += 1;
+    }
+    // the entropy of a context of n occurrences, times n, is n log n less
+    // m log m for the count m of every byte after it
+    let n_log_n = |n: u64| n as f64 * (n as f64).log2();
+    let bits = contexts.values().map(|&n| n_log_n(n)).sum::<f64>()
+        - pairs.values().map(|&m| n_log_n(m)).sum::<f64>();
+    // which may round to a little below zero
+    bits.max(0.0) / (data.len() - k) as f64
+}
+
+/// The byte string compressors of the crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// `huffman::compress`, of order 0.
+    Huffman,
+    /// `ppm::compress`, of order 4.
+    Ppm,
+    /// `cm::compress`.
+    ContextMixing,
+}
+
+impl Codec {
+    /// All the codecs, from the fastest.
+    pub const ALL: [Codec; 3] = [Codec::Huffman, Codec::Ppm, Codec::ContextMixing];
+
+    /// Compresses `data` with the codec.
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Codec::Huffman => huffman::compress(data),
+            Codec::Ppm => ppm::compress(data),
+            Codec::ContextMixing => cm::compress(data),
+        }
+    }
+}
+
+/// Returns the compressed size of `sample` with every codec, in the order of
+/// `Codec::ALL`.
+///
+/// The sizes are exact for the sample, and estimate those of data like it.
+/// The adaptive codecs learn as they go, so they do relatively better on
+/// more of the data than on a small sample.
+pub fn estimate_sizes(sample: &[u8]) -> Vec<(Codec, usize)> {
+    Codec::ALL
+        .iter()
+        .map(|&codec| (codec, codec.compress(sample).len()))
+        .collect()
+}
+
+/// Returns the codec that compresses `sample` best, with its compressed
+/// size. Of codecs of the same size, the fastest is chosen.
+pub fn best_codec(sample: &[u8]) -> (Codec, usize) {
+    estimate_sizes(sample)
+        .into_iter()
+        .min_by_key(|&(_, size)| size)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histograms_and_entropy() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(histogram(&data), [1; 256]);
+        assert_eq!(entropy(&data), 8.0);
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[7; 100]), 0.0);
+        let counts = histogram(b"aab");
+        assert_eq!((counts[b'a' as usize], counts[b'b' as usize]), (2, 1));
+        let h = entropy(b"aab");
+        assert!((h - 0.918_295_834).abs() < 1e-9, "{}", h);
+    }
+
+    #[test]
+    fn conditional() {
+        // a random walk over 16 values: 4 bits of order 0, and the 1 bit of
+        // the step once the byte before is known
+        let mut byte = 0u8;
+        let data: Vec<u8> = (0..20_000u64)
+            .map(|i| {
+                let x = i.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                if (x ^ (x >> 29)).wrapping_mul(0xbf58_476d_1ce4_e5b9) >> 63 == 1 {
+                    byte = (byte + 1) % 16;
+                } else {
+                    byte = (byte + 15) % 16;
+                }
+                byte
+            })
+            .collect();
+        let h0 = conditional_entropy(&data, 0);
+        assert_eq!(h0, entropy(&data));
+        assert!((h0 - 4.0).abs() < 0.01, "{}", h0);
+        let h1 = conditional_entropy(&data, 1);
+        assert!((h1 - 1.0).abs() < 0.01, "{}", h1);
+        let h2 = conditional_entropy(&data, 2);
+        assert!(h2 <= h1 + 1e-9);
+        assert_eq!(conditional_entropy(b"abc", 3), 0.0);
+        assert_eq!(conditional_entropy(b"abc", 2), 0.0);
+    }
+
+    #[test]
+    fn codecs() {
+        let text = b"it was the best of times, it was the worst of times, ".repeat(20);
+        let sizes = estimate_sizes(&text);
+        assert_eq!(sizes.len(), Codec::ALL.len());
+        for &(codec, size) in sizes.iter() {
+            assert_eq!(codec.compress(&text).len(), size);
+        }
+        let (codec, size) = best_codec(&text);
+        assert_ne!(codec, Codec::Huffman);
+        assert!(size < text.len() / 10);
+        // the table of code lengths costs more than a small sample
+        let (codec, _) = best_codec(&[0; 10]);
+        assert_ne!(codec, Codec::Huffman);
+    }
+}
@@ -0,0 +1,478 @@
+//! Arithmetic coding of values with a static model, a table of the
+//! frequency of every value.
+//!
+//! Where a Huffman code spends a whole number of bits, at least one, on
+//! every value, an arithmetic coder narrows an interval by the probability
+//! of each value in turn and spends about `-log2(p)` bits on it, a fraction
+//! of a bit for a very frequent value.
+//!
+//! The coder keeps the interval in 32-bit integers, which are renormalized
+//! bit by bit as in the coder of Witten, Neal and Cleary, and writes and
+//! reads the bits through a [`BitSink`] or a [`BitSource`]. It codes the
+//! values of any [`Model`], of which a [`CumulativeFrequencies`] table is
+//! the static one.
+//!
+//! [`BinaryEncoder`] and [`BinaryDecoder`] code single bits instead, each
+//! with its own probability, as adaptive [`BitModel`]s of contexts or
+//! context-mixing models give them. A binary coder writes whole bytes.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rust_compress::arithmetic::{ArithmeticDecoder, ArithmeticEncoder, CumulativeFrequencies};
+//! # use rust_compress::bit_vec::BitVec;
+//! let table = CumulativeFrequencies::new(&[60, 2, 2]);
+//! let values = [0; 100];
+//! let mut encoder = ArithmeticEncoder::new(BitVec::new());
+//! for &value in values.iter() {
+//!     encoder.encode(&table, value).unwrap();
+//! }
+//! let bits = encoder.finish().unwrap();
+//! // far less than the bit per value of a Huffman code
+//! assert!(bits.len() < 15);
+//! let mut decoder = ArithmeticDecoder::new(bits.iter()).unwrap();
+//! for &value in values.iter() {
+//!     assert_eq!(decoder.decode(&table).unwrap(), value);
+//! }
+//! ```
+
+use std::io;
+
+use crate::bit_io::{BitSink, BitSource};
+use crate::model::Model;
+
+mod binary;
+
+pub use self::binary::{BinaryDecoder, BinaryEncoder, BitModel, PROBABILITY_BITS};
+
+/// The largest total frequency of a table.
+pub const MAX_TOTAL: u32 = 1 << 16;
+
+const TOP: u64 = (1 << 32) - 1;
+const HALF: u64 = 1 << 31;
+const FIRST_QUARTER: u64 = 1 << 30;
+const THIRD_QUARTER: u64 = 3 << 30;
+
+/// The number of bits past the end of the input that a decoder reads as
+/// zeros, more than the 32 bits it reads ahead of the code of the encoder.
+const SLACK_BITS: u32 = 64;
+
+/// The frequencies of the values `0..n`, with their cumulative sums, which
+/// split the interval of the coder.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::arithmetic::CumulativeFrequencies;
+/// let table = CumulativeFrequencies::new(&[3, 0, 5]);
+/// assert_eq!(table.total(), 8);
+/// assert_eq!(table.range(2), (3, 8));
+/// assert_eq!(table.value_at(4), 2);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CumulativeFrequencies {
+    /// The sum of the frequencies of the values before each one, and the
+    /// total last.
+    cum: Vec<u32>,
+}
+
+impl CumulativeFrequencies {
+    /// Builds the table of the given frequencies, of which values of zero
+    /// cannot be coded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total frequency is zero or greater than `MAX_TOTAL`.
+    pub fn new(frequencies: &[u32]) -> CumulativeFrequencies {
+        let mut cum = Vec::with_capacity(frequencies.len() + 1);
+        let mut total: u32 = 0;
+        cum.push(0);
+        for &frequency in frequencies.iter() {
+            total = total.saturating_add(frequency);
+            cum.push(total);
+        }
+        assert!(
+            total > 0 && total <= MAX_TOTAL,
+            "total frequency {} out of range",
+            total
+        );
+        CumulativeFrequencies { cum }
+    }
+
+    /// Builds a table of frequencies proportional to `counts`, scaled down
+    /// to fit in `MAX_TOTAL` if needed. Values that occur keep a frequency
+    /// of at least one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all counts are zero, or if there are more than `MAX_TOTAL`
+    /// counts.
+    pub fn from_counts(counts: &[u64]) -> CumulativeFrequencies {
+        assert!(
+            counts.len() <= MAX_TOTAL as usize,
+            "{} values do not fit in a table",
+            counts.len()
+        );
+        let total: u128 = counts.iter().map(|&count| count as u128).sum();
+        if total <= MAX_TOTAL as u128 {
+            let frequencies: Vec<u32> = counts.iter().map(|&count| count as u32).collect();
+            return CumulativeFrequencies::new(&frequencies);
+        }
+        // one is added to every count that occurs, which the rest leaves
+        // room for
+        let room = (MAX_TOTAL as usize - counts.len()) as u128;
+        let frequencies: Vec<u32> = counts
+            .iter()
+            .map(|&count| match count {
+                0 => 0,
+                _ => (count as u128 * room / total) as u32 + 1,
+            })
+            .collect();
+        CumulativeFrequencies::new(&frequencies)
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.cum.len() - 1
+    }
+
+    /// Returns `true` if there are no values, which never happens.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total frequency.
+    pub fn total(&self) -> u32 {
+        self.cum[self.len()]
+    }
+
+    /// Returns the frequency of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range.
+    pub fn frequency(&self, value: usize) -> u32 {
+        let (low, high) = self.range(value);
+        high - low
+    }
+
+    /// Returns the range of cumulative frequencies of `value`, from the sum
+    /// of those of the values before it up to the sum including it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range.
+    pub fn range(&self, value: usize) -> (u32, u32) {
+        assert!(
+            value < self.len(),
+            "value {} out of range for alphabet of size {}",
+            value,
+            self.len()
+        );
+        (self.cum[value], self.cum[value + 1])
+    }
+
+    /// Returns the value whose range holds the cumulative frequency `cum`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cum` is not less than the total frequency.
+    pub fn value_at(&self, cum: u32) -> usize {
+        assert!(
+            cum < self.total(),
+            "cumulative frequency {} out of range",
+            cum
+        );
+        self.cum.partition_point(|&c| c <= cum) - 1
+    }
+}
+
+/// An arithmetic encoder writing to a [`BitSink`], `S` being the sink
+/// itself or a mutable reference to it.
+///
+/// The last bits are only written by `finish`.
+#[derive(Debug)]
+pub struct ArithmeticEncoder<S: BitSink> {
+    sink: S,
+    low: u64,
+    high: u64,
+    /// The number of opposite bits to write after the next bit, from
+    /// intervals straddling the middle.
+    pending: u64,
+}
+
+impl<S: BitSink> ArithmeticEncoder<S> {
+    pub fn new(sink: S) -> ArithmeticEncoder<S> {
+        ArithmeticEncoder {
+            sink,
+            low: 0,
+            high: TOP,
+            pending: 0,
+        }
+    }
+
+    /// Encodes `value` with the frequencies of `table`, leaving it as it
+    /// is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range or has a frequency of zero.
+    pub fn encode<M: Model + ?Sized>(&mut self, table: &M, value: usize) -> io::Result<()> {
+        let (cum_low, cum_high) = table.range(value);
+        assert!(
+            cum_low < cum_high,
+            "value {} has a frequency of zero",
+            value
+        );
+        let total = table.total() as u64;
+        let range = self.high - self.low + 1;
+        self.high = self.low + range * cum_high as u64 / total - 1;
+        self.low += range * cum_low as u64 / total;
+        loop {
+            if self.high < HALF {
+                self.write(false)?;
+            } else if self.low >= HALF {
+                self.write(true)?;
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= FIRST_QUARTER && self.high < THIRD_QUARTER {
+                self.pending += 1;
+                self.low -= FIRST_QUARTER;
+                self.high -= FIRST_QUARTER;
+            } else {
+                return Ok(());
+            }
+            self.low *= 2;
+            self.high = 2 * self.high + 1;
+        }
+    }
+
+    /// Encodes `value` with the frequencies of `model`, then updates it.
+    pub fn encode_update<M: Model + ?Sized>(
+        &mut self,
+        model: &mut M,
+        value: usize,
+    ) -> io::Result<()> {
+        self.encode(model, value)?;
+        model.update(value);
+        Ok(())
+    }
+
+    /// Writes the bits that pick out the final interval, and returns the
+    /// sink.
+    ///
+    /// The decoder reads bits past the end as zeros, so they are left out.
+    pub fn finish(mut self) -> io::Result<S> {
+        self.pending += 1;
+        let bit = self.low >= FIRST_QUARTER;
+        self.write(bit)?;
+        Ok(self.sink)
+    }
+
+    /// Writes `bit` and the pending bits after it.
+    fn write(&mut self, bit: bool) -> io::Result<()> {
+        self.sink.write_bit(bit)?;
+        for _ in 0..self.pending {
+            self.sink.write_bit(!bit)?;
+        }
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+/// An arithmetic decoder reading from a [`BitSource`].
+///
+/// Bits past the end of the input read as zeros, so the decoder cannot
+/// tell where the values end: it must be told their number, or decode an
+/// end-of-stream value. Past 64 such bits, more than the code of any
+/// encoder needs, it fails with `ErrorKind::UnexpectedEof` instead, so that
+/// a number of values too large for the input fails soon.
+#[derive(Debug)]
+pub struct ArithmeticDecoder<S: BitSource> {
+    source: S,
+    low: u64,
+    high: u64,
+    /// The first 32 bits of the input not yet shifted out.
+    value: u64,
+    /// The number of bits read past the end of the input.
+    past_end: u32,
+}
+
+impl<S: BitSource> ArithmeticDecoder<S> {
+    /// Creates a decoder, reading the first 32 bits of the input.
+    pub fn new(source: S) -> io::Result<ArithmeticDecoder<S>> {
+        let mut decoder = ArithmeticDecoder {
+            source,
+            low: 0,
+            high: TOP,
+            value: 0,
+            past_end: 0,
+        };
+        for _ in 0..32 {
+            decoder.value = 2 * decoder.value + decoder.read()? as u64;
+        }
+        Ok(decoder)
+    }
+
+    /// Decodes a value with the frequencies of `table`, which must be the
+    /// table it was encoded with.
+    pub fn decode<M: Model + ?Sized>(&mut self, table: &M) -> io::Result<usize> {
+        let total = table.total() as u64;
+        let range = self.high - self.low + 1;
+        let cum = ((self.value - self.low + 1) * total - 1) / range;
+        let value = table.value_at(cum as u32);
+        let (cum_low, cum_high) = table.range(value);
+        self.high = self.low + range * cum_high as u64 / total - 1;
+        self.low += range * cum_low as u64 / total;
+        loop {
+            if self.high < HALF {
+            } else if self.low >= HALF {
+                self.value -= HALF;
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= FIRST_QUARTER && self.high < THIRD_QUARTER {
+                self.value -= FIRST_QUARTER;
+                self.low -= FIRST_QUARTER;
+                self.high -= FIRST_QUARTER;
+            } else {
+                return Ok(value);
+            }
+            self.low *= 2;
+            self.high = 2 * self.high + 1;
+            self.value = 2 * self.value + self.read()? as u64;
+        }
+    }
+
+    /// Decodes a value with the frequencies of `model`, then updates it.
+    pub fn decode_update<M: Model + ?Sized>(&mut self, model: &mut M) -> io::Result<usize> {
+        let value = self.decode(model)?;
+        model.update(value);
+        Ok(value)
+    }
+
+    /// Returns the source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn read(&mut self) -> io::Result<bool> {
+        match self.source.read_bit() {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && self.past_end < SLACK_BITS => {
+                self.past_end += 1;
+                Ok(false)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_io::{BitReader, BitWriter};
+    use crate::bit_vec::BitVec;
+
+    fn skewed(n: usize) -> Vec<usize> {
+        (0..n as u64)
+            .map(|i| match i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 54 {
+                0..=999 => 0,
+                1000..=1015 => 1,
+                1016..=1019 => 2,
+                _ => 3,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn table() {
+        let table = CumulativeFrequencies::new(&[0, 3, 0, 0, 5, 0]);
+        assert_eq!(table.len(), 6);
+        assert_eq!(table.total(), 8);
+        assert_eq!(table.frequency(0), 0);
+        assert_eq!(table.range(4), (3, 8));
+        let values: Vec<usize> = (0..8).map(|cum| table.value_at(cum)).collect();
+        assert_eq!(values, [1, 1, 1, 4, 4, 4, 4, 4]);
+
+        let table = CumulativeFrequencies::from_counts(&[1 << 40, 0, 1, 1 << 20]);
+        assert!(table.total() <= MAX_TOTAL);
+        assert_eq!(table.frequency(1), 0);
+        assert_eq!(table.frequency(2), 1);
+        assert!(table.frequency(0) > 65_000);
+        let table = CumulativeFrequencies::from_counts(&[5, 7]);
+        assert_eq!(table, CumulativeFrequencies::new(&[5, 7]));
+    }
+
+    #[test]
+    fn round_trip() {
+        let values = skewed(10_000);
+        let mut counts = [0; 4];
+        for &value in values.iter() {
+            counts[value] += 1;
+        }
+        let table = CumulativeFrequencies::from_counts(&counts);
+        let mut encoder = ArithmeticEncoder::new(BitVec::new());
+        for &value in values.iter() {
+            encoder.encode(&table, value).unwrap();
+        }
+        let bits = encoder.finish().unwrap();
+        // within a few bits of the entropy of the counts
+        let n = values.len() as f64;
+        let entropy: f64 = counts
+            .iter()
+            .filter(|&&c| c > 0)
+            .map(|&c| -(c as f64) * (c as f64 / n).log2())
+            .sum();
+        assert!((bits.len() as f64) < entropy + 32.0);
+        assert!(bits.len() < values.len() / 4);
+
+        let mut decoder = ArithmeticDecoder::new(bits.iter()).unwrap();
+        for &value in values.iter() {
+            assert_eq!(decoder.decode(&table).unwrap(), value);
+        }
+        // the values of a bit each run out of input soon
+        let half = CumulativeFrequencies::new(&[1, 1]);
+        let err = (0..100)
+            .try_for_each(|_| decoder.decode(&half).map(drop))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn tables_and_streams() {
+        // a table per value, and a stream of bytes
+        let tables = [
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            CumulativeFrequencies::new(&[1; 300]),
+            CumulativeFrequencies::new(&[7]),
+        ];
+        let values: Vec<(usize, usize)> = (0..3000)
+            .map(|i| {
+                let table = i % 7 % 4;
+                let value = match table {
+                    1 => (i % 50 == 1) as usize,
+                    _ => (i * i + 3) % tables[table].len(),
+                };
+                (table, value)
+            })
+            .collect();
+        let mut encoder = ArithmeticEncoder::new(BitWriter::new(Vec::new()));
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        let bytes = encoder.finish().unwrap().finish().unwrap();
+        let mut decoder = ArithmeticDecoder::new(BitReader::new(&bytes[..])).unwrap();
+        for &(table, value) in values.iter() {
+            assert_eq!(decoder.decode(&tables[table]).unwrap(), value);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "value 1 has a frequency of zero")]
+    fn zero_frequency() {
+        let table = CumulativeFrequencies::new(&[1, 0, 1]);
+        ArithmeticEncoder::new(BitVec::new())
+            .encode(&table, 1)
+            .unwrap();
+    }
+}
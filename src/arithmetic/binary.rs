@@ -0,0 +1,324 @@
+//! Adaptive binary arithmetic coding, bit by bit with a probability per
+//! context.
+
+use std::io;
+
+/// The precision of probabilities, which are in units of `2^-12`.
+pub const PROBABILITY_BITS: u32 = 12;
+
+/// The adaptation rate of a [`BitModel`]: every bit moves the probability
+/// by `1/16` of the way towards it.
+const SHIFT: u32 = 4;
+
+/// The number of bytes past the end of the input that a decoder reads as
+/// zeros, more than the 4 bytes it reads ahead of the code of the encoder.
+const SLACK: u32 = 8;
+
+/// The probability state of a context: the probability that the next bit
+/// is 1, adapted after every bit coded in the context.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::arithmetic::BitModel;
+/// let mut model = BitModel::new();
+/// assert_eq!(model.probability(), 2048);
+/// model.update(true);
+/// assert_eq!(model.probability(), 2176);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitModel {
+    p: u16,
+}
+
+impl BitModel {
+    /// Creates a model of even odds.
+    pub fn new() -> BitModel {
+        BitModel {
+            p: 1 << (PROBABILITY_BITS - 1),
+        }
+    }
+
+    /// Returns the probability of a 1, between 1 and `2^12 - 1`.
+    pub fn probability(&self) -> u16 {
+        self.p
+    }
+
+    /// Moves the probability towards `bit` by a fixed fraction, computed by
+    /// a shift.
+    pub fn update(&mut self, bit: bool) {
+        if bit {
+            self.p += ((1 << PROBABILITY_BITS) - self.p) >> SHIFT;
+        } else {
+            self.p -= self.p >> SHIFT;
+        }
+    }
+}
+
+impl Default for BitModel {
+    fn default() -> BitModel {
+        BitModel::new()
+    }
+}
+
+/// Returns the point of `[x1, x2]` splitting it by the probability `p` of a
+/// 1, the bit 1 taking the lower part.
+fn split(x1: u32, x2: u32, p: u16) -> u32 {
+    assert!(
+        p > 0 && u32::from(p) < 1 << PROBABILITY_BITS,
+        "probability {} out of range",
+        p
+    );
+    let range = x2 - x1;
+    let p = u32::from(p);
+    x1 + (range >> PROBABILITY_BITS) * p
+        + (((range & ((1 << PROBABILITY_BITS) - 1)) * p) >> PROBABILITY_BITS)
+}
+
+/// An encoder of bits writing bytes to `W`.
+///
+/// The interval is kept in two 32-bit bounds, whose leading byte is
+/// written as soon as they share it. It never needs a carry, so the bytes
+/// written are final.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::arithmetic::{BinaryDecoder, BinaryEncoder, BitModel};
+/// let bits: Vec<bool> = (0..1000).map(|i| i % 10 == 0).collect();
+/// // a context per position modulo 10
+/// let mut models = [BitModel::new(); 10];
+/// let mut encoder = BinaryEncoder::new(Vec::new());
+/// for (i, &bit) in bits.iter().enumerate() {
+///     encoder.encode_bit(&mut models[i % 10], bit).unwrap();
+/// }
+/// let bytes = encoder.finish().unwrap();
+/// assert!(bytes.len() < 20);
+///
+/// let mut models = [BitModel::new(); 10];
+/// let mut decoder = BinaryDecoder::new(&bytes[..]).unwrap();
+/// for (i, &bit) in bits.iter().enumerate() {
+///     assert_eq!(decoder.decode_bit(&mut models[i % 10]).unwrap(), bit);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BinaryEncoder<W: io::Write> {
+    inner: W,
+    x1: u32,
+    x2: u32,
+}
+
+impl<W: io::Write> BinaryEncoder<W> {
+    pub fn new(inner: W) -> BinaryEncoder<W> {
+        BinaryEncoder {
+            inner,
+            x1: 0,
+            x2: !0,
+        }
+    }
+
+    /// Encodes `bit`, which is 1 with the probability `p` in units of
+    /// `2^-12`, as given by a model of the caller's.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is 0 or not less than `2^12`.
+    pub fn encode(&mut self, bit: bool, p: u16) -> io::Result<()> {
+        let mid = split(self.x1, self.x2, p);
+        if bit {
+            self.x2 = mid;
+        } else {
+            self.x1 = mid + 1;
+        }
+        while (self.x1 ^ self.x2) >> 24 == 0 {
+            self.inner.write_all(&[(self.x2 >> 24) as u8])?;
+            self.x1 <<= 8;
+            self.x2 = (self.x2 << 8) | 0xff;
+        }
+        Ok(())
+    }
+
+    /// Encodes `bit` with the probability of `model`, then updates it.
+    pub fn encode_bit(&mut self, model: &mut BitModel, bit: bool) -> io::Result<()> {
+        self.encode(bit, model.probability())?;
+        model.update(bit);
+        Ok(())
+    }
+
+    /// Writes the last byte and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        // the bounds differ in their leading byte, so the interval holds
+        // the next value of that byte followed by zeros, which is how the
+        // decoder reads the end of the input
+        self.inner.write_all(&[(self.x1 >> 24) as u8 + 1])?;
+        Ok(self.inner)
+    }
+}
+
+/// A decoder of the bits of a [`BinaryEncoder`], reading bytes from `R`.
+///
+/// Bytes past the end of the input read as zeros, so the decoder must be
+/// told the number of bits. Past 8 such bytes, more than the code of any
+/// encoder needs, it fails with `ErrorKind::UnexpectedEof` instead, so that
+/// a number of bits too large for the input fails soon.
+#[derive(Debug)]
+pub struct BinaryDecoder<R: io::Read> {
+    inner: R,
+    x1: u32,
+    x2: u32,
+    /// The next 32 bits of the input.
+    x: u32,
+    /// The number of bytes read past the end of the input.
+    past_end: u32,
+}
+
+impl<R: io::Read> BinaryDecoder<R> {
+    /// Creates a decoder, reading the first four bytes of the input.
+    pub fn new(inner: R) -> io::Result<BinaryDecoder<R>> {
+        let mut decoder = BinaryDecoder {
+            inner,
+            x1: 0,
+            x2: !0,
+            x: 0,
+            past_end: 0,
+        };
+        for _ in 0..4 {
+            decoder.x = (decoder.x << 8) | u32::from(decoder.read()?);
+        }
+        Ok(decoder)
+    }
+
+    /// Decodes a bit encoded with the probability `p` of a 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is 0 or not less than `2^12`.
+    pub fn decode(&mut self, p: u16) -> io::Result<bool> {
+        let mid = split(self.x1, self.x2, p);
+        let bit = self.x <= mid;
+        if bit {
+            self.x2 = mid;
+        } else {
+            self.x1 = mid + 1;
+        }
+        while (self.x1 ^ self.x2) >> 24 == 0 {
+            self.x1 <<= 8;
+            self.x2 = (self.x2 << 8) | 0xff;
+            self.x = (self.x << 8) | u32::from(self.read()?);
+        }
+        Ok(bit)
+    }
+
+    /// Decodes a bit with the probability of `model`, then updates it.
+    pub fn decode_bit(&mut self, model: &mut BitModel) -> io::Result<bool> {
+        let bit = self.decode(model.probability())?;
+        model.update(bit);
+        Ok(bit)
+    }
+
+    /// Returns the reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        loop {
+            match self.inner.read(&mut byte) {
+                Ok(0) if self.past_end < SLACK => {
+                    self.past_end += 1;
+                    return Ok(0);
+                }
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => return Ok(byte[0]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model() {
+        let mut model = BitModel::default();
+        for _ in 0..1000 {
+            model.update(true);
+        }
+        assert_eq!(model.probability(), 4081);
+        for _ in 0..1000 {
+            model.update(false);
+        }
+        assert_eq!(model.probability(), 15);
+    }
+
+    #[test]
+    fn round_trip() {
+        // bits of a byte stream, in the context of the bits before them in
+        // the byte, as a bytewise model does
+        let data: Vec<u8> = (0..4000u64)
+            .map(|i| b"abracadabra"[(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61) as usize])
+            .collect();
+        let mut models = vec![BitModel::new(); 256];
+        let mut encoder = BinaryEncoder::new(Vec::new());
+        for &byte in data.iter() {
+            let mut ctx = 1;
+            for i in (0..8).rev() {
+                let bit = (byte >> i) & 1 == 1;
+                encoder.encode_bit(&mut models[ctx], bit).unwrap();
+                ctx = 2 * ctx + bit as usize;
+            }
+        }
+        let bytes = encoder.finish().unwrap();
+        // five letters, of about 2.2 bits of entropy
+        assert!(bytes.len() * 8 < data.len() * 5 / 2);
+
+        let mut models = vec![BitModel::new(); 256];
+        let mut decoder = BinaryDecoder::new(&bytes[..]).unwrap();
+        for &byte in data.iter() {
+            let mut ctx = 1;
+            for _ in 0..8 {
+                let bit = decoder.decode_bit(&mut models[ctx]).unwrap();
+                ctx = 2 * ctx + bit as usize;
+            }
+            assert_eq!(ctx as u8, byte);
+        }
+    }
+
+    #[test]
+    fn extreme_probabilities() {
+        // bits against the odds, which narrow the interval most
+        let bits: Vec<(bool, u16)> = (0..2000)
+            .map(|i| match i % 4 {
+                0 => (true, 1),
+                1 => (false, 4095),
+                2 => (i % 3 == 0, 2048),
+                _ => (true, 4095),
+            })
+            .collect();
+        let mut encoder = BinaryEncoder::new(Vec::new());
+        for &(bit, p) in bits.iter() {
+            encoder.encode(bit, p).unwrap();
+        }
+        let bytes = encoder.finish().unwrap();
+        let mut decoder = BinaryDecoder::new(&bytes[..]).unwrap();
+        for &(bit, p) in bits.iter() {
+            assert_eq!(decoder.decode(p).unwrap(), bit);
+        }
+        // even bits run out of input soon
+        let err = (0..200)
+            .try_for_each(|_| decoder.decode(2048).map(drop))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(BinaryEncoder::new(Vec::new()).finish().unwrap().len() == 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "probability 4096 out of range")]
+    fn invalid_probability() {
+        BinaryEncoder::new(Vec::new()).encode(true, 4096).unwrap();
+    }
+}
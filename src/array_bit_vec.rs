@@ -0,0 +1,333 @@
+//! A fixed-capacity bit vector stored inline, for use where allocation is
+//! unavailable.
+//!
+//! The module needs no standard library, and an [`ArrayBitVec`] is a
+//! `BitSink` with it, so that the entropy coders write into it.
+
+use core::fmt;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::error;
+
+use crate::bit_order::{BitOrder, LsbFirst};
+#[cfg(feature = "std")]
+use crate::bit_vec::OrderedBitVec;
+
+const BITS: usize = 8;
+
+/// A bit vector holding at most `8 * N` bits in a `[u8; N]`, packed in the
+/// order `O`.
+///
+/// The API follows `BitVec`, except that operations which would grow the
+/// vector beyond its capacity fail with a [`CapacityError`] instead of
+/// allocating. The unused bits of the buffer are always zero.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::array_bit_vec::ArrayBitVec;
+/// let mut v = ArrayBitVec::<2>::new();
+/// v.push(true).unwrap();
+/// v.push_bits(0b0110, 4).unwrap();
+/// assert_eq!(v.len(), 5);
+/// assert_eq!(v.as_bytes(), &[0b0_1101]);
+/// assert!(v.push_bits(0, 12).is_err());
+/// assert_eq!(v.len(), 5);
+/// assert_eq!(v.iter().collect::<Vec<_>>(), vec![true, false, true, true, false]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ArrayBitVec<const N: usize, O: BitOrder = LsbFirst> {
+    len: usize,
+    data: [u8; N],
+    order: PhantomData<O>,
+}
+
+impl<const N: usize, O: BitOrder> ArrayBitVec<N, O> {
+    /// Constructs a new, empty `ArrayBitVec`.
+    pub const fn new() -> Self {
+        ArrayBitVec {
+            len: 0,
+            data: [0; N],
+            order: PhantomData,
+        }
+    }
+
+    /// Returns the number of bits the vector can hold.
+    pub const fn capacity(&self) -> usize {
+        N * BITS
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the vector is filled to capacity.
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    /// Appends an element to the back of the vector.
+    ///
+    /// # Errors
+    ///
+    /// Fails, leaving the vector unchanged, if it is full.
+    pub fn push(&mut self, value: bool) -> Result<(), CapacityError> {
+        if self.is_full() {
+            return Err(CapacityError);
+        }
+        self.push_logical(value as u64, 1);
+        Ok(())
+    }
+
+    /// Appends the low `nbits` bits of `value` as an integer field, as
+    /// `BitVec::push_bits` does.
+    ///
+    /// # Errors
+    ///
+    /// Fails, leaving the vector unchanged, if fewer than `nbits` bits of
+    /// capacity remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nbits` is greater than 64.
+    pub fn push_bits(&mut self, value: u64, nbits: usize) -> Result<(), CapacityError> {
+        assert!(nbits <= 64, "cannot push {} bits from a u64", nbits);
+        if self.capacity() - self.len < nbits {
+            return Err(CapacityError);
+        }
+        self.push_logical(O::convert_field(value, nbits), nbits);
+        Ok(())
+    }
+
+    /// Appends all 8 bits of `byte`, as by `push_bits(byte as u64, 8)`.
+    ///
+    /// # Errors
+    ///
+    /// Fails, leaving the vector unchanged, if fewer than 8 bits of capacity
+    /// remain.
+    pub fn push_byte(&mut self, byte: u8) -> Result<(), CapacityError> {
+        self.push_bits(byte as u64, BITS)
+    }
+
+    /// Appends the low `nbits` bits of `bits`, bit 0 first. The caller has
+    /// checked the capacity.
+    fn push_logical(&mut self, mut bits: u64, mut nbits: usize) {
+        while nbits > 0 {
+            let bit = self.len % BITS;
+            let n = (BITS - bit).min(nbits);
+            let chunk = (bits & ((1 << n) - 1)) as u8;
+            self.data[self.len / BITS] |= O::convert_byte(chunk << bit);
+            bits >>= n;
+            nbits -= n;
+            self.len += n;
+        }
+    }
+
+    /// Removes the last element and returns it, or `None` if the vector is
+    /// empty.
+    pub fn pop(&mut self) -> Option<bool> {
+        let value = self.last()?;
+        self.len -= 1;
+        self.data[self.len / BITS] &= !O::convert_byte(1 << (self.len % BITS));
+        Some(value)
+    }
+
+    /// Returns the last element, or `None` if the vector is empty.
+    pub fn last(&self) -> Option<bool> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// Returns the element at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index < self.len {
+            Some(get_bit::<O>(&self.data, index))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the element at `index` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(
+            index < self.len,
+            "index out of bounds: the len is {} but the index is {}",
+            self.len,
+            index
+        );
+        let mask = O::convert_byte(1 << (index % BITS));
+        if value {
+            self.data[index / BITS] |= mask;
+        } else {
+            self.data[index / BITS] &= !mask;
+        }
+    }
+
+    /// Clears the vector, removing all elements.
+    pub fn clear(&mut self) {
+        self.data = [0; N];
+        self.len = 0;
+    }
+
+    /// Returns the bytes holding the elements, with the unused bits of the
+    /// last byte set to zero.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len.div_ceil(BITS)]
+    }
+
+    /// Copies the elements into a growable `BitVec` with the same order.
+    #[cfg(feature = "std")]
+    pub fn to_bit_vec(&self) -> OrderedBitVec<O> {
+        OrderedBitVec::from_bytes(self.as_bytes().to_vec(), self.len).unwrap()
+    }
+
+    /// Returns an iterator over the elements.
+    pub fn iter(&self) -> Iter<'_, O> {
+        Iter {
+            data: &self.data,
+            pos: 0,
+            end: self.len,
+            order: PhantomData,
+        }
+    }
+}
+
+impl<const N: usize, O: BitOrder> Default for ArrayBitVec<N, O> {
+    fn default() -> Self {
+        ArrayBitVec::new()
+    }
+}
+
+impl<'a, const N: usize, O: BitOrder> IntoIterator for &'a ArrayBitVec<N, O> {
+    type Item = bool;
+    type IntoIter = Iter<'a, O>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An error returned when an [`ArrayBitVec`] has no room for more bits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bit vector capacity exceeded")
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for CapacityError {}
+
+/// An iterator over the elements of an [`ArrayBitVec`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a, O: BitOrder = LsbFirst> {
+    data: &'a [u8],
+    pos: usize,
+    end: usize,
+    order: PhantomData<O>,
+}
+
+impl<'a, O: BitOrder> Iterator for Iter<'a, O> {
+    type Item = bool;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
+        let b = get_bit::<O>(self.data, self.pos);
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl<'a, O: BitOrder> DoubleEndedIterator for Iter<'a, O> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(get_bit::<O>(self.data, self.end))
+    }
+}
+
+impl<'a, O: BitOrder> ExactSizeIterator for Iter<'a, O> {}
+
+fn get_bit<O: BitOrder>(data: &[u8], index: usize) -> bool {
+    (O::convert_byte(data[index / BITS]) >> (index % BITS)) & 1 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflow() {
+        // writes that do not fit fail and leave the vector as it was
+        let mut v = ArrayBitVec::<1>::new();
+        v.push_bits(0b101, 3).unwrap();
+        assert_eq!(v.push_bits(0, 6), Err(CapacityError));
+        assert_eq!(v.push_byte(0), Err(CapacityError));
+        assert_eq!(v.len(), 3);
+        v.push_bits(!0, 5).unwrap();
+        assert_eq!(v.push(false), Err(CapacityError));
+        assert_eq!(v.as_bytes(), &[0b1111_1101]);
+        assert_eq!(v.pop(), Some(true));
+        v.push(false).unwrap();
+        assert_eq!(v.as_bytes(), &[0b0111_1101]);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::bit_order::MsbFirst;
+
+    fn check<O: BitOrder>() {
+        let mut v = ArrayBitVec::<3, O>::new();
+        let mut expected = OrderedBitVec::<O>::new();
+        for (i, nbits) in [3, 0, 7, 1, 8, 5].iter().enumerate() {
+            let value = 0x9e37_79b9_7f4a_7c15u64.rotate_left(i as u32 * 7);
+            v.push_bits(value, *nbits).unwrap();
+            expected.push_bits(value, *nbits);
+        }
+        assert_eq!(v.push_bits(0, 1), Err(CapacityError));
+        assert_eq!(v.push(true), Err(CapacityError));
+        assert!(v.is_full());
+        assert_eq!(v.as_bytes(), expected.as_bytes());
+        assert_eq!(v.to_bit_vec(), expected);
+        assert!(v.iter().eq(expected.iter()));
+        assert!(v.iter().rev().eq(expected.iter().rev()));
+
+        v.set(4, !v.get(4).unwrap());
+        expected.set(4, !expected.get(4).unwrap());
+        for _ in 0..11 {
+            assert_eq!(v.pop(), expected.pop());
+        }
+        assert_eq!(v.as_bytes(), expected.as_bytes());
+        v.push_byte(0xa5).unwrap();
+        expected.push_byte(0xa5);
+        assert_eq!(v.to_bit_vec(), expected);
+        v.clear();
+        assert_eq!(v, ArrayBitVec::default());
+    }
+
+    #[test]
+    fn matches_bit_vec() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+}
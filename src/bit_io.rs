@@ -0,0 +1,399 @@
+//! Streaming bit I/O over `std::io` readers and writers, and the
+//! [`BitSink`] and [`BitSource`] traits through which codecs write and read
+//! bits.
+//!
+//! Like `OrderedBitVec`, the readers and writers take a [`BitOrder`] type
+//! parameter, `LsbFirst` (as in DEFLATE) by default or `MsbFirst` (as in
+//! JPEG and bzip2), so a stream written by [`BitWriter`] has the same bytes
+//! as a bit vector of the same order built with the same calls, and
+//! [`BitReader`] reads it back. [`BitCursor`] reads such streams from memory
+//! with random access, and [`SliceBitReader`] reads them from a byte slice
+//! with the least overhead per read, for entropy decoders.
+
+use std::io;
+
+use crate::array_bit_vec::{ArrayBitVec, CapacityError};
+use crate::bit_order::BitOrder;
+use crate::bit_vec::{Iter, OrderedBitVec};
+
+mod cursor;
+mod reader;
+mod slice;
+mod writer;
+
+pub use self::cursor::BitCursor;
+pub use self::reader::BitReader;
+pub use self::slice::SliceBitReader;
+pub use self::writer::BitWriter;
+
+/// A destination of bits, such as a `BitVec` or a [`BitWriter`].
+///
+/// Multi-bit fields are laid out as the sink's bit order prescribes, so the
+/// same calls produce the same bits in a `BitVec` and a `BitWriter` of the
+/// same order.
+pub trait BitSink {
+    /// Writes a single bit.
+    fn write_bit(&mut self, bit: bool) -> io::Result<()>;
+
+    /// Writes the low `n` bits of `value` as an integer field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()>;
+
+    /// Writes `n` in unary: `n` ones followed by a zero.
+    fn write_unary(&mut self, n: usize) -> io::Result<()> {
+        // fields of ones are the same in either order
+        for _ in 0..n / 64 {
+            self.write_bits(!0, 64)?;
+        }
+        self.write_bits((1 << (n % 64)) - 1, n % 64)?;
+        self.write_bit(false)
+    }
+}
+
+/// A source of bits, such as a `BitVec` iterator or a [`BitReader`].
+///
+/// Reads past the end fail with `ErrorKind::UnexpectedEof`.
+pub trait BitSource {
+    /// Reads a single bit.
+    fn read_bit(&mut self) -> io::Result<bool>;
+
+    /// Reads `n` bits as an integer field, the inverse of
+    /// `BitSink::write_bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    fn read_bits(&mut self, n: usize) -> io::Result<u64>;
+
+    /// Reads a unary-coded number, `n` ones followed by a zero.
+    fn read_unary(&mut self) -> io::Result<usize> {
+        let mut n = 0;
+        while self.read_bit()? {
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+/// A [`BitSource`] that can look ahead, for table-driven decoders.
+pub trait BitPeek: BitSource {
+    /// Returns the next `n` bits without consuming them, in stream order:
+    /// the next bit is bit 0, whatever the bit order. Bits past the end of
+    /// the input read as zeros.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 56.
+    fn peek(&mut self, n: usize) -> io::Result<u64>;
+
+    /// Skips `n` bits.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if fewer than `n` bits remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    fn consume(&mut self, n: usize) -> io::Result<()>;
+}
+
+impl<S: BitSink + ?Sized> BitSink for &mut S {
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        (**self).write_bit(bit)
+    }
+
+    fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()> {
+        (**self).write_bits(value, n)
+    }
+
+    fn write_unary(&mut self, n: usize) -> io::Result<()> {
+        (**self).write_unary(n)
+    }
+}
+
+impl<S: BitSource + ?Sized> BitSource for &mut S {
+    fn read_bit(&mut self) -> io::Result<bool> {
+        (**self).read_bit()
+    }
+
+    fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        (**self).read_bits(n)
+    }
+
+    fn read_unary(&mut self) -> io::Result<usize> {
+        (**self).read_unary()
+    }
+}
+
+impl<S: BitPeek + ?Sized> BitPeek for &mut S {
+    fn peek(&mut self, n: usize) -> io::Result<u64> {
+        (**self).peek(n)
+    }
+
+    fn consume(&mut self, n: usize) -> io::Result<()> {
+        (**self).consume(n)
+    }
+}
+
+impl<O: BitOrder> BitSink for OrderedBitVec<O> {
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.push(bit);
+        Ok(())
+    }
+
+    fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()> {
+        self.push_bits(value, n);
+        Ok(())
+    }
+
+    fn write_unary(&mut self, n: usize) -> io::Result<()> {
+        self.push_unary(n);
+        Ok(())
+    }
+}
+
+/// Bits past the capacity fail with `ErrorKind::WriteZero`, leaving the
+/// vector unchanged.
+impl<const N: usize, O: BitOrder> BitSink for ArrayBitVec<N, O> {
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.push(bit).map_err(overflow)
+    }
+
+    fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()> {
+        self.push_bits(value, n).map_err(overflow)
+    }
+
+    fn write_unary(&mut self, n: usize) -> io::Result<()> {
+        if self.capacity() - self.len() <= n {
+            return Err(overflow(CapacityError));
+        }
+        for _ in 0..n / 64 {
+            self.write_bits(!0, 64)?;
+        }
+        self.write_bits((1 << (n % 64)) - 1, n % 64)?;
+        self.write_bit(false)
+    }
+}
+
+fn overflow(e: CapacityError) -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, e)
+}
+
+impl<'a, O: BitOrder> BitSource for Iter<'a, O> {
+    fn read_bit(&mut self) -> io::Result<bool> {
+        self.next()
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        Iter::read_bits(self, n).ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn read_unary(&mut self) -> io::Result<usize> {
+        Iter::read_unary(self).ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+impl<'a, O: BitOrder> BitPeek for Iter<'a, O> {
+    fn peek(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= 56, "cannot peek {} bits", n);
+        let n = n.min(self.len());
+        Ok(O::convert_field(self.peek_bits(n).unwrap(), n))
+    }
+
+    fn consume(&mut self, n: usize) -> io::Result<()> {
+        match self.read_bits(n) {
+            Some(_) => Ok(()),
+            None => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+impl<W: io::Write, O: BitOrder> BitSink for BitWriter<W, O> {
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        BitWriter::write_bit(self, bit)
+    }
+
+    fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()> {
+        BitWriter::write_bits(self, value, n)
+    }
+
+    fn write_unary(&mut self, n: usize) -> io::Result<()> {
+        BitWriter::write_unary(self, n)
+    }
+}
+
+impl<R: io::Read, O: BitOrder> BitSource for BitReader<R, O> {
+    fn read_bit(&mut self) -> io::Result<bool> {
+        BitReader::read_bit(self)
+    }
+
+    fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        BitReader::read_bits(self, n)
+    }
+
+    fn read_unary(&mut self) -> io::Result<usize> {
+        BitReader::read_unary(self)
+    }
+}
+
+impl<R: io::Read, O: BitOrder> BitPeek for BitReader<R, O> {
+    fn peek(&mut self, n: usize) -> io::Result<u64> {
+        Ok(O::convert_field(self.peek_bits(n)?, n))
+    }
+
+    fn consume(&mut self, n: usize) -> io::Result<()> {
+        BitReader::consume(self, n)
+    }
+}
+
+impl<'a, O: BitOrder> BitSource for SliceBitReader<'a, O> {
+    fn read_bit(&mut self) -> io::Result<bool> {
+        SliceBitReader::read_bit(self)
+    }
+
+    fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        SliceBitReader::read_bits(self, n)
+    }
+
+    fn read_unary(&mut self) -> io::Result<usize> {
+        SliceBitReader::read_unary(self)
+    }
+}
+
+impl<'a, O: BitOrder> BitPeek for SliceBitReader<'a, O> {
+    fn peek(&mut self, n: usize) -> io::Result<u64> {
+        Ok(O::convert_field(self.peek_bits(n)?, n))
+    }
+
+    fn consume(&mut self, n: usize) -> io::Result<()> {
+        SliceBitReader::consume(self, n)
+    }
+}
+
+impl<T: AsRef<[u8]>, O: BitOrder> BitSource for BitCursor<T, O> {
+    fn read_bit(&mut self) -> io::Result<bool> {
+        BitCursor::read_bit(self)
+    }
+
+    fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        BitCursor::read_bits(self, n)
+    }
+
+    fn read_unary(&mut self) -> io::Result<usize> {
+        BitCursor::read_unary(self)
+    }
+}
+
+impl<T: AsRef<[u8]>, O: BitOrder> BitPeek for BitCursor<T, O> {
+    fn peek(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= 56, "cannot peek {} bits", n);
+        Ok(O::convert_field(self.peek_bits(n), n))
+    }
+
+    fn consume(&mut self, n: usize) -> io::Result<()> {
+        BitCursor::consume(self, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::{LsbFirst, MsbFirst};
+
+    fn write_all<S: BitSink>(mut sink: S) -> S {
+        sink.write_bit(true).unwrap();
+        sink.write_bits(0x2545_f491, 29).unwrap();
+        sink.write_unary(70).unwrap();
+        sink.write_bits(!0, 64).unwrap();
+        sink
+    }
+
+    fn read_all<S: BitSource>(mut source: S) {
+        assert!(source.read_bit().unwrap());
+        assert_eq!(source.read_bits(29).unwrap(), 0x2545_f491 & ((1 << 29) - 1));
+        assert_eq!(source.read_unary().unwrap(), 70);
+        assert_eq!(source.read_bits(64).unwrap(), !0);
+        let err = source.read_bits(8).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// A sink relying on the default `write_unary`.
+    struct Bits(Vec<bool>);
+
+    impl BitSink for Bits {
+        fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+            self.0.push(bit);
+            Ok(())
+        }
+
+        fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()> {
+            let value = MsbFirst::convert_field(value, n);
+            self.0.extend((0..n).map(|i| (value >> i) & 1 == 1));
+            Ok(())
+        }
+    }
+
+    fn peek_all<S: BitPeek>(mut source: S) {
+        assert_eq!(source.peek(1).unwrap(), 1);
+        source.consume(1).unwrap();
+        // the field 0x2545_f491 starts with its least or most significant bit
+        let first = source.peek(29).unwrap() & 1;
+        assert_eq!(source.read_bit().unwrap(), first == 1);
+        for &n in [28, 64, 7, 60].iter() {
+            source.consume(n).unwrap();
+        }
+        assert_eq!(source.peek(8).unwrap(), 0b1111);
+        assert!(source.consume(8).is_err());
+    }
+
+    #[test]
+    fn sinks_and_sources_agree() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+        let bits = write_all(Bits(Vec::new())).0;
+        let v = write_all(OrderedBitVec::<MsbFirst>::new());
+        assert!(bits.into_iter().eq(v.iter()));
+    }
+
+    fn check<O: BitOrder>() {
+        let v = write_all(OrderedBitVec::<O>::new());
+        let w = BitWriter::<_, O>::with_order(Vec::new());
+        let bytes = write_all(w).finish().unwrap();
+        assert_eq!(v.as_bytes(), &bytes[..]);
+        let a = write_all(ArrayBitVec::<21, O>::new());
+        assert_eq!(a.as_bytes(), &bytes[..]);
+        read_all(v.iter());
+        read_all(BitReader::<_, O>::with_order(&bytes[..]));
+        read_all(BitCursor::<_, O>::with_order(&bytes));
+        read_all(SliceBitReader::<O>::with_order(&bytes));
+        peek_all(v.iter());
+        peek_all(BitReader::<_, O>::with_order(&bytes[..]));
+        peek_all(BitCursor::<_, O>::with_order(&bytes));
+        peek_all(SliceBitReader::<O>::with_order(&bytes));
+    }
+
+    #[test]
+    fn array_bit_vec_overflow() {
+        // writes that do not fit fail and leave the vector as it was
+        let mut v = ArrayBitVec::<2>::new();
+        crate::universal_codes::encode_gamma(&mut v, 100).unwrap();
+        let len = v.len();
+        for result in [
+            v.write_unary(16 - len),
+            v.write_bits(0, 17 - len),
+            crate::universal_codes::encode_gamma(&mut v, 1000),
+        ] {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::WriteZero);
+        }
+        assert_eq!(v.len(), len);
+        v.write_unary(15 - len).unwrap();
+        assert!(v.is_full());
+        assert_eq!(
+            v.write_bit(true).unwrap_err().kind(),
+            io::ErrorKind::WriteZero
+        );
+    }
+}
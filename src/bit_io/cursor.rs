@@ -0,0 +1,293 @@
+//! A seekable bit reader over an in-memory buffer.
+
+use std::io::{self, SeekFrom};
+use std::marker::PhantomData;
+
+use crate::bit_order::{BitOrder, LsbFirst};
+
+/// A reader of bits from an in-memory byte buffer, with random access.
+///
+/// Like `std::io::Cursor`, `BitCursor` wraps anything that can be viewed as
+/// a byte slice, such as `&[u8]` or `Vec<u8>`, and keeps a position, here
+/// counted in bits. The bits are read as by [`BitReader`](super::BitReader),
+/// in the order `O`.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::SeekFrom;
+/// # use rust_compress::bit_io::BitCursor;
+/// let mut c = BitCursor::new(vec![0b1100_1101, 0b0000_0001]);
+/// c.seek(SeekFrom::Start(1)).unwrap();
+/// assert_eq!(c.read_bits(8).unwrap(), 0b1110_0110);
+/// assert_eq!(c.position(), 9);
+/// c.seek(SeekFrom::Current(-3)).unwrap();
+/// assert_eq!(c.read_bits(3).unwrap(), 0b111);
+/// c.rewind();
+/// assert_eq!(c.read_bit().unwrap(), true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BitCursor<T, O: BitOrder = LsbFirst> {
+    inner: T,
+    pos: u64,
+    order: PhantomData<O>,
+}
+
+impl<T: AsRef<[u8]>> BitCursor<T> {
+    /// Creates a cursor at the first bit of `inner`, reading LSB-first bits.
+    pub fn new(inner: T) -> BitCursor<T> {
+        BitCursor::with_order(inner)
+    }
+}
+
+impl<T: AsRef<[u8]>, O: BitOrder> BitCursor<T, O> {
+    /// Creates a cursor at the first bit of `inner`, reading bits in the
+    /// order `O`.
+    pub fn with_order(inner: T) -> BitCursor<T, O> {
+        BitCursor {
+            inner,
+            pos: 0,
+            order: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Returns the number of bits in the buffer.
+    pub fn len(&self) -> u64 {
+        8 * self.inner.as_ref().len() as u64
+    }
+
+    /// Returns `true` if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.as_ref().is_empty()
+    }
+
+    /// Returns the current position in bits.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Sets the position in bits. It may lie past the end of the buffer, in
+    /// which case reads fail.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// Returns the number of bits left after the current position.
+    pub fn remaining(&self) -> u64 {
+        self.len().saturating_sub(self.pos)
+    }
+
+    /// Moves the position to the start of the buffer.
+    pub fn rewind(&mut self) {
+        self.pos = 0;
+    }
+
+    /// Moves the position by a number of bits, relative to the start, the
+    /// end or the current position, and returns the new position.
+    ///
+    /// Fails with `ErrorKind::InvalidInput` if the position would be before
+    /// the start or overflow.
+    pub fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.len(), offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    /// Reads a single bit.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` at the end of the buffer.
+    pub fn read_bit(&mut self) -> io::Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    /// Reads `n` bits as an integer field laid out in the order `O`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof`, without moving, if fewer than
+    /// `n` bits remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= 64, "cannot read {} bits into a u64", n);
+        if self.remaining() < n as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let value = self.peek_bits(n);
+        self.pos += n as u64;
+        Ok(value)
+    }
+
+    /// Returns the next `n` bits as an integer field without moving.
+    ///
+    /// Bits past the end of the buffer read as zeros, as with
+    /// `BitReader::peek_bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn peek_bits(&self, n: usize) -> u64 {
+        assert!(n <= 64, "cannot read {} bits into a u64", n);
+        O::convert_field(self.peek_logical(n), n)
+    }
+
+    /// Returns the next `n` bits in stream order, the first one in bit 0.
+    fn peek_logical(&self, n: usize) -> u64 {
+        let bytes = self.inner.as_ref();
+        if n == 0 || self.pos >= self.len() {
+            return 0;
+        }
+        let start = (self.pos / 8) as usize;
+        let end = (start + 9).min(bytes.len());
+        let mut buf = [0; 16];
+        for (b, &byte) in buf.iter_mut().zip(&bytes[start..end]) {
+            *b = O::convert_byte(byte);
+        }
+        let bits = u128::from_le_bytes(buf) >> (self.pos % 8);
+        bits as u64 & (u64::MAX >> (64 - n))
+    }
+
+    /// Skips `n` bits, typically after looking at them with `peek_bits`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof`, without moving, if fewer than
+    /// `n` bits remain.
+    pub fn consume(&mut self, n: usize) -> io::Result<()> {
+        if self.remaining() < n as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.pos += n as u64;
+        Ok(())
+    }
+
+    /// Reads a unary-coded number, `n` ones followed by a zero.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof`, without moving, if no zero
+    /// remains.
+    pub fn read_unary(&mut self) -> io::Result<usize> {
+        let start = self.pos;
+        let mut n = 0;
+        while self.remaining() > 0 {
+            let chunk = self.remaining().min(64) as usize;
+            let ones = self.peek_logical(chunk).trailing_ones() as usize;
+            if ones < chunk {
+                self.pos += (ones + 1) as u64;
+                return Ok(n + ones);
+            }
+            self.pos += chunk as u64;
+            n += chunk;
+        }
+        self.pos = start;
+        Err(io::ErrorKind::UnexpectedEof.into())
+    }
+
+    /// Moves the position forward to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::MsbFirst;
+    use crate::bit_vec::{BitVec, OrderedBitVec};
+
+    #[test]
+    fn random_access() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    fn check<O: BitOrder>() {
+        let bits: Vec<bool> = (0..300u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62 == 0)
+            .collect();
+        let v: OrderedBitVec<O> = bits.clone().into();
+        let mut c = BitCursor::<_, O>::with_order(v.as_bytes());
+        for pos in (0..300).rev() {
+            for &n in [0, 1, 7, 8, 9, 57, 64].iter() {
+                c.set_position(pos as u64);
+                if pos + n <= 304 {
+                    let expected = if pos + n <= 300 {
+                        v.read_bits(pos, n)
+                    } else {
+                        // the padding bits are zero and follow the data
+                        let k = 300 - pos;
+                        O::convert_field(O::convert_field(v.read_bits(pos, k), k), n)
+                    };
+                    assert_eq!(c.read_bits(n).unwrap(), expected);
+                    assert_eq!(c.position(), (pos + n) as u64);
+                } else {
+                    assert!(c.read_bits(n).is_err());
+                    assert_eq!(c.position(), pos as u64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn peek_past_end() {
+        let mut c = BitCursor::new([0b1011_0110]);
+        assert_eq!(c.peek_bits(64), 0b1011_0110);
+        c.consume(5).unwrap();
+        assert_eq!(c.peek_bits(8), 0b101);
+        assert!(c.consume(4).is_err());
+        assert_eq!(c.position(), 5);
+        c.set_position(100);
+        assert_eq!(c.peek_bits(3), 0);
+    }
+
+    #[test]
+    fn seek() {
+        let mut c = BitCursor::new(&[0xff; 4][..]);
+        assert_eq!(c.seek(SeekFrom::End(-5)).unwrap(), 27);
+        assert_eq!(c.seek(SeekFrom::Current(2)).unwrap(), 29);
+        assert!(c.seek(SeekFrom::Current(-30)).is_err());
+        assert_eq!(c.position(), 29);
+        assert_eq!(c.seek(SeekFrom::Start(40)).unwrap(), 40);
+        assert!(c.read_bit().is_err());
+        c.set_position(3);
+        c.align_to_byte();
+        assert_eq!(c.position(), 8);
+        assert!(c.read_unary().is_err());
+        assert_eq!(c.position(), 8);
+    }
+
+    #[test]
+    fn read_unary() {
+        let mut v = BitVec::new();
+        for n in [0, 3, 64, 100, 1].iter() {
+            v.push_unary(*n);
+        }
+        let mut c = BitCursor::new(v.clone().into_bytes());
+        for n in [0, 3, 64, 100, 1].iter() {
+            assert_eq!(c.read_unary().unwrap(), *n);
+        }
+        assert_eq!(c.position(), v.len() as u64);
+    }
+}
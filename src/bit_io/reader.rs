@@ -0,0 +1,300 @@
+//! A bit reader over `std::io::Read`.
+
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+use crate::bit_order::{BitOrder, LsbFirst};
+
+const WORD_BITS: usize = 64;
+
+/// The widest field read from the buffer in one piece; wider reads are
+/// split so that a refill of whole bytes always fits.
+const MAX_CHUNK: usize = WORD_BITS - 8;
+
+/// A reader of individual bits and bit fields from an underlying byte
+/// reader, the counterpart of [`BitWriter`](super::BitWriter), unpacking
+/// bytes in the order `O`.
+///
+/// The reader fetches up to eight bytes at a time into a 64-bit buffer, so
+/// it may consume bytes of the underlying reader beyond the last bit read.
+/// Wrap unbuffered sources such as files in a `BufReader`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_io::BitReader;
+/// let mut r = BitReader::new(&[0b1100_1101, 0b0000_0001][..]);
+/// assert_eq!(r.read_bit().unwrap(), true);
+/// assert_eq!(r.read_bits(8).unwrap(), 0b1110_0110);
+/// r.align_to_byte();
+/// assert!(r.read_bit().is_err());
+/// ```
+#[derive(Debug)]
+pub struct BitReader<R: Read, O: BitOrder = LsbFirst> {
+    inner: R,
+    /// Buffered bits in stream order, the next one in bit 0.
+    buf: u64,
+    /// Number of buffered bits.
+    nbits: usize,
+    /// Number of bits fetched from the inner reader.
+    fetched: u64,
+    order: PhantomData<O>,
+}
+
+impl<R: Read> BitReader<R> {
+    /// Creates a reader of LSB-first bits from `inner`.
+    pub fn new(inner: R) -> BitReader<R> {
+        BitReader::with_order(inner)
+    }
+}
+
+impl<R: Read, O: BitOrder> BitReader<R, O> {
+    /// Creates a reader of bits from `inner` in the order `O`.
+    pub fn with_order(inner: R) -> BitReader<R, O> {
+        BitReader {
+            inner,
+            buf: 0,
+            nbits: 0,
+            fetched: 0,
+            order: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the underlying reader, discarding the buffered bits.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the number of bits read so far.
+    pub fn bits_read(&self) -> u64 {
+        self.fetched - self.nbits as u64
+    }
+
+    /// Reads a single bit.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` at the end of the stream.
+    pub fn read_bit(&mut self) -> io::Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    /// Reads `n` bits as an integer field laid out in the order `O`, the
+    /// inverse of `BitWriter::write_bits`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the stream ends first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= 64, "cannot read {} bits into a u64", n);
+        Ok(O::convert_field(self.read_logical(n)?, n))
+    }
+
+    /// Reads `n` bits in stream order, the first one in bit 0.
+    fn read_logical(&mut self, n: usize) -> io::Result<u64> {
+        if n > MAX_CHUNK {
+            let low = self.read_logical(32)?;
+            return Ok(low | (self.read_logical(n - 32)? << 32));
+        }
+        while self.nbits < n {
+            if self.refill()? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        let value = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.nbits -= n;
+        Ok(value)
+    }
+
+    /// Returns the next `n` bits as an integer field without consuming them.
+    ///
+    /// Bits past the end of the stream read as zeros, so that a table-driven
+    /// decoder can look up its last, possibly short, code; `consume` then
+    /// tells whether the code really fits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 56.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_io::BitReader;
+    /// let mut r = BitReader::new(&[0b1100_1101][..]);
+    /// assert_eq!(r.peek_bits(12).unwrap(), 0b0000_1100_1101);
+    /// r.consume(3).unwrap();
+    /// assert_eq!(r.peek_bits(4).unwrap(), 0b1001);
+    /// assert!(r.consume(6).is_err());
+    /// ```
+    pub fn peek_bits(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= MAX_CHUNK, "cannot peek {} bits", n);
+        while self.nbits < n {
+            if self.refill()? == 0 {
+                break;
+            }
+        }
+        Ok(O::convert_field(self.buf & ((1 << n) - 1), n))
+    }
+
+    /// Skips `n` bits, typically after looking at them with `peek_bits`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the stream ends first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn consume(&mut self, n: usize) -> io::Result<()> {
+        if n <= self.nbits {
+            // `n` may be 64 only if the buffer is full
+            self.buf = self.buf.checked_shr(n as u32).unwrap_or(0);
+            self.nbits -= n;
+            return Ok(());
+        }
+        self.read_logical(n).map(drop)
+    }
+
+    /// Reads a unary-coded number, `n` ones followed by a zero, the inverse
+    /// of `BitWriter::write_unary`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the stream ends first.
+    pub fn read_unary(&mut self) -> io::Result<usize> {
+        let mut n = 0;
+        loop {
+            if self.nbits == 0 && self.refill()? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let ones = (self.buf.trailing_ones() as usize).min(self.nbits);
+            if ones < self.nbits {
+                self.buf >>= ones + 1;
+                self.nbits -= ones + 1;
+                return Ok(n + ones);
+            }
+            n += ones;
+            self.buf = 0;
+            self.nbits = 0;
+        }
+    }
+
+    /// Skips the remaining bits of the current byte.
+    pub fn align_to_byte(&mut self) {
+        let skip = self.nbits % 8;
+        self.buf >>= skip;
+        self.nbits -= skip;
+    }
+
+    /// Fetches as many whole bytes as fit into the buffer, returning the
+    /// number of bytes read, which is zero only at the end of the stream.
+    fn refill(&mut self) -> io::Result<usize> {
+        let mut bytes = [0; 8];
+        let want = (WORD_BITS - self.nbits) / 8;
+        let got = loop {
+            match self.inner.read(&mut bytes[..want]) {
+                Ok(got) => break got,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        };
+        if got > 0 {
+            self.buf |= O::convert_word(u64::from_ne_bytes(bytes)) << self.nbits;
+            self.nbits += 8 * got;
+            self.fetched += 8 * got as u64;
+        }
+        Ok(got)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::MsbFirst;
+    use crate::bit_vec::{BitVec, OrderedBitVec};
+
+    /// A reader returning at most three bytes per call.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn matches_bit_vec() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    fn check<O: BitOrder>() {
+        let mut v = OrderedBitVec::<O>::new();
+        let mut fields = Vec::new();
+        for i in 0..200 {
+            let n = (i * 7) % 65;
+            let value = 0x9e37_79b9_7f4a_7c15u64.rotate_left(i as u32);
+            let value = if n == 64 {
+                value
+            } else {
+                value & ((1 << n) - 1)
+            };
+            v.push_bits(value, n);
+            fields.push((value, n));
+            if i % 17 == 0 {
+                v.push_unary(i);
+            }
+        }
+        let mut r = BitReader::<_, O>::with_order(Trickle(v.as_bytes()));
+        let mut pos = 0;
+        for (i, &(value, n)) in fields.iter().enumerate() {
+            assert_eq!(r.read_bits(n).unwrap(), value);
+            pos += n;
+            if i % 17 == 0 {
+                assert_eq!(r.read_unary().unwrap(), i);
+                pos += i + 1;
+            }
+            assert_eq!(r.bits_read(), pos as u64);
+        }
+        r.align_to_byte();
+        let err = r.read_bit().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn peek_and_consume() {
+        let mut v = BitVec::new();
+        for i in 0..300u64 {
+            v.push(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 63 == 1);
+        }
+        let mut r = BitReader::new(Trickle(v.as_bytes()));
+        let mut pos = 0;
+        for i in 0.. {
+            let n = (i * 13) % 57;
+            let expected = v.read_bits(pos, n.min(300 - pos));
+            assert_eq!(r.peek_bits(n).unwrap(), expected);
+            let m = n.min(i % 11);
+            if pos + m > 300 {
+                break;
+            }
+            r.consume(m).unwrap();
+            pos += m;
+            assert_eq!(r.bits_read(), pos as u64);
+        }
+        assert!(r.consume(305 - pos).is_err());
+    }
+
+    #[test]
+    fn unexpected_eof() {
+        let mut r = BitReader::new(&[0xff, 0xff][..]);
+        assert_eq!(r.read_bits(3).unwrap(), 0b111);
+        assert!(r.read_bits(14).is_err());
+        let mut r = BitReader::new(&[0xff, 0xff][..]);
+        assert!(r.read_unary().is_err());
+    }
+}
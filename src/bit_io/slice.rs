@@ -0,0 +1,269 @@
+//! A fast bit reader over a byte slice.
+
+use std::convert::TryInto;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::bit_order::{BitOrder, LsbFirst};
+
+const WORD_BITS: usize = 64;
+
+/// The widest field read from the buffer in one piece, the number of bits
+/// that a refill always makes available while input lasts.
+const MAX_CHUNK: usize = WORD_BITS - 8;
+
+/// A reader of bits from a byte slice, tuned for entropy decoders.
+///
+/// Like [`BitReader`](super::BitReader), it keeps up to 64 bits in a buffer,
+/// but it refills the buffer with a single unaligned eight-byte load and
+/// no per-byte branches, and only checks the bounds of the slice once per
+/// refill. Only the last seven bytes of the slice are read one at a time.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_io::SliceBitReader;
+/// let mut r = SliceBitReader::new(&[0b1100_1101, 0b0000_0001]);
+/// assert_eq!(r.read_bit().unwrap(), true);
+/// assert_eq!(r.peek_bits(8).unwrap(), 0b1110_0110);
+/// r.consume(8).unwrap();
+/// assert_eq!(r.remaining(), 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SliceBitReader<'a, O: BitOrder = LsbFirst> {
+    data: &'a [u8],
+    /// Index of the first byte not yet counted in the buffer.
+    next: usize,
+    /// Buffered bits in stream order, the next one in bit 0. The bits above
+    /// `nbits` are either zero or the correct bits of byte `next`.
+    buf: u64,
+    /// Number of buffered bits.
+    nbits: usize,
+    order: PhantomData<O>,
+}
+
+impl<'a> SliceBitReader<'a> {
+    /// Creates a reader of LSB-first bits from `data`.
+    pub fn new(data: &'a [u8]) -> SliceBitReader<'a> {
+        SliceBitReader::with_order(data)
+    }
+}
+
+impl<'a, O: BitOrder> SliceBitReader<'a, O> {
+    /// Creates a reader of bits from `data` in the order `O`.
+    pub fn with_order(data: &'a [u8]) -> SliceBitReader<'a, O> {
+        SliceBitReader {
+            data,
+            next: 0,
+            buf: 0,
+            nbits: 0,
+            order: PhantomData,
+        }
+    }
+
+    /// Returns the number of bits read so far.
+    pub fn bits_read(&self) -> u64 {
+        8 * self.next as u64 - self.nbits as u64
+    }
+
+    /// Returns the number of bits left.
+    pub fn remaining(&self) -> u64 {
+        8 * self.data.len() as u64 - self.bits_read()
+    }
+
+    /// Reads a single bit.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` at the end of the slice.
+    pub fn read_bit(&mut self) -> io::Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    /// Reads `n` bits as an integer field laid out in the order `O`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof`, without moving, if fewer than
+    /// `n` bits remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn read_bits(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= 64, "cannot read {} bits into a u64", n);
+        if n > MAX_CHUNK {
+            if self.remaining() < n as u64 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let low = self.read_logical(32)?;
+            let high = self.read_logical(n - 32)?;
+            return Ok(O::convert_field(low | (high << 32), n));
+        }
+        Ok(O::convert_field(self.read_logical(n)?, n))
+    }
+
+    /// Returns the next `n` bits as an integer field without consuming them.
+    ///
+    /// Bits past the end of the slice read as zeros, as with
+    /// `BitReader::peek_bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 56.
+    pub fn peek_bits(&mut self, n: usize) -> io::Result<u64> {
+        assert!(n <= MAX_CHUNK, "cannot peek {} bits", n);
+        if self.nbits < n {
+            self.refill();
+        }
+        Ok(O::convert_field(self.buf & ((1 << n) - 1), n))
+    }
+
+    /// Skips `n` bits, typically after looking at them with `peek_bits`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof`, without moving, if fewer than
+    /// `n` bits remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn consume(&mut self, n: usize) -> io::Result<()> {
+        if n <= self.nbits {
+            // `n` may be 64 only if the buffer is full
+            self.buf = self.buf.checked_shr(n as u32).unwrap_or(0);
+            self.nbits -= n;
+            return Ok(());
+        }
+        self.read_bits(n).map(drop)
+    }
+
+    /// Reads a unary-coded number, `n` ones followed by a zero.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the slice ends first.
+    pub fn read_unary(&mut self) -> io::Result<usize> {
+        let mut n = 0;
+        loop {
+            if self.nbits == 0 {
+                self.refill();
+                if self.nbits == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+            let ones = (self.buf.trailing_ones() as usize).min(self.nbits);
+            if ones < self.nbits {
+                self.buf >>= ones + 1;
+                self.nbits -= ones + 1;
+                return Ok(n + ones);
+            }
+            n += ones;
+            self.buf = 0;
+            self.nbits = 0;
+        }
+    }
+
+    /// Skips the remaining bits of the current byte.
+    pub fn align_to_byte(&mut self) {
+        let skip = self.nbits % 8;
+        self.buf >>= skip;
+        self.nbits -= skip;
+    }
+
+    /// Reads at most 56 bits in stream order, the first one in bit 0.
+    fn read_logical(&mut self, n: usize) -> io::Result<u64> {
+        if self.nbits < n {
+            self.refill();
+            if self.nbits < n {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        let value = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.nbits -= n;
+        Ok(value)
+    }
+
+    /// Tops the buffer up to at least 56 bits, or to the end of the slice.
+    #[inline]
+    fn refill(&mut self) {
+        if let Some(bytes) = self.data.get(self.next..self.next + 8) {
+            let word = u64::from_ne_bytes(bytes.try_into().unwrap());
+            // the bytes only partly fitting are shifted out or re-read later
+            self.buf |= O::convert_word(word) << self.nbits;
+            self.next += (WORD_BITS - 1 - self.nbits) / 8;
+            self.nbits |= MAX_CHUNK;
+        } else {
+            self.refill_tail();
+        }
+    }
+
+    #[cold]
+    fn refill_tail(&mut self) {
+        while self.nbits <= MAX_CHUNK && self.next < self.data.len() {
+            let byte = O::convert_byte(self.data[self.next]) as u64;
+            self.buf |= byte << self.nbits;
+            self.next += 1;
+            self.nbits += 8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::MsbFirst;
+    use crate::bit_vec::OrderedBitVec;
+
+    #[test]
+    fn matches_bit_vec() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    fn check<O: BitOrder>() {
+        let mut v = OrderedBitVec::<O>::new();
+        let mut fields = Vec::new();
+        for i in 0..200 {
+            let n = (i * 7) % 65;
+            let value = 0x9e37_79b9_7f4a_7c15u64.rotate_left(i as u32);
+            let value = if n == 64 {
+                value
+            } else {
+                value & ((1 << n) - 1)
+            };
+            v.push_bits(value, n);
+            fields.push((value, n));
+            if i % 17 == 0 {
+                v.push_unary(i);
+            }
+        }
+        let bytes = v.as_bytes();
+        let mut r = SliceBitReader::<O>::with_order(bytes);
+        let mut pos = 0;
+        for (i, &(value, n)) in fields.iter().enumerate() {
+            if n <= 56 {
+                let expected = v.read_bits(pos, n);
+                assert_eq!(r.peek_bits(n).unwrap(), expected);
+            }
+            assert_eq!(r.read_bits(n).unwrap(), value);
+            pos += n;
+            if i % 17 == 0 {
+                assert_eq!(r.read_unary().unwrap(), i);
+                pos += i + 1;
+            }
+            assert_eq!(r.bits_read(), pos as u64);
+        }
+        let rest = r.remaining() as usize;
+        assert_eq!(rest, 8 * bytes.len() - pos);
+        assert_eq!(r.peek_bits(rest + 3).unwrap(), 0);
+        assert!(r.consume(rest + 1).is_err());
+        r.consume(rest).unwrap();
+        let err = r.read_bit().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn unexpected_eof() {
+        let mut r = SliceBitReader::new(&[0xff; 9]);
+        assert_eq!(r.read_bits(3).unwrap(), 0b111);
+        assert_eq!(r.read_bits(64).unwrap(), !0);
+        assert!(r.read_bits(6).is_err());
+        assert_eq!(r.remaining(), 5);
+        assert!(r.read_unary().is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! A bit writer over `std::io::Write`.
+
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+use crate::bit_order::{BitOrder, LsbFirst};
+
+const WORD_BITS: usize = 64;
+
+/// A writer of individual bits and bit fields to an underlying byte writer,
+/// packing them into bytes in the order `O`.
+///
+/// Bits are collected in a 64-bit buffer and written out eight bytes at a
+/// time. The trailing partial byte is only written by `finish`, which pads
+/// it with zeros; dropping the writer discards the bits still buffered.
+///
+/// `BitWriter` does not buffer beyond one word itself, so wrap unbuffered
+/// destinations such as files in a `BufWriter`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_io::BitWriter;
+/// let mut w = BitWriter::new(Vec::new());
+/// w.write_bit(true).unwrap();
+/// w.write_bits(0b1110_0110, 8).unwrap();
+/// assert_eq!(w.bits_written(), 9);
+/// assert_eq!(w.finish().unwrap(), vec![0b1100_1101, 0b0000_0001]);
+/// ```
+///
+/// MSB-first streams, as in JPEG or bzip2, are written by a writer created
+/// with `with_order`:
+///
+/// ```
+/// # use rust_compress::bit_io::BitWriter;
+/// # use rust_compress::bit_order::MsbFirst;
+/// let mut w = BitWriter::<_, MsbFirst>::with_order(Vec::new());
+/// w.write_bit(true).unwrap();
+/// w.write_bits(0b1110_0110, 8).unwrap();
+/// assert_eq!(w.finish().unwrap(), vec![0b1111_0011, 0b0000_0000]);
+/// ```
+#[derive(Debug)]
+pub struct BitWriter<W: Write, O: BitOrder = LsbFirst> {
+    inner: W,
+    /// Pending bits in stream order, the first one in bit 0.
+    buf: u64,
+    /// Number of pending bits, always less than 64.
+    nbits: usize,
+    /// Number of bits handed to the inner writer.
+    flushed: u64,
+    order: PhantomData<O>,
+}
+
+impl<W: Write> BitWriter<W> {
+    /// Creates a writer of LSB-first bits to `inner`.
+    pub fn new(inner: W) -> BitWriter<W> {
+        BitWriter::with_order(inner)
+    }
+}
+
+impl<W: Write, O: BitOrder> BitWriter<W, O> {
+    /// Creates a writer of bits to `inner` in the order `O`.
+    pub fn with_order(inner: W) -> BitWriter<W, O> {
+        BitWriter {
+            inner,
+            buf: 0,
+            nbits: 0,
+            flushed: 0,
+            order: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly interleaves bytes with the buffered bits, which
+    /// is only meaningful right after `flush` at a byte boundary.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the number of bits written so far, including buffered ones.
+    pub fn bits_written(&self) -> u64 {
+        self.flushed + self.nbits as u64
+    }
+
+    /// Writes a single bit.
+    pub fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.write_bits(bit as u64, 1)
+    }
+
+    /// Writes the low `n` bits of `value` as an integer field laid out in the
+    /// order `O`, as `OrderedBitVec::push_bits` does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    pub fn write_bits(&mut self, value: u64, n: usize) -> io::Result<()> {
+        assert!(n <= 64, "cannot write {} bits from a u64", n);
+        self.write_logical(O::convert_field(value, n), n)
+    }
+
+    /// Writes `n` in unary: `n` ones followed by a zero, as
+    /// `BitVec::push_unary` does.
+    pub fn write_unary(&mut self, n: usize) -> io::Result<()> {
+        for _ in 0..n / WORD_BITS {
+            self.write_logical(!0, WORD_BITS)?;
+        }
+        let rest = n % WORD_BITS;
+        self.write_logical((1 << rest) - 1, rest + 1)
+    }
+
+    /// Writes `n` bits given in stream order, the first one in bit 0, with
+    /// the bits above them clear.
+    fn write_logical(&mut self, value: u64, n: usize) -> io::Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        self.buf |= value << self.nbits;
+        if self.nbits + n < WORD_BITS {
+            self.nbits += n;
+            return Ok(());
+        }
+        self.inner
+            .write_all(&O::convert_word(self.buf).to_ne_bytes())?;
+        self.flushed += WORD_BITS as u64;
+        let written = WORD_BITS - self.nbits;
+        self.buf = if written == WORD_BITS {
+            0
+        } else {
+            value >> written
+        };
+        self.nbits = n - written;
+        Ok(())
+    }
+
+    /// Pads the stream with zeros up to the next byte boundary.
+    pub fn align_to_byte(&mut self) -> io::Result<()> {
+        let pad = (8 - self.bits_written() % 8) % 8;
+        self.write_bits(0, pad as usize)
+    }
+
+    /// Writes all complete buffered bytes and flushes the underlying writer.
+    ///
+    /// The bits of a trailing partial byte stay buffered.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let bytes = self.nbits / 8;
+        self.inner
+            .write_all(&O::convert_word(self.buf).to_ne_bytes()[..bytes])?;
+        self.flushed += 8 * bytes as u64;
+        self.nbits -= 8 * bytes;
+        // `bytes` is less than 8, so the shift does not overflow
+        self.buf >>= 8 * bytes;
+        self.inner.flush()
+    }
+
+    /// Pads the stream to a byte boundary, writes out everything buffered and
+    /// returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.align_to_byte()?;
+        self.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::MsbFirst;
+    use crate::bit_vec::OrderedBitVec;
+
+    #[test]
+    fn matches_bit_vec() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    fn check<O: BitOrder>() {
+        let mut w = BitWriter::<_, O>::with_order(Vec::new());
+        let mut expected = OrderedBitVec::<O>::new();
+        for i in 0..200 {
+            let n = (i * 7) % 65;
+            let value = 0x9e37_79b9_7f4a_7c15u64.rotate_left(i as u32);
+            w.write_bits(value, n).unwrap();
+            expected.push_bits(value, n);
+            if i % 17 == 0 {
+                w.write_unary(i).unwrap();
+                expected.push_unary(i);
+            }
+            if i % 31 == 0 {
+                w.flush().unwrap();
+            }
+            assert_eq!(w.bits_written(), expected.len() as u64);
+        }
+        w.write_bit(true).unwrap();
+        expected.push(true);
+        assert_eq!(w.finish().unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn align_to_byte() {
+        let mut w = BitWriter::new(Vec::new());
+        w.align_to_byte().unwrap();
+        w.write_bits(0b101, 3).unwrap();
+        w.align_to_byte().unwrap();
+        assert_eq!(w.bits_written(), 8);
+        w.write_bits(0xff, 8).unwrap();
+        w.align_to_byte().unwrap();
+        assert_eq!(w.bits_written(), 16);
+        assert_eq!(w.finish().unwrap(), vec![0b101, 0xff]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Orders in which a bit stream is packed into bytes.
+
+use core::fmt::Debug;
+use core::hash::Hash;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The order in which bits are packed into bytes, and in which the bits of
+/// a multi-bit integer field are laid out in the stream.
+///
+/// This trait is sealed; it is implemented by [`LsbFirst`] and [`MsbFirst`]
+/// only.
+pub trait BitOrder: Copy + Debug + Default + Eq + Ord + Hash + private::Sealed {
+    /// Converts between the stored form of a byte and its logical form, in
+    /// which bit `i` (counted from the least significant bit) is the `i`-th
+    /// bit of the stream covered by the byte.
+    ///
+    /// The conversion is its own inverse.
+    fn convert_byte(byte: u8) -> u8;
+
+    /// Converts between the stored form of a 64-bit word, whose bytes in
+    /// memory are the bytes of the stream, and its logical form, in which
+    /// bit `i` is the `i`-th bit of the stream covered by the word.
+    ///
+    /// The conversion is a bit permutation and its own inverse, so masks
+    /// converted with it can be applied to stored words directly.
+    fn convert_word(word: u64) -> u64;
+
+    /// Converts between the low `nbits` bits of an integer field and the
+    /// same bits in stream order, where bit `i` of the result is the `i`-th
+    /// bit written to the stream.
+    ///
+    /// The conversion is its own inverse.
+    fn convert_field(value: u64, nbits: usize) -> u64;
+}
+
+/// Least significant bit first, as used by DEFLATE.
+///
+/// The first bit of the stream is the least significant bit of the first
+/// byte, and integer fields are written starting from their least
+/// significant bit.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct LsbFirst;
+
+impl private::Sealed for LsbFirst {}
+
+impl BitOrder for LsbFirst {
+    #[inline]
+    fn convert_byte(byte: u8) -> u8 {
+        byte
+    }
+
+    #[inline]
+    fn convert_word(word: u64) -> u64 {
+        u64::from_le(word)
+    }
+
+    #[inline]
+    fn convert_field(value: u64, nbits: usize) -> u64 {
+        if nbits == 64 {
+            value
+        } else {
+            value & ((1 << nbits) - 1)
+        }
+    }
+}
+
+/// Most significant bit first, as used by JPEG and bzip2.
+///
+/// The first bit of the stream is the most significant bit of the first
+/// byte, and integer fields are written starting from their most
+/// significant bit.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MsbFirst;
+
+impl private::Sealed for MsbFirst {}
+
+impl BitOrder for MsbFirst {
+    #[inline]
+    fn convert_byte(byte: u8) -> u8 {
+        byte.reverse_bits()
+    }
+
+    #[inline]
+    fn convert_word(word: u64) -> u64 {
+        u64::from_le(word).reverse_bits().swap_bytes()
+    }
+
+    #[inline]
+    fn convert_field(value: u64, nbits: usize) -> u64 {
+        if nbits == 0 {
+            0
+        } else {
+            value.reverse_bits() >> (64 - nbits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_word() {
+        let bytes = [0b1000_0001, 0b0000_0110, 0, 0, 0, 0, 0, 0b1000_0000];
+        let word = u64::from_ne_bytes(bytes);
+        assert_eq!(
+            LsbFirst::convert_word(word),
+            1 | 1 << 7 | 0b11 << 9 | 1 << 63
+        );
+        assert_eq!(
+            MsbFirst::convert_word(word),
+            1 | 1 << 7 | 0b11 << 13 | 1 << 56
+        );
+        for b in bytes.iter() {
+            let word = u64::from_ne_bytes([*b; 8]);
+            let logical = (*b as u64) * 0x0101_0101_0101_0101;
+            assert_eq!(LsbFirst::convert_word(word), logical);
+            assert_eq!(
+                MsbFirst::convert_word(word),
+                (MsbFirst::convert_byte(*b) as u64) * 0x0101_0101_0101_0101
+            );
+            assert_eq!(MsbFirst::convert_word(MsbFirst::convert_word(word)), word);
+        }
+    }
+
+    #[test]
+    fn convert_field() {
+        assert_eq!(LsbFirst::convert_field(0b1101, 4), 0b1101);
+        assert_eq!(LsbFirst::convert_field(0b1101, 3), 0b101);
+        assert_eq!(LsbFirst::convert_field(!0, 64), !0);
+        assert_eq!(MsbFirst::convert_field(0b1101, 4), 0b1011);
+        assert_eq!(MsbFirst::convert_field(0b1101, 6), 0b101100);
+        assert_eq!(MsbFirst::convert_field(0b1101, 3), 0b101);
+        assert_eq!(MsbFirst::convert_field(1, 64), 1 << 63);
+        assert_eq!(MsbFirst::convert_field(0, 0), 0);
+    }
+}
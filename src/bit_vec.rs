@@ -1,12 +1,52 @@
+use std::convert::{TryFrom, TryInto};
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Bound, Not, RangeBounds,
+};
+use std::str::FromStr;
+
+use crate::bit_order::{BitOrder, LsbFirst, MsbFirst};
+use crate::varint;
+
+mod words;
+
+use self::words::Words;
+
 const BITS: usize = 8;
+const WORD_BITS: usize = 64;
 
+/// A growable vector of bits, packed into bytes in the order `O`.
+///
+/// Most code uses the [`BitVec`] (LSB-first) or [`MsbBitVec`] (MSB-first)
+/// aliases. The order only affects the byte representation (`into_bytes`,
+/// `from_bytes`) and multi-bit fields (`push_bits`, `read_bits`); `push`,
+/// `get` and `iter` see the same sequence of bits for either order.
+///
+/// The bits are stored in 64-bit words whose bytes in memory are the bytes
+/// of the stream, so word-at-a-time operations and `as_bytes` need no
+/// conversion. The unused bits of the last word are always zero, whatever
+/// sequence of operations produced the vector, so the bytes can be hashed,
+/// compared or written out as they are.
+///
+/// Vectors of up to 128 bits are stored inline, without allocating.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-pub struct BitVec {
-    bit: u8,
-    data: Vec<u8>,
+pub struct OrderedBitVec<O: BitOrder> {
+    len: usize,
+    data: Words,
+    order: PhantomData<O>,
 }
 
-impl BitVec {
+/// A bit vector packed least significant bit first.
+pub type BitVec = OrderedBitVec<LsbFirst>;
+
+/// A bit vector packed most significant bit first.
+pub type MsbBitVec = OrderedBitVec<MsbFirst>;
+
+impl<O: BitOrder> OrderedBitVec<O> {
     /// Constructs a new, empty `BitVec`.
     ///
     /// # Examples
@@ -15,7 +55,7 @@ impl BitVec {
     /// # use rust_compress::bit_vec::BitVec;
     /// let mut v = BitVec::new();
     /// ```
-    pub fn new() -> BitVec {
+    pub fn new() -> Self {
         Self::with_capacity(0)
     }
 
@@ -38,23 +78,90 @@ impl BitVec {
     /// // ...but this may make the vector reallocate
     /// v.push(true);
     /// ```
-    pub fn with_capacity(capacity: usize) -> BitVec {
-        let bytes = Self::byte_pos(capacity + BITS - 1);
-        BitVec {
-            bit: BITS as u8,
-            data: Vec::with_capacity(bytes),
+    pub fn with_capacity(capacity: usize) -> Self {
+        OrderedBitVec {
+            len: 0,
+            data: Words::with_capacity(Self::words_for(capacity)),
+            order: PhantomData,
+        }
+    }
+
+    /// Constructs a `BitVec` of `bit_len` elements from `bytes`.
+    ///
+    /// `bytes` must hold exactly the bytes needed for `bit_len` elements, in
+    /// the order `O`, and the unused bits of the last byte must be zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v = BitVec::from_bytes(vec![0b1111_0000, 0b0000_0101], 11).unwrap();
+    /// assert_eq!(v.len(), 11);
+    /// assert_eq!(v.get(4), Some(true));
+    /// assert_eq!(v.into_bytes(), vec![0b1111_0000, 0b0000_0101]);
+    ///
+    /// assert!(BitVec::from_bytes(vec![0xff], 9).is_err());
+    /// assert!(BitVec::from_bytes(vec![0b1000_0000], 7).is_err());
+    ///
+    /// # use rust_compress::bit_vec::MsbBitVec;
+    /// let v = MsbBitVec::from_bytes(vec![0b1000_0000], 2).unwrap();
+    /// assert_eq!(v.get(0), Some(true));
+    /// assert_eq!(v.get(1), Some(false));
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>, bit_len: usize) -> Result<Self, FromBytesError> {
+        if bytes.len() != bit_len.div_ceil(BITS) {
+            return Err(FromBytesError::LengthMismatch {
+                bytes: bytes.len(),
+                bit_len,
+            });
+        }
+        let mut data = Words::with_capacity(Self::words_for(bit_len));
+        for chunk in bytes.chunks(WORD_BITS / BITS) {
+            let mut word = [0; WORD_BITS / BITS];
+            word[..chunk.len()].copy_from_slice(chunk);
+            data.push(u64::from_ne_bytes(word));
+        }
+        let v = OrderedBitVec {
+            len: bit_len,
+            data,
+            order: PhantomData,
+        };
+        let used = bit_len % WORD_BITS;
+        if used != 0 && v.load(v.data.len() - 1) >> used != 0 {
+            return Err(FromBytesError::NonZeroPadding);
         }
+        Ok(v)
+    }
+
+    #[inline]
+    fn words_for(len: usize) -> usize {
+        len.div_ceil(WORD_BITS)
+    }
+
+    /// Returns the logical form of word `i`.
+    #[inline]
+    fn load(&self, i: usize) -> u64 {
+        O::convert_word(self.data[i])
     }
 
+    /// Stores the logical word `word` at `i`.
     #[inline]
-    fn byte_pos(index: usize) -> usize {
-        index / BITS
+    fn store(&mut self, i: usize, word: u64) {
+        self.data[i] = O::convert_word(word);
+    }
+
+    /// Zeroes the unused bits of the last word.
+    fn clear_padding(&mut self) {
+        let used = self.len % WORD_BITS;
+        if used != 0 {
+            *self.data.last_mut().unwrap() &= O::convert_word((1 << used) - 1);
+        }
     }
 
     /// Returns the number of elements the vector can hold without
     /// reallocating.
     ///
-    /// Capacity is reserved in multiples of 8.
+    /// Capacity is reserved in multiples of 64.
     ///
     /// # Examples
     ///
@@ -64,7 +171,54 @@ impl BitVec {
     /// assert!(v.capacity() >= 10);
     /// ```
     pub fn capacity(&self) -> usize {
-        self.data.capacity() * BITS
+        self.data.capacity() * WORD_BITS
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be
+    /// inserted. May reserve more space to avoid frequent reallocations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true].into();
+    /// v.reserve(100);
+    /// assert!(v.capacity() >= 101);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        let words = Self::words_for(self.len + additional);
+        self.data.reserve(words - self.data.len());
+    }
+
+    /// Reserves the minimum capacity for exactly `additional` more elements
+    /// to be inserted, rounded up to a whole word.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true].into();
+    /// v.reserve_exact(100);
+    /// assert!(v.capacity() >= 101);
+    /// ```
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let words = Self::words_for(self.len + additional);
+        self.data.reserve_exact(words - self.data.len());
+    }
+
+    /// Shrinks the capacity of the vector as much as possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::with_capacity(100);
+    /// v.push(true);
+    /// v.shrink_to_fit();
+    /// assert!(v.capacity() >= 1);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
     }
 
     /// Returns the number of elements the vector hold.
@@ -80,7 +234,7 @@ impl BitVec {
     /// assert_eq!(v.len(), 2)
     /// ```
     pub fn len(&self) -> usize {
-        self.data.len() * BITS + self.bit as usize - BITS
+        self.len
     }
 
     /// Returns `true` if the vector contains no elements.
@@ -96,7 +250,7 @@ impl BitVec {
     /// assert!(!v.is_empty());
     /// ```
     pub fn is_empty(&self) -> bool {
-        self.len() == 0
+        self.len == 0
     }
 
     /// Appends an element to the back of a collection.
@@ -113,75 +267,107 @@ impl BitVec {
     /// v.push(false);
     /// ```
     pub fn push(&mut self, value: bool) {
-        if self.bit == BITS as u8 {
+        let bit = self.len % WORD_BITS;
+        if bit == 0 {
             self.data.push(0);
-            self.bit = 0;
         }
         if value {
-            *self.data.last_mut().unwrap() ^= 1 << self.bit;
+            *self.data.last_mut().unwrap() |= O::convert_word(1 << bit);
         }
-        self.bit += 1;
+        self.len += 1;
     }
 
-    /// Split the vector into two part.
-    /// Right part is fragment of bytes.
-    /// Left part is other.
+    /// Appends the low `nbits` bits of `value`.
+    ///
+    /// The least significant bit of the field is appended first for
+    /// [`BitVec`] and last for [`MsbBitVec`], so that `into_bytes` gives the
+    /// same bytes as an LSB-first or MSB-first bit writer respectively.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nbits` is greater than 64.
     ///
     /// # Examples
     ///
     /// ```
     /// # use rust_compress::bit_vec::BitVec;
     /// let mut v = BitVec::new();
-    /// for i in 0..12 {
-    ///     v.push(i % 2 == 0);
-    /// }
-    /// let (rest, last) = v.split_rest();
-    /// assert_eq!(rest.into_bytes(), vec![0b01010101]);
-    /// assert_eq!(last.into_bytes(), vec![0b00000101]);
+    /// v.push(true);
+    /// v.push_bits(0b1110_0110, 8);
+    /// assert_eq!(v.len(), 9);
+    /// assert_eq!(v.into_bytes(), vec![0b1100_1101, 0b0000_0001]);
+    ///
+    /// # use rust_compress::bit_vec::MsbBitVec;
+    /// let mut v = MsbBitVec::new();
+    /// v.push(true);
+    /// v.push_bits(0b1110_0110, 8);
+    /// assert_eq!(v.into_bytes(), vec![0b1111_0011, 0b0000_0000]);
     /// ```
-    pub fn split_rest(self) -> (BitVec, BitVec) {
-        if self.bit == BITS as u8 {
-            (self, BitVec::new())
+    pub fn push_bits(&mut self, value: u64, nbits: usize) {
+        assert!(nbits <= 64, "cannot push {} bits from a u64", nbits);
+        self.push_logical(O::convert_field(value, nbits), nbits);
+    }
+
+    /// Appends the low `nbits` bits of the logical word `bits`, bit 0 first.
+    /// The bits above `nbits` must be zero.
+    fn push_logical(&mut self, bits: u64, nbits: usize) {
+        if nbits == 0 {
+            return;
+        }
+        let bit = self.len % WORD_BITS;
+        if bit == 0 {
+            self.data.push(O::convert_word(bits));
         } else {
-            let mut rest = self;
-            let mut last = BitVec::with_capacity(8);
-            let b = rest.data.pop().unwrap();
-            for i in 0..rest.bit {
-                last.push((b >> i) & 1 == 1);
+            *self.data.last_mut().unwrap() |= O::convert_word(bits << bit);
+            if bit + nbits > WORD_BITS {
+                self.data.push(O::convert_word(bits >> (WORD_BITS - bit)));
             }
-            rest.bit = BITS as u8;
-            (rest, last)
         }
+        self.len += nbits;
     }
 
-    /// Modes all elements of `other` into `self`, leaving `other` empty.
+    /// Appends all 8 bits of `byte`, as by `push_bits(byte as u64, 8)`.
     ///
     /// # Examples
     ///
     /// ```
     /// # use rust_compress::bit_vec::BitVec;
-    /// let mut vec: BitVec = vec![true, false, true].into();
-    /// let mut vec2: BitVec = vec![true, true, false].into();
-    /// vec.append(&mut vec2);
-    /// assert_eq!(vec, vec![true, false, true, true, true, false].into());
-    /// assert_eq!(vec2, vec![].into());
+    /// let mut v = BitVec::new();
+    /// v.push_byte(0xa5);
+    /// v.push_byte(0x3c);
+    /// assert_eq!(v.into_bytes(), vec![0xa5, 0x3c]);
     /// ```
-    pub fn append(&mut self, other: &mut BitVec) {
-        if self.bit == BITS as u8 {
-            self.data.append(&mut other.data);
-            self.bit = other.bit;
-            other.bit = BITS as u8;
-        } else {
-            // FIXME Improve algorithm
-            for value in other.iter() {
-                self.push(value);
-            }
-            other.data.clear();
-            other.bit = BITS as u8;
+    pub fn push_byte(&mut self, byte: u8) {
+        self.push_bits(byte as u64, BITS);
+    }
+
+    /// Appends `n` in unary: `n` ones followed by a zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push_unary(3);
+    /// v.push_unary(0);
+    /// assert_eq!(v, "11100".parse().unwrap());
+    /// assert_eq!(v.read_unary(0), Some(3));
+    /// assert_eq!(v.read_unary(4), Some(0));
+    /// ```
+    pub fn push_unary(&mut self, n: usize) {
+        self.reserve(n + 1);
+        for _ in 0..n / WORD_BITS {
+            self.push_logical(!0, WORD_BITS);
         }
+        let rest = n % WORD_BITS;
+        self.push_logical((1 << rest) - 1, rest + 1);
     }
 
-    /// Convert into bytes `Vec<u8>`.
+    /// Appends `8 * bytes.len()` bits from bytes packed in the order `O`, as
+    /// if by calling `push_byte` for each of them.
+    ///
+    /// Eight bytes are appended at a time, copied as they are when the
+    /// vector ends on a word boundary and shifted into place otherwise.
     ///
     /// # Examples
     ///
@@ -189,110 +375,2463 @@ impl BitVec {
     /// # use rust_compress::bit_vec::BitVec;
     /// let mut v = BitVec::new();
     /// v.push(true);
-    /// v.push(false);
-    /// v.push(true);
-    /// assert_eq!(v.into_bytes(), vec![0b00000101]);
+    /// v.extend_from_byte_slice(&[0xff, 0x00]);
+    /// assert_eq!(v.len(), 17);
+    /// assert_eq!(v.into_bytes(), vec![0xff, 0x01, 0x00]);
     /// ```
-    pub fn into_bytes(self) -> Vec<u8> {
-        self.data
+    pub fn extend_from_byte_slice(&mut self, bytes: &[u8]) {
+        self.reserve(bytes.len() * BITS);
+        let mut chunks = bytes.chunks_exact(WORD_BITS / BITS);
+        if self.len.is_multiple_of(WORD_BITS) {
+            for chunk in &mut chunks {
+                self.data
+                    .push(u64::from_ne_bytes(chunk.try_into().unwrap()));
+            }
+            self.len += (bytes.len() - chunks.remainder().len()) * BITS;
+        } else {
+            for chunk in &mut chunks {
+                let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+                self.push_logical(O::convert_word(word), WORD_BITS);
+            }
+        }
+        for b in chunks.remainder() {
+            self.push_logical(O::convert_byte(*b) as u64, BITS);
+        }
     }
 
-    /// Make iterator of bit.
-    pub fn iter(&self) -> Iter {
-        Iter {
-            pos: 0,
-            last_bit: self.bit,
-            bit: 0,
-            data: &self.data,
+    /// Removes the last element from a vector and returns it, or `None` if it
+    /// is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, false, true].into();
+    /// assert_eq!(v.pop(), Some(true));
+    /// assert_eq!(v.pop(), Some(false));
+    /// assert_eq!(v, vec![true].into());
+    /// ```
+    pub fn pop(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let bit = self.len % WORD_BITS;
+        let last = self.data.last_mut().unwrap();
+        let mask = O::convert_word(1 << bit);
+        let value = *last & mask != 0;
+        *last &= !mask;
+        if bit == 0 {
+            self.data.pop();
         }
+        Some(value)
     }
-}
 
-impl From<Vec<bool>> for BitVec {
-    fn from(bits: Vec<bool>) -> BitVec {
-        let mut v = BitVec::with_capacity(bits.len());
-        for bit in bits {
-            v.push(bit);
+    /// Returns the last element of the vector, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// assert_eq!(v.last(), None);
+    /// v.push(true);
+    /// v.push(false);
+    /// assert_eq!(v.last(), Some(false));
+    /// ```
+    pub fn last(&self) -> Option<bool> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(unsafe { self.get_unchecked(self.len - 1) })
         }
-        v
     }
-}
 
-impl From<&[bool]> for BitVec {
-    fn from(bits: &[bool]) -> BitVec {
-        let mut v = BitVec::with_capacity(bits.len());
-        for bit in bits {
-            v.push(*bit);
+    /// Returns the element at `index`, or `None` if out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true].into();
+    /// assert_eq!(v.get(1), Some(false));
+    /// assert_eq!(v.get(2), Some(true));
+    /// assert_eq!(v.get(3), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index < self.len() {
+            Some(unsafe { self.get_unchecked(index) })
+        } else {
+            None
         }
-        v
     }
-}
 
-impl Default for BitVec {
-    fn default() -> Self {
-        BitVec::new()
+    /// Returns the element at `index`, without doing bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true].into();
+    /// assert!(unsafe { v.get_unchecked(2) });
+    /// ```
+    pub unsafe fn get_unchecked(&self, index: usize) -> bool {
+        let w = *self.data.get_unchecked(index / WORD_BITS);
+        (O::convert_word(w) >> (index % WORD_BITS)) & 1 == 1
     }
-}
 
-pub struct Iter<'a> {
-    pos: usize,
-    last_bit: u8,
-    bit: u8,
-    data: &'a [u8],
-}
+    /// Sets the element at `index` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, false, true].into();
+    /// v.set(0, false);
+    /// v.set(1, true);
+    /// assert_eq!(v, vec![false, true, true].into());
+    /// ```
+    pub fn set(&mut self, index: usize, value: bool) {
+        let len = self.len();
+        assert!(
+            index < len,
+            "index out of bounds: the len is {} but the index is {}",
+            len,
+            index
+        );
+        unsafe { self.set_unchecked(index, value) }
+    }
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = bool;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.bit == BITS as u8 || self.pos + 1 == self.data.len() && self.bit == self.last_bit {
-            self.bit = 0;
-            self.pos += 1;
+    /// Sets the element at `index` to `value`, without doing bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![false; 3].into();
+    /// unsafe { v.set_unchecked(1, true) };
+    /// assert_eq!(v, vec![false, true, false].into());
+    /// ```
+    pub unsafe fn set_unchecked(&mut self, index: usize, value: bool) {
+        let w = self.data.get_unchecked_mut(index / WORD_BITS);
+        let mask = O::convert_word(1 << (index % WORD_BITS));
+        if value {
+            *w |= mask;
+        } else {
+            *w &= !mask;
         }
-        if self.pos == self.data.len() {
-            return None;
+    }
+
+    /// Sets all elements in `range` to `value`.
+    ///
+    /// Whole words are filled at once and only the words at the edges of
+    /// the range are masked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![false; 6].into();
+    /// v.set_range(1..4, true);
+    /// assert_eq!(v, vec![false, true, true, true, false, false].into());
+    /// v.set_range(..=1, false);
+    /// assert_eq!(v, vec![false, false, true, true, false, false].into());
+    /// ```
+    pub fn set_range<R: RangeBounds<usize>>(&mut self, range: R, value: bool) {
+        let (start, end) = resolve_range(range, self.len);
+        if start == end {
+            return;
+        }
+        let first = start / WORD_BITS;
+        let last = (end - 1) / WORD_BITS;
+        let fill = |w: &mut u64, mask: u64| {
+            let mask = O::convert_word(mask);
+            if value {
+                *w |= mask;
+            } else {
+                *w &= !mask;
+            }
+        };
+        let head = !0 << (start % WORD_BITS);
+        let tail = !0 >> (WORD_BITS - 1 - (end - 1) % WORD_BITS);
+        if first == last {
+            fill(&mut self.data[first], head & tail);
+            return;
+        }
+        fill(&mut self.data[first], head);
+        for w in self.data[first + 1..last].iter_mut() {
+            *w = if value { !0 } else { 0 };
         }
-        let b = (self.data[self.pos] >> self.bit) & 1;
-        self.bit += 1;
-        Some(b == 1)
+        fill(&mut self.data[last], tail);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Inserts an element at position `index`, shifting all elements after
+    /// it to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, false, true].into();
+    /// v.insert(1, true);
+    /// assert_eq!(v, vec![true, true, false, true].into());
+    /// v.insert(4, false);
+    /// assert_eq!(v, vec![true, true, false, true, false].into());
+    /// ```
+    pub fn insert(&mut self, index: usize, value: bool) {
+        let len = self.len();
+        assert!(
+            index <= len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            len
+        );
+        self.push(false);
+        let word = index / WORD_BITS;
+        let bit = index % WORD_BITS;
+        let low = (1 << bit) - 1;
+        let w = self.load(word);
+        let mut carry = w >> (WORD_BITS - 1);
+        self.store(
+            word,
+            (w & low) | ((value as u64) << bit) | ((w & !low) << 1),
+        );
+        for i in word + 1..self.data.len() {
+            let w = self.load(i);
+            self.store(i, (w << 1) | carry);
+            carry = w >> (WORD_BITS - 1);
+        }
+    }
 
-    #[test]
-    fn len() {
-        let mut v = BitVec::new();
-        assert_eq!(v.len(), 0);
-        v.push(false);
-        assert_eq!(v.len(), 1);
-        for _ in 0..6 {
-            v.push(false);
+    /// Removes and returns the element at position `index`, shifting all
+    /// elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, false, true].into();
+    /// assert_eq!(v.remove(1), false);
+    /// assert_eq!(v, vec![true, true].into());
+    /// ```
+    pub fn remove(&mut self, index: usize) -> bool {
+        let len = self.len();
+        assert!(
+            index < len,
+            "removal index (is {}) should be < len (is {})",
+            index,
+            len
+        );
+        let value = unsafe { self.get_unchecked(index) };
+        let word = index / WORD_BITS;
+        let low = (1 << (index % WORD_BITS)) - 1;
+        let w = self.load(word);
+        let mut shifted = (w & low) | ((w >> 1) & !low);
+        for i in word + 1..self.data.len() {
+            let w = self.load(i);
+            self.store(i - 1, shifted | (w << (WORD_BITS - 1)));
+            shifted = w >> 1;
         }
-        assert_eq!(v.len(), 7);
-        v.push(false);
-        assert_eq!(v.len(), 8);
-        v.push(false);
-        assert_eq!(v.len(), 9);
+        let last = self.data.len() - 1;
+        self.store(last, shifted);
+        // drop the vacated last position, which now holds a zero
+        self.pop();
+        value
     }
 
-    #[test]
-    fn split_rest() {
-        let v = BitVec::new();
-        let (rest, last) = v.split_rest();
-        assert_eq!(rest, BitVec::new());
-        assert_eq!(last, BitVec::new());
+    /// Reads `n` bits starting at bit offset `pos` and returns them as an
+    /// integer field, the inverse of `push_bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64 or the range is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push_bits(0b10110, 5);
+    /// v.push_bits(0x1234, 16);
+    /// assert_eq!(v.read_bits(0, 5), 0b10110);
+    /// assert_eq!(v.read_bits(5, 16), 0x1234);
+    /// assert_eq!(v.read_bits(1, 3), 0b011);
+    /// ```
+    pub fn read_bits(&self, pos: usize, n: usize) -> u64 {
+        self.as_bit_slice().read_bits(pos, n)
+    }
 
-        let v: BitVec = vec![true; 8].into();
-        let (rest, last) = v.split_rest();
-        assert_eq!(rest, vec![true; 8].into());
-        assert_eq!(last, BitVec::new());
+    /// Reads a unary-coded number starting at bit offset `pos`, the inverse
+    /// of `push_unary`, or returns `None` if no terminating zero follows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is greater than the length.
+    pub fn read_unary(&self, pos: usize) -> Option<usize> {
+        self.slice(pos..).iter().read_unary()
+    }
 
-        let v: BitVec = vec![true; 9].into();
-        let (rest, last) = v.split_rest();
-        assert_eq!(rest, vec![true; 8].into());
-        assert_eq!(last, vec![true].into());
+    /// Overwrites the `n` bits starting at bit offset `pos` with the low `n`
+    /// bits of `value`, laid out as `push_bits` would.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64 or the range is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push_bits(0, 12);
+    /// v.write_bits(3, 0b10110, 5);
+    /// assert_eq!(v.read_bits(3, 5), 0b10110);
+    /// assert_eq!(v.count_ones(), 3);
+    /// ```
+    pub fn write_bits(&mut self, pos: usize, value: u64, n: usize) {
+        assert!(n <= 64, "cannot write {} bits from a u64", n);
+        assert!(
+            pos + n <= self.len,
+            "range end index {} out of range for slice of length {}",
+            pos + n,
+            self.len
+        );
+        if n == 0 {
+            return;
+        }
+        let bits = O::convert_field(value, n);
+        let mask = if n == WORD_BITS { !0 } else { (1 << n) - 1 };
+        let word = pos / WORD_BITS;
+        let bit = pos % WORD_BITS;
+        let w = self.load(word);
+        self.store(word, (w & !(mask << bit)) | (bits << bit));
+        if bit + n > WORD_BITS {
+            let shift = WORD_BITS - bit;
+            let w = self.load(word + 1);
+            self.store(word + 1, (w & !(mask >> shift)) | (bits >> shift));
+        }
+    }
+
+    /// Returns the number of elements set to `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true].into();
+    /// assert_eq!(v.count_ones(), 3);
+    /// ```
+    pub fn count_ones(&self) -> usize {
+        // unused bits of the last word are always zero
+        self.data.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns the number of elements set to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true].into();
+    /// assert_eq!(v.count_zeros(), 1);
+    /// ```
+    pub fn count_zeros(&self) -> usize {
+        self.len() - self.count_ones()
+    }
+
+    /// Returns the number of positions at which `self` and `other` differ,
+    /// computed a word at a time with XOR and popcount.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vectors have different lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let a: BitVec = "1011001".parse().unwrap();
+    /// let b: BitVec = "1110011".parse().unwrap();
+    /// assert_eq!(a.hamming_distance(&b), 3);
+    /// ```
+    pub fn hamming_distance(&self, other: &Self) -> usize {
+        assert_eq!(
+            self.len, other.len,
+            "hamming distance of bit vectors with different lengths"
+        );
+        self.data
+            .iter()
+            .zip(other.data.iter())
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of elements set to `true` in `0..index`.
+    ///
+    /// This scans the vector; use [`RankSelectIndex`] for repeated queries.
+    ///
+    /// [`RankSelectIndex`]: crate::rank_select::RankSelectIndex
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true, false].into();
+    /// assert_eq!(v.rank1(0), 0);
+    /// assert_eq!(v.rank1(3), 2);
+    /// assert_eq!(v.rank1(5), 3);
+    /// ```
+    pub fn rank1(&self, index: usize) -> usize {
+        assert!(
+            index <= self.len(),
+            "rank index {} out of range for length {}",
+            index,
+            self.len()
+        );
+        let word = index / WORD_BITS;
+        let full: usize = self.data[..word]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+        let rest = index % WORD_BITS;
+        if rest == 0 {
+            full
+        } else {
+            let w = self.load(word) & ((1 << rest) - 1);
+            full + w.count_ones() as usize
+        }
+    }
+
+    /// Returns the number of elements set to `false` in `0..index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true, false].into();
+    /// assert_eq!(v.rank0(3), 1);
+    /// assert_eq!(v.rank0(5), 2);
+    /// ```
+    pub fn rank0(&self, index: usize) -> usize {
+        index - self.rank1(index)
+    }
+
+    /// Returns the position of the `k`-th (counted from zero) element set to
+    /// `true`, or `None` if there are not that many.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true, false].into();
+    /// assert_eq!(v.select1(0), Some(0));
+    /// assert_eq!(v.select1(2), Some(3));
+    /// assert_eq!(v.select1(3), None);
+    /// ```
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        self.select(k, true)
+    }
+
+    /// Returns the position of the `k`-th (counted from zero) element set to
+    /// `false`, or `None` if there are not that many.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true, false].into();
+    /// assert_eq!(v.select0(1), Some(4));
+    /// assert_eq!(v.select0(2), None);
+    /// ```
+    pub fn select0(&self, k: usize) -> Option<usize> {
+        self.select(k, false)
+    }
+
+    fn select(&self, k: usize, value: bool) -> Option<usize> {
+        let mut k = k;
+        for i in 0..self.data.len() {
+            let w = if value { self.load(i) } else { !self.load(i) };
+            let ones = w.count_ones() as usize;
+            if k < ones {
+                let pos = i * WORD_BITS + select_in_word(w, k);
+                return if pos < self.len() { Some(pos) } else { None };
+            }
+            k -= ones;
+        }
+        None
+    }
+
+    /// Returns the position of the first occurrence of `pattern`, or `None`
+    /// if it does not occur. An empty pattern matches at position 0.
+    ///
+    /// Candidate positions are compared 64 bits at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = "0010110111".parse().unwrap();
+    /// assert_eq!(v.find(&"1011".parse().unwrap()), Some(2));
+    /// assert_eq!(v.find(&"111".parse().unwrap()), Some(7));
+    /// assert_eq!(v.find(&"1111".parse().unwrap()), None);
+    /// ```
+    pub fn find(&self, pattern: &Self) -> Option<usize> {
+        let m = pattern.len();
+        if m > self.len {
+            return None;
+        }
+        let head = m.min(WORD_BITS);
+        let first = pattern.read_bits(0, head);
+        (0..=self.len - m).find(|&pos| {
+            self.read_bits(pos, head) == first
+                && (head..m).step_by(WORD_BITS).all(|offset| {
+                    let n = (m - offset).min(WORD_BITS);
+                    self.read_bits(pos + offset, n) == pattern.read_bits(offset, n)
+                })
+        })
+    }
+
+    /// Split the vector into two part.
+    /// Right part is fragment of bytes.
+    /// Left part is other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// for i in 0..12 {
+    ///     v.push(i % 2 == 0);
+    /// }
+    /// let (rest, last) = v.split_rest();
+    /// assert_eq!(rest.into_bytes(), vec![0b01010101]);
+    /// assert_eq!(last.into_bytes(), vec![0b00000101]);
+    /// ```
+    pub fn split_rest(self) -> (Self, Self) {
+        let mut rest = self;
+        let at = rest.len - rest.len % BITS;
+        let last = rest.split_off(at);
+        (rest, last)
+    }
+
+    /// Splits the vector into two at the given index.
+    ///
+    /// Returns a newly allocated vector containing the elements in
+    /// `at..len`, leaving `self` with the elements `0..at`. `at` does not
+    /// have to be byte-aligned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is greater than the length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, false, false, true, true].into();
+    /// let tail = v.split_off(3);
+    /// assert_eq!(v, vec![true, false, false].into());
+    /// assert_eq!(tail, vec![true, true].into());
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(
+            at <= len,
+            "`at` split index (is {}) should be <= len (is {})",
+            at,
+            len
+        );
+        if at.is_multiple_of(WORD_BITS) {
+            let tail = OrderedBitVec {
+                len: len - at,
+                data: self.data.split_off(at / WORD_BITS),
+                order: PhantomData,
+            };
+            self.len = at;
+            tail
+        } else {
+            let tail = self.slice(at..).to_bit_vec();
+            self.truncate(at);
+            tail
+        }
+    }
+
+    /// Modes all elements of `other` into `self`, leaving `other` empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut vec: BitVec = vec![true, false, true].into();
+    /// let mut vec2: BitVec = vec![true, true, false].into();
+    /// vec.append(&mut vec2);
+    /// assert_eq!(vec, vec![true, false, true, true, true, false].into());
+    /// assert_eq!(vec2, vec![].into());
+    /// ```
+    pub fn append(&mut self, other: &mut Self) {
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.data.append(&mut other.data);
+            self.len += other.len;
+            other.len = 0;
+        } else {
+            self.extend_from_bitvec(other);
+            other.clear();
+        }
+    }
+
+    /// Appends all elements of `other`, shifting whole words into place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut vec: BitVec = vec![true, false, true].into();
+    /// let vec2: BitVec = vec![true; 9].into();
+    /// vec.extend_from_bitvec(&vec2);
+    /// assert_eq!(vec.len(), 12);
+    /// assert_eq!(vec.into_bytes(), vec![0b1111_1101, 0b0000_1111]);
+    /// ```
+    pub fn extend_from_bitvec(&mut self, other: &Self) {
+        let bit = self.len % WORD_BITS;
+        if bit == 0 {
+            self.data.extend_from_slice(&other.data);
+            self.len += other.len;
+            return;
+        }
+        let len = self.len + other.len;
+        self.data.reserve(other.data.len());
+        for i in 0..other.data.len() {
+            let w = other.load(i);
+            *self.data.last_mut().unwrap() |= O::convert_word(w << bit);
+            self.data.push(O::convert_word(w >> (WORD_BITS - bit)));
+        }
+        // the last pushed word only holds padding if `other` ended early enough
+        self.data.truncate(Self::words_for(len));
+        self.len = len;
+    }
+
+    /// Appends all elements of `slice`, copying up to 64 bits at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut vec: BitVec = vec![true, false, true].into();
+    /// let vec2: BitVec = vec![false, true, true, false].into();
+    /// vec.extend_from_bit_slice(vec2.slice(1..3));
+    /// assert_eq!(vec, vec![true, false, true, true, true].into());
+    /// ```
+    pub fn extend_from_bit_slice(&mut self, slice: BitSlice<'_, O>) {
+        let mut pos = 0;
+        while pos < slice.len() {
+            let n = (slice.len() - pos).min(64);
+            self.push_bits(slice.read_bits(pos, n), n);
+            pos += n;
+        }
+    }
+
+    /// Reverses the order of elements in the vector, in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, true, false, true, false].into();
+    /// v.reverse();
+    /// assert_eq!(v, vec![false, true, false, true, true].into());
+    /// ```
+    pub fn reverse(&mut self) {
+        // reversing the padded words moves the padding to the front, which
+        // is then shifted out
+        self.data.reverse();
+        for i in 0..self.data.len() {
+            let w = self.load(i);
+            self.store(i, w.reverse_bits());
+        }
+        let pad = self.data.len() * WORD_BITS - self.len;
+        if pad == 0 {
+            return;
+        }
+        let n = self.data.len();
+        for i in 0..n {
+            let next = if i + 1 < n { self.load(i + 1) } else { 0 };
+            let w = self.load(i);
+            self.store(i, (w >> pad) | (next << (WORD_BITS - pad)));
+        }
+    }
+
+    /// Rotates the vector in place such that the first `n % len` elements
+    /// move to the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, true, false, true, false].into();
+    /// v.rotate_left(2);
+    /// assert_eq!(v, vec![false, true, false, true, true].into());
+    /// v.rotate_left(7);
+    /// assert_eq!(v, vec![false, true, true, false, true].into());
+    /// ```
+    pub fn rotate_left(&mut self, n: usize) {
+        if self.is_empty() {
+            return;
+        }
+        let n = n % self.len();
+        if n == 0 {
+            return;
+        }
+        let mut rotated = self.split_off(n);
+        rotated.extend_from_bitvec(self);
+        *self = rotated;
+    }
+
+    /// Rotates the vector in place such that the last `n % len` elements
+    /// move to the front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, true, false, true, false].into();
+    /// v.rotate_right(2);
+    /// assert_eq!(v, vec![true, false, true, true, false].into());
+    /// ```
+    pub fn rotate_right(&mut self, n: usize) {
+        if self.is_empty() {
+            return;
+        }
+        let n = n % self.len();
+        self.rotate_left(self.len() - n);
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping
+    /// the rest.
+    ///
+    /// If `len` is greater than the current length, this has no effect.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true; 10].into();
+    /// v.truncate(3);
+    /// assert_eq!(v, vec![true; 3].into());
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        self.data.truncate(Self::words_for(len));
+        self.len = len;
+        self.clear_padding();
+    }
+
+    /// Clears the vector, removing all elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true; 10].into();
+    /// v.clear();
+    /// assert!(v.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.len = 0;
+    }
+
+    /// Removes the elements in `range` from the vector and returns them as
+    /// an iterator, shifting the elements after the range to close the gap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true, true, false, true, false].into();
+    /// let header: Vec<bool> = v.drain(..2).collect();
+    /// assert_eq!(header, vec![true, true]);
+    /// assert_eq!(v, vec![false, true, false].into());
+    /// ```
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<O> {
+        let (start, end) = resolve_range(range, self.len());
+        let drained = self.slice(start..end).to_bit_vec();
+        let tail = self.slice(end..).to_bit_vec();
+        self.truncate(start);
+        self.extend_from_bitvec(&tail);
+        Drain {
+            iter: drained.into_iter(),
+        }
+    }
+
+    /// Convert into bytes `Vec<u8>`, packed in the order `O`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push(true);
+    /// v.push(false);
+    /// v.push(true);
+    /// assert_eq!(v.into_bytes(), vec![0b00000101]);
+    /// ```
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    /// Returns the underlying bytes, packed in the order `O`.
+    ///
+    /// The unused bits of the last byte are always zero. Only the bytes
+    /// holding elements are returned, not the whole last word.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v: BitVec = vec![true; 11].into();
+    /// v.set(9, false);
+    /// assert_eq!(v.as_bytes(), &[0xff, 0b0000_0101]);
+    /// v.truncate(9);
+    /// assert_eq!(v.as_bytes(), &[0xff, 0b0000_0001]);
+    /// ```
+    pub fn as_bytes(&self) -> &[u8] {
+        let bytes = self.len.div_ceil(BITS);
+        // SAFETY: `data` holds at least `bytes` initialized bytes, and `u8`
+        // has no alignment requirement. Stored words hold the stream bytes
+        // in memory order whichever the endianness of the target.
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const u8, bytes) }
+    }
+
+    /// Writes the vector as its bit length, encoded as an unsigned LEB128
+    /// varint, followed by the bytes returned by `as_bytes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true; 9].into();
+    /// let mut out = Vec::new();
+    /// v.write_to(&mut out).unwrap();
+    /// assert_eq!(out, vec![9, 0xff, 0x01]);
+    /// ```
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        varint::write_u64(&mut writer, self.len as u64)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    /// Reads a vector written by `write_to`.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the input ends early and
+    /// with `ErrorKind::InvalidData` if the length is malformed or the
+    /// padding bits are not zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v = BitVec::read_from(&[9, 0xff, 0x01][..]).unwrap();
+    /// assert_eq!(v, vec![true; 9].into());
+    /// assert!(BitVec::read_from(&[9, 0xff][..]).is_err());
+    /// ```
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let len = varint::read_u64(&mut reader)?;
+        let len = usize::try_from(len).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "bit length overflows usize")
+        })?;
+        let bytes = len.div_ceil(BITS);
+        let mut buf = Vec::new();
+        // read through `take` rather than allocating `bytes` up front, so a
+        // corrupt length cannot exhaust memory
+        reader.take(bytes as u64).read_to_end(&mut buf)?;
+        if buf.len() != bytes {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Self::from_bytes(buf, len).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Make iterator of bit.
+    pub fn iter(&self) -> Iter<'_, O> {
+        self.as_bit_slice().iter()
+    }
+
+    /// Returns an iterator over the positions of elements set to `true`, in
+    /// increasing order. Words without any set bit are skipped at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![false, true, true, false, true].into();
+    /// assert_eq!(v.iter_ones().collect::<Vec<_>>(), vec![1, 2, 4]);
+    /// ```
+    pub fn iter_ones(&self) -> IterOnes<'_, O> {
+        IterOnes::new(&self.data, self.len, 0)
+    }
+
+    /// Returns an iterator over the positions of elements set to `false`,
+    /// in increasing order. Words without any clear bit are skipped at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![false, true, true, false, true].into();
+    /// assert_eq!(v.iter_zeros().collect::<Vec<_>>(), vec![0, 3]);
+    /// ```
+    pub fn iter_zeros(&self) -> IterOnes<'_, O> {
+        IterOnes::new(&self.data, self.len, !0)
+    }
+
+    /// Returns an iterator over all overlapping windows of `n` bits, each
+    /// given as the integer field `read_bits(pos, n)` for successive `pos`.
+    ///
+    /// Each window is computed from the previous one with a shift.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0 or greater than 64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push_bits(0b10110, 5);
+    /// let windows: Vec<u64> = v.windows(3).collect();
+    /// assert_eq!(windows, vec![0b110, 0b011, 0b101]);
+    /// ```
+    pub fn windows(&self, n: usize) -> Windows<'_, O> {
+        assert!(
+            0 < n && n <= 64,
+            "window size must be in 1..=64, but is {}",
+            n
+        );
+        let window = if n <= self.len {
+            O::convert_field(self.read_bits(0, n), n)
+        } else {
+            0
+        };
+        Windows {
+            data: &self.data,
+            n,
+            pos: n,
+            end: self.len,
+            window,
+            order: PhantomData,
+        }
+    }
+
+    /// Borrows the whole vector as a `BitSlice`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true].into();
+    /// assert_eq!(v.as_bit_slice().len(), 3);
+    /// ```
+    pub fn as_bit_slice(&self) -> BitSlice<'_, O> {
+        BitSlice {
+            data: &self.data,
+            start: 0,
+            end: self.len(),
+            order: PhantomData,
+        }
+    }
+
+    /// Borrows the elements in `range` as a `BitSlice`, without copying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true, false].into();
+    /// let s = v.slice(1..4);
+    /// assert_eq!(s.len(), 3);
+    /// assert_eq!(s.to_bit_vec(), vec![false, true, true].into());
+    /// ```
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitSlice<'_, O> {
+        self.as_bit_slice().slice(range)
+    }
+}
+
+impl<O: BitOrder> From<Vec<bool>> for OrderedBitVec<O> {
+    fn from(bits: Vec<bool>) -> Self {
+        bits.into_iter().collect()
+    }
+}
+
+impl<O: BitOrder> From<&[bool]> for OrderedBitVec<O> {
+    fn from(bits: &[bool]) -> Self {
+        bits.iter().cloned().collect()
+    }
+}
+
+impl<O: BitOrder> Extend<bool> for OrderedBitVec<O> {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
+
+impl<'a, O: BitOrder> Extend<&'a bool> for OrderedBitVec<O> {
+    fn extend<I: IntoIterator<Item = &'a bool>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<O: BitOrder> FromIterator<bool> for OrderedBitVec<O> {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut v = Self::new();
+        v.extend(iter);
+        v
+    }
+}
+
+/// Formats the elements as a string of `0` and `1`, first element first.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::BitVec;
+/// let v: BitVec = vec![true, false, true, true].into();
+/// assert_eq!(v.to_string(), "1011");
+/// ```
+impl<O: BitOrder> fmt::Display for OrderedBitVec<O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.as_bit_slice(), f)
+    }
+}
+
+/// Parses a string of `0` and `1`, first element first. Underscores and
+/// whitespace are ignored as separators.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::BitVec;
+/// let v: BitVec = "1011_0 01".parse().unwrap();
+/// assert_eq!(v, vec![true, false, true, true, false, false, true].into());
+/// assert!("10a1".parse::<BitVec>().is_err());
+/// ```
+impl<O: BitOrder> FromStr for OrderedBitVec<O> {
+    type Err = ParseBitVecError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut v = Self::with_capacity(s.len());
+        for (index, c) in s.char_indices() {
+            match c {
+                '0' => v.push(false),
+                '1' => v.push(true),
+                '_' => {}
+                c if c.is_whitespace() => {}
+                c => return Err(ParseBitVecError { index, invalid: c }),
+            }
+        }
+        Ok(v)
+    }
+}
+
+impl<O: BitOrder> Default for OrderedBitVec<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O: BitOrder> OrderedBitVec<O> {
+    /// Combines `other` into `self` word by word with `f`.
+    ///
+    /// The shorter operand is treated as if it were extended with `false`
+    /// up to the length of the longer one.
+    fn zip_words_with<F: Fn(u64, u64) -> u64>(&mut self, other: &Self, f: F) {
+        if other.len() > self.len() {
+            self.data.resize(other.data.len(), 0);
+            self.len = other.len;
+        }
+        let (head, tail) = self.data.split_at_mut(other.data.len());
+        for (a, b) in head.iter_mut().zip(other.data.iter()) {
+            *a = f(*a, *b);
+        }
+        for a in tail {
+            *a = f(*a, 0);
+        }
+    }
+}
+
+macro_rules! impl_bit_op {
+    ($op:ident, $method:ident, $op_assign:ident, $method_assign:ident, $f:expr) => {
+        impl<O: BitOrder> $op_assign<&OrderedBitVec<O>> for OrderedBitVec<O> {
+            fn $method_assign(&mut self, other: &OrderedBitVec<O>) {
+                self.zip_words_with(other, $f);
+            }
+        }
+
+        impl<O: BitOrder> $op_assign for OrderedBitVec<O> {
+            fn $method_assign(&mut self, other: OrderedBitVec<O>) {
+                self.zip_words_with(&other, $f);
+            }
+        }
+
+        impl<O: BitOrder> $op<&OrderedBitVec<O>> for &OrderedBitVec<O> {
+            type Output = OrderedBitVec<O>;
+            fn $method(self, other: &OrderedBitVec<O>) -> OrderedBitVec<O> {
+                let mut v = self.clone();
+                v.zip_words_with(other, $f);
+                v
+            }
+        }
+
+        impl<O: BitOrder> $op for OrderedBitVec<O> {
+            type Output = OrderedBitVec<O>;
+            fn $method(mut self, other: OrderedBitVec<O>) -> OrderedBitVec<O> {
+                self.zip_words_with(&other, $f);
+                self
+            }
+        }
+    };
+}
+
+// Operands of unequal length are zero-extended, so the result is as long as
+// the longer operand.
+impl_bit_op!(BitAnd, bitand, BitAndAssign, bitand_assign, |a, b| a & b);
+impl_bit_op!(BitOr, bitor, BitOrAssign, bitor_assign, |a, b| a | b);
+impl_bit_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, |a, b| a ^ b);
+
+impl<O: BitOrder> Not for OrderedBitVec<O> {
+    type Output = OrderedBitVec<O>;
+    fn not(mut self) -> OrderedBitVec<O> {
+        for w in self.data.iter_mut() {
+            *w = !*w;
+        }
+        self.clear_padding();
+        self
+    }
+}
+
+impl<O: BitOrder> Not for &OrderedBitVec<O> {
+    type Output = OrderedBitVec<O>;
+    fn not(self) -> OrderedBitVec<O> {
+        !self.clone()
+    }
+}
+
+/// An error returned by [`BitVec::from_bytes`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FromBytesError {
+    /// The number of bytes does not match the bit length.
+    LengthMismatch { bytes: usize, bit_len: usize },
+    /// The unused bits of the last byte are not zero.
+    NonZeroPadding,
+}
+
+impl fmt::Display for FromBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FromBytesError::LengthMismatch { bytes, bit_len } => {
+                write!(f, "{} bytes cannot hold exactly {} bits", bytes, bit_len)
+            }
+            FromBytesError::NonZeroPadding => write!(f, "padding bits are not zero"),
+        }
+    }
+}
+
+impl error::Error for FromBytesError {}
+
+/// An error returned when parsing a `BitVec` from a string fails.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseBitVecError {
+    index: usize,
+    invalid: char,
+}
+
+impl fmt::Display for ParseBitVecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid character {:?} at byte {} in bit string",
+            self.invalid, self.index
+        )
+    }
+}
+
+impl error::Error for ParseBitVecError {}
+
+/// A borrowed view into a range of a `BitVec`.
+///
+/// `BitSlice` is to `BitVec` what `&[T]` is to `Vec<T>`.
+#[derive(Clone, Copy, Debug)]
+pub struct BitSlice<'a, O: BitOrder = LsbFirst> {
+    data: &'a [u64],
+    start: usize,
+    end: usize,
+    order: PhantomData<O>,
+}
+
+impl<'a, O: BitOrder> BitSlice<'a, O> {
+    /// Returns the number of elements in the slice.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the slice contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the element at `index`, or `None` if out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true].into();
+    /// let s = v.slice(1..);
+    /// assert_eq!(s.get(0), Some(false));
+    /// assert_eq!(s.get(1), Some(true));
+    /// assert_eq!(s.get(2), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index < self.len() {
+            Some(get_bit::<O>(self.data, self.start + index))
+        } else {
+            None
+        }
+    }
+
+    /// Borrows the elements in `range` as a `BitSlice`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, true, true, false].into();
+    /// let s = v.slice(1..).slice(..2);
+    /// assert_eq!(s.to_bit_vec(), vec![false, true].into());
+    /// ```
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitSlice<'a, O> {
+        let (start, end) = resolve_range(range, self.len());
+        BitSlice {
+            data: self.data,
+            start: self.start + start,
+            end: self.start + end,
+            order: PhantomData,
+        }
+    }
+
+    /// Reads `n` bits starting at bit offset `pos` of the slice as an
+    /// integer field, like `BitVec::read_bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64 or the range is out of bounds.
+    pub fn read_bits(&self, pos: usize, n: usize) -> u64 {
+        assert!(n <= 64, "cannot read {} bits into a u64", n);
+        assert!(
+            pos + n <= self.len(),
+            "range end index {} out of range for slice of length {}",
+            pos + n,
+            self.len()
+        );
+        read_bits::<O>(self.data, self.start + pos, n)
+    }
+
+    /// Make iterator of bit.
+    pub fn iter(&self) -> Iter<'a, O> {
+        Iter {
+            data: self.data,
+            pos: self.start,
+            end: self.end,
+            order: PhantomData,
+        }
+    }
+
+    /// Copies the slice into a new `BitVec`.
+    pub fn to_bit_vec(&self) -> OrderedBitVec<O> {
+        let mut v = OrderedBitVec::with_capacity(self.len());
+        v.extend_from_bit_slice(*self);
+        v
+    }
+}
+
+impl<'a, 'b, O: BitOrder> PartialEq<BitSlice<'b, O>> for BitSlice<'a, O> {
+    fn eq(&self, other: &BitSlice<'b, O>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'a, O: BitOrder> fmt::Display for BitSlice<'a, O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, O: BitOrder> Eq for BitSlice<'a, O> {}
+
+impl<'a, O: BitOrder> IntoIterator for BitSlice<'a, O> {
+    type Item = bool;
+    type IntoIter = Iter<'a, O>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, O: BitOrder> From<BitSlice<'a, O>> for OrderedBitVec<O> {
+    fn from(slice: BitSlice<'a, O>) -> Self {
+        slice.to_bit_vec()
+    }
+}
+
+/// Resolves `range` against a sequence of length `len`.
+///
+/// # Panics
+///
+/// Panics if the range is decreasing or extends past `len`.
+fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end + 1,
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end,
+        "slice index starts at {} but ends at {}",
+        start,
+        end
+    );
+    assert!(
+        end <= len,
+        "range end index {} out of range for slice of length {}",
+        end,
+        len
+    );
+    (start, end)
+}
+
+#[inline]
+fn get_bit<O: BitOrder>(data: &[u64], index: usize) -> bool {
+    (O::convert_word(data[index / WORD_BITS]) >> (index % WORD_BITS)) & 1 == 1
+}
+
+/// Returns the position of the `k`-th (counted from zero) set bit of `word`.
+///
+/// `word` must have more than `k` bits set.
+#[inline]
+pub(crate) fn select_in_word(word: u64, k: usize) -> usize {
+    let mut word = word;
+    for _ in 0..k {
+        word &= word - 1;
+    }
+    word.trailing_zeros() as usize
+}
+
+fn read_bits<O: BitOrder>(data: &[u64], pos: usize, n: usize) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    let word = pos / WORD_BITS;
+    let bit = pos % WORD_BITS;
+    let mut value = O::convert_word(data[word]) >> bit;
+    if bit + n > WORD_BITS {
+        value |= O::convert_word(data[word + 1]) << (WORD_BITS - bit);
+    }
+    if n < WORD_BITS {
+        value &= (1 << n) - 1;
+    }
+    O::convert_field(value, n)
+}
+
+pub struct Iter<'a, O: BitOrder = LsbFirst> {
+    data: &'a [u64],
+    pos: usize,
+    end: usize,
+    order: PhantomData<O>,
+}
+
+impl<'a, O: BitOrder> Iter<'a, O> {
+    /// Returns the next `n` bits as an integer without advancing the
+    /// iterator, or `None` if fewer than `n` bits remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push_bits(0b1101, 4);
+    /// let mut iter = v.iter();
+    /// assert_eq!(iter.peek_bits(3), Some(0b101));
+    /// assert_eq!(iter.peek_bits(5), None);
+    /// assert_eq!(iter.next(), Some(true));
+    /// ```
+    pub fn peek_bits(&self, n: usize) -> Option<u64> {
+        assert!(n <= 64, "cannot read {} bits into a u64", n);
+        if self.pos + n <= self.end {
+            Some(read_bits::<O>(self.data, self.pos, n))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the next `n` bits as an integer and advances past them, or
+    /// `None` (without advancing) if fewer than `n` bits remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let mut v = BitVec::new();
+    /// v.push_bits(0b1101, 4);
+    /// let mut iter = v.iter();
+    /// assert_eq!(iter.read_bits(3), Some(0b101));
+    /// assert_eq!(iter.read_bits(3), None);
+    /// assert_eq!(iter.read_bits(1), Some(0b1));
+    /// ```
+    pub fn read_bits(&mut self, n: usize) -> Option<u64> {
+        let value = self.peek_bits(n)?;
+        self.pos += n;
+        Some(value)
+    }
+
+    /// Reads a unary-coded number, `n` ones followed by a zero, and advances
+    /// past it, or returns `None` (without advancing) if no zero remains.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = "110011".parse().unwrap();
+    /// let mut iter = v.iter();
+    /// assert_eq!(iter.read_unary(), Some(2));
+    /// assert_eq!(iter.read_unary(), Some(0));
+    /// assert_eq!(iter.read_unary(), None);
+    /// assert_eq!(iter.len(), 2);
+    /// ```
+    pub fn read_unary(&mut self) -> Option<usize> {
+        let mut pos = self.pos;
+        while pos < self.end {
+            let n = WORD_BITS.min(self.end - pos);
+            let word = O::convert_field(read_bits::<O>(self.data, pos, n), n);
+            let ones = word.trailing_ones() as usize;
+            if ones < n {
+                let value = pos + ones - self.pos;
+                self.pos = pos + ones + 1;
+                return Some(value);
+            }
+            pos += n;
+        }
+        None
+    }
+}
+
+impl<'a, O: BitOrder> Iterator for Iter<'a, O> {
+    type Item = bool;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
+        let b = get_bit::<O>(self.data, self.pos);
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl<'a, O: BitOrder> DoubleEndedIterator for Iter<'a, O> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(get_bit::<O>(self.data, self.end))
+    }
+}
+
+impl<'a, O: BitOrder> ExactSizeIterator for Iter<'a, O> {}
+
+/// An iterator over the positions of set (or, for `iter_zeros`, clear)
+/// elements of a `BitVec`.
+#[derive(Clone, Debug)]
+pub struct IterOnes<'a, O: BitOrder = LsbFirst> {
+    data: &'a [u64],
+    len: usize,
+    invert: u64,
+    /// Index of the word held in `current`.
+    word: usize,
+    /// The remaining bits of the current word to report.
+    current: u64,
+    order: PhantomData<O>,
+}
+
+impl<'a, O: BitOrder> IterOnes<'a, O> {
+    fn new(data: &'a [u64], len: usize, invert: u64) -> IterOnes<'a, O> {
+        let mut iter = IterOnes {
+            data,
+            len,
+            invert,
+            word: 0,
+            current: 0,
+            order: PhantomData,
+        };
+        iter.current = iter.load(0);
+        iter
+    }
+
+    fn load(&self, word: usize) -> u64 {
+        match self.data.get(word) {
+            Some(w) => {
+                let w = O::convert_word(*w) ^ self.invert;
+                let end = self.len - word * WORD_BITS;
+                if end < WORD_BITS {
+                    w & ((1 << end) - 1)
+                } else {
+                    w
+                }
+            }
+            None => 0,
+        }
+    }
+}
+
+impl<'a, O: BitOrder> Iterator for IterOnes<'a, O> {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.current == 0 {
+            if self.word + 1 >= self.data.len() {
+                return None;
+            }
+            self.word += 1;
+            self.current = self.load(self.word);
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.word * WORD_BITS + bit)
+    }
+}
+
+/// An iterator over overlapping windows of bits, created by
+/// [`OrderedBitVec::windows`].
+#[derive(Clone, Debug)]
+pub struct Windows<'a, O: BitOrder = LsbFirst> {
+    data: &'a [u64],
+    n: usize,
+    /// Position just after the current window.
+    pos: usize,
+    end: usize,
+    /// The current window in stream order.
+    window: u64,
+    order: PhantomData<O>,
+}
+
+impl<'a, O: BitOrder> Iterator for Windows<'a, O> {
+    type Item = u64;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos > self.end {
+            return None;
+        }
+        let window = O::convert_field(self.window, self.n);
+        if self.pos < self.end {
+            let bit = get_bit::<O>(self.data, self.pos) as u64;
+            self.window = (self.window >> 1) | (bit << (self.n - 1));
+        }
+        self.pos += 1;
+        Some(window)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end + 1).saturating_sub(self.pos);
+        (len, Some(len))
+    }
+}
+
+impl<'a, O: BitOrder> ExactSizeIterator for Windows<'a, O> {}
+
+/// An iterator over the elements removed by [`OrderedBitVec::drain`].
+#[derive(Clone, Debug)]
+pub struct Drain<O: BitOrder = LsbFirst> {
+    iter: IntoIter<O>,
+}
+
+impl<O: BitOrder> Iterator for Drain<O> {
+    type Item = bool;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<O: BitOrder> DoubleEndedIterator for Drain<O> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back()
+    }
+}
+
+impl<O: BitOrder> ExactSizeIterator for Drain<O> {}
+
+/// An owning iterator over the elements of a `BitVec`.
+#[derive(Clone, Debug)]
+pub struct IntoIter<O: BitOrder = LsbFirst> {
+    bits: OrderedBitVec<O>,
+    pos: usize,
+    end: usize,
+}
+
+impl<O: BitOrder> Iterator for IntoIter<O> {
+    type Item = bool;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
+        let b = get_bit::<O>(&self.bits.data, self.pos);
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl<O: BitOrder> DoubleEndedIterator for IntoIter<O> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(get_bit::<O>(&self.bits.data, self.end))
+    }
+}
+
+impl<O: BitOrder> ExactSizeIterator for IntoIter<O> {}
+
+impl<O: BitOrder> IntoIterator for OrderedBitVec<O> {
+    type Item = bool;
+    type IntoIter = IntoIter<O>;
+
+    /// Creates an iterator consuming the vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// let v: BitVec = vec![true, false, false].into();
+    /// let mut iter = v.into_iter();
+    /// assert_eq!(iter.len(), 3);
+    /// assert_eq!(iter.next_back(), Some(false));
+    /// assert_eq!(iter.next(), Some(true));
+    /// assert_eq!(iter.len(), 1);
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        let end = self.len();
+        IntoIter {
+            bits: self,
+            pos: 0,
+            end,
+        }
+    }
+}
+
+impl<'a, O: BitOrder> IntoIterator for &'a OrderedBitVec<O> {
+    type Item = bool;
+    type IntoIter = Iter<'a, O>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity() {
+        let mut v = BitVec::new();
+        v.reserve_exact(9);
+        assert!(v.capacity() >= 9);
+        for _ in 0..9 {
+            v.push(true);
+        }
+        v.reserve(7);
+        assert!(v.capacity() >= 16);
+        v.pop();
+        v.shrink_to_fit();
+        assert!(v.capacity() >= 8);
+    }
+
+    #[test]
+    fn len() {
+        let mut v = BitVec::new();
+        assert_eq!(v.len(), 0);
+        v.push(false);
+        assert_eq!(v.len(), 1);
+        for _ in 0..6 {
+            v.push(false);
+        }
+        assert_eq!(v.len(), 7);
+        v.push(false);
+        assert_eq!(v.len(), 8);
+        v.push(false);
+        assert_eq!(v.len(), 9);
+    }
+
+    #[test]
+    fn split_rest() {
+        let v = BitVec::new();
+        let (rest, last) = v.split_rest();
+        assert_eq!(rest, BitVec::new());
+        assert_eq!(last, BitVec::new());
+
+        let v: BitVec = vec![true; 8].into();
+        let (rest, last) = v.split_rest();
+        assert_eq!(rest, vec![true; 8].into());
+        assert_eq!(last, BitVec::new());
+
+        let v: BitVec = vec![true; 9].into();
+        let (rest, last) = v.split_rest();
+        assert_eq!(rest, vec![true; 8].into());
+        assert_eq!(last, vec![true].into());
+    }
+
+    #[test]
+    fn push_bits() {
+        let mut v = BitVec::new();
+        let mut expected = BitVec::new();
+        for (i, nbits) in [3, 0, 7, 8, 13, 1, 64, 5].iter().enumerate() {
+            let value = 0x9e37_79b9_7f4a_7c15u64.rotate_left(i as u32 * 7);
+            v.push_bits(value, *nbits);
+            for j in 0..*nbits {
+                expected.push((value >> j) & 1 == 1);
+            }
+            assert_eq!(v, expected);
+        }
+        assert_eq!(v.len(), 101);
+    }
+
+    #[test]
+    fn read_bits() {
+        let bits: Vec<bool> = (0..150).map(|i| (i * 7 + i / 5) % 3 == 0).collect();
+        let v: BitVec = bits.clone().into();
+        for pos in 0..86 {
+            for n in [0, 1, 7, 8, 9, 33, 64].iter() {
+                let expected = bits[pos..pos + n]
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &b| acc << 1 | b as u64);
+                assert_eq!(v.read_bits(pos, *n), expected);
+            }
+        }
+        let mut iter = v.iter();
+        assert_eq!(iter.read_bits(64), Some(v.read_bits(0, 64)));
+        assert_eq!(iter.next(), bits.get(64).cloned());
+        assert_eq!(iter.peek_bits(64), Some(v.read_bits(65, 64)));
+        assert_eq!(iter.read_bits(64), Some(v.read_bits(65, 64)));
+        assert_eq!(iter.read_bits(22), None);
+        assert_eq!(iter.read_bits(21), Some(v.read_bits(129, 21)));
+    }
+
+    #[test]
+    fn write_bits() {
+        fn check<O: BitOrder>() {
+            let mut v: OrderedBitVec<O> = vec![true; 150].into();
+            let mut expected = OrderedBitVec::<O>::new();
+            expected.push_bits(!0, 3);
+            for (i, n) in [0, 1, 7, 64, 13, 59].iter().enumerate() {
+                let value = 0x9e37_79b9_7f4a_7c15u64.rotate_left(i as u32 * 11);
+                v.write_bits(expected.len(), value, *n);
+                expected.push_bits(value, *n);
+            }
+            let rest = 150 - expected.len();
+            expected.extend(std::iter::repeat_n(true, rest));
+            assert_eq!(v, expected);
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn unary() {
+        fn check<O: BitOrder>() {
+            let values = [0, 1, 5, 63, 64, 65, 200, 0, 127, 128];
+            let mut v = OrderedBitVec::<O>::new();
+            v.push(true);
+            for &n in values.iter() {
+                v.push_unary(n);
+            }
+            assert_eq!(v.len(), 1 + values.iter().map(|n| n + 1).sum::<usize>());
+            assert_eq!(v.count_zeros(), values.len());
+            let mut iter = v.iter();
+            iter.next();
+            for &n in values.iter() {
+                assert_eq!(iter.read_unary(), Some(n));
+            }
+            assert_eq!(iter.read_unary(), None);
+            v.push_bits(!0, 64);
+            v.push_bits(!0, 64);
+            let mut iter = v.slice(v.len() - 129..).iter();
+            assert_eq!(iter.read_unary(), Some(0));
+            assert_eq!(iter.read_unary(), None);
+            assert_eq!(iter.len(), 128);
+            assert_eq!(v.read_unary(1 + 1 + 2), Some(5));
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn from_bytes() {
+        assert_eq!(BitVec::from_bytes(vec![], 0), Ok(BitVec::new()));
+        assert_eq!(BitVec::from_bytes(vec![0xff], 8), Ok(vec![true; 8].into()));
+        assert_eq!(
+            BitVec::from_bytes(vec![0xff, 0x01], 9),
+            Ok(vec![true; 9].into())
+        );
+        assert_eq!(
+            BitVec::from_bytes(vec![0xff], 0),
+            Err(FromBytesError::LengthMismatch {
+                bytes: 1,
+                bit_len: 0
+            })
+        );
+        assert_eq!(
+            BitVec::from_bytes(vec![0xff, 0x03], 9),
+            Err(FromBytesError::NonZeroPadding)
+        );
+        let mut v = BitVec::from_bytes(vec![0x01], 3).unwrap();
+        v.push(true);
+        assert_eq!(v, vec![true, false, false, true].into());
+    }
+
+    #[test]
+    fn extend_from_byte_slice() {
+        fn check<O: BitOrder>() {
+            let bytes: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(0x9d) ^ 0x5a).collect();
+            for head in [0, 3, 8, 64, 67].iter() {
+                for n in [0, 1, 7, 8, 9, 17, 40].iter() {
+                    let mut v: OrderedBitVec<O> = vec![true; *head].into();
+                    v.extend_from_byte_slice(&bytes[..*n]);
+                    let mut expected: OrderedBitVec<O> = vec![true; *head].into();
+                    for b in bytes[..*n].iter() {
+                        expected.push_byte(*b);
+                    }
+                    assert_eq!(v, expected);
+                }
+            }
+            let mut v = OrderedBitVec::<O>::new();
+            v.extend_from_byte_slice(&bytes);
+            assert_eq!(v.as_bytes(), &bytes[..]);
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn pop() {
+        let mut v: BitVec = vec![true; 9].into();
+        assert_eq!(v.pop(), Some(true));
+        assert_eq!(v.len(), 8);
+        assert_eq!(v, vec![true; 8].into());
+        assert_eq!(v.last(), Some(true));
+        for _ in 0..8 {
+            assert_eq!(v.pop(), Some(true));
+        }
+        assert_eq!(v.pop(), None);
+        assert_eq!(v.last(), None);
+        assert_eq!(v, BitVec::new());
+
+        let mut v: BitVec = vec![true, false, true, true].into();
+        v.pop();
+        v.pop();
+        let (rest, last) = v.split_rest();
+        assert_eq!(rest, BitVec::new());
+        assert_eq!(last, vec![true, false].into());
+    }
+
+    #[test]
+    fn get_set() {
+        let mut v: BitVec = vec![false; 20].into();
+        for i in (0..20).step_by(3) {
+            v.set(i, true);
+        }
+        for i in 0..20 {
+            assert_eq!(v.get(i), Some(i % 3 == 0));
+        }
+        assert_eq!(v.get(20), None);
+        v.set(9, false);
+        assert_eq!(v.get(9), Some(false));
+        assert_eq!(v.len(), 20);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_out_of_bounds() {
+        let mut v: BitVec = vec![false; 9].into();
+        v.set(9, true);
+    }
+
+    #[test]
+    fn slice() {
+        let v: BitVec = (0..20).map(|i| i % 3 == 0).collect::<Vec<_>>().into();
+        let s = v.slice(5..17);
+        assert_eq!(s.len(), 12);
+        for i in 0..12 {
+            assert_eq!(s.get(i), Some((i + 5) % 3 == 0));
+        }
+        assert_eq!(s.get(12), None);
+        assert_eq!(s.slice(3..=4), v.slice(8..10));
+        assert!(v.slice(20..).is_empty());
+        assert_eq!(v.slice(..).to_bit_vec(), v);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_out_of_bounds() {
+        let v: BitVec = vec![false; 9].into();
+        v.slice(3..10);
+    }
+
+    #[test]
+    fn count_ones() {
+        let mut v = BitVec::new();
+        assert_eq!(v.count_ones(), 0);
+        assert_eq!(v.count_zeros(), 0);
+        for i in 0..100 {
+            v.push(i % 7 == 0);
+        }
+        assert_eq!(v.count_ones(), 15);
+        assert_eq!(v.count_zeros(), 85);
+        v.pop();
+        v.pop();
+        assert_eq!(v.count_ones(), 14);
+        assert_eq!((!v).count_ones(), 84);
+    }
+
+    #[test]
+    fn rank_select() {
+        let bits: Vec<bool> = (0..100).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        let v: BitVec = bits.clone().into();
+        let mut ones = 0;
+        for (i, &b) in bits.iter().enumerate() {
+            assert_eq!(v.rank1(i), ones);
+            assert_eq!(v.rank0(i), i - ones);
+            if b {
+                assert_eq!(v.select1(ones), Some(i));
+                ones += 1;
+            } else {
+                assert_eq!(v.select0(i - ones), Some(i));
+            }
+        }
+        assert_eq!(v.rank1(100), ones);
+        assert_eq!(v.select1(ones), None);
+        assert_eq!(v.select0(100 - ones), None);
+    }
+
+    #[test]
+    fn find() {
+        let bits: Vec<bool> = (0..300u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62 == 0)
+            .collect();
+        let v: BitVec = bits.clone().into();
+        for &(start, len) in [(0, 0), (5, 3), (17, 20), (100, 64), (130, 150), (299, 1)].iter() {
+            let pattern: BitVec = bits[start..start + len].into();
+            let expected = (0..=300 - len).find(|&i| bits[i..i + len] == bits[start..start + len]);
+            assert_eq!(v.find(&pattern), expected);
+            assert!(expected.unwrap() <= start);
+        }
+        assert_eq!(v.find(&vec![true; 40].into()), None);
+        assert_eq!(BitVec::new().find(&vec![true].into()), None);
+    }
+
+    #[test]
+    fn hamming_distance() {
+        let a: MsbBitVec = (0..150).map(|i| i % 3 == 0).collect();
+        let b: MsbBitVec = (0..150).map(|i| i % 5 == 0).collect();
+        let expected = (0..150).filter(|i| (i % 3 == 0) != (i % 5 == 0)).count();
+        assert_eq!(a.hamming_distance(&b), expected);
+        assert_eq!(a.hamming_distance(&a), 0);
+        assert_eq!(a.hamming_distance(&!a.clone()), 150);
+    }
+
+    #[test]
+    #[should_panic]
+    fn hamming_distance_length_mismatch() {
+        let a: BitVec = vec![true; 3].into();
+        a.hamming_distance(&vec![true; 4].into());
+    }
+
+    #[test]
+    fn bit_ops() {
+        let a: BitVec = vec![true, true, false, false, true, false, true, true, true].into();
+        let b: BitVec = vec![true, false, true, false, true].into();
+        assert_eq!(
+            &a & &b,
+            vec![true, false, false, false, true, false, false, false, false].into()
+        );
+        assert_eq!(
+            &b | &a,
+            vec![true, true, true, false, true, false, true, true, true].into()
+        );
+        assert_eq!(
+            a.clone() ^ b.clone(),
+            vec![false, true, true, false, false, false, true, true, true].into()
+        );
+        assert_eq!(
+            !&a,
+            vec![false, false, true, true, false, true, false, false, false].into()
+        );
+        assert_eq!(!!a.clone(), a);
+        assert_eq!(!BitVec::new(), BitVec::new());
+
+        let mut c = b.clone();
+        c &= &a;
+        assert_eq!(c, &a & &b);
+        assert_eq!(c.len(), 9);
+
+        let m: MsbBitVec = vec![true, false, true].into();
+        assert_eq!((!m).into_bytes(), vec![0b0100_0000]);
+    }
+
+    #[test]
+    fn msb_first() {
+        let mut v = MsbBitVec::new();
+        let mut lsb = BitVec::new();
+        for i in 0..20 {
+            v.push(i % 3 == 0);
+            lsb.push(i % 3 == 0);
+        }
+        assert_eq!(v.iter().collect::<Vec<_>>(), lsb.iter().collect::<Vec<_>>());
+        v.set(1, true);
+        assert_eq!(v.pop(), Some(false));
+        assert_eq!(v.last(), Some(true));
+        v.push_bits(0b110, 3);
+        assert_eq!(v.read_bits(19, 3), 0b110);
+        assert_eq!(v.read_bits(0, 8), 0b1101_0010);
+        assert_eq!(
+            v.clone().into_bytes(),
+            vec![0b1101_0010, 0b0100_1001, 0b0011_1000]
+        );
+        let (rest, last) = v.split_rest();
+        assert_eq!(rest.into_bytes(), vec![0b1101_0010, 0b0100_1001]);
+        assert_eq!(last.into_bytes(), vec![0b0011_1000]);
+    }
+
+    #[test]
+    fn extend_from_bitvec() {
+        fn check<O: BitOrder>() {
+            let bits: Vec<bool> = (0..40).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+            for i in 0..20 {
+                for j in 0..20 {
+                    let mut a: OrderedBitVec<O> = bits[..i].into();
+                    let b: OrderedBitVec<O> = bits[i..i + j].into();
+                    a.extend_from_bitvec(&b);
+                    assert_eq!(a, bits[..i + j].into());
+
+                    let mut a: OrderedBitVec<O> = bits[..i].into();
+                    let mut b: OrderedBitVec<O> = bits[i..i + j].into();
+                    a.append(&mut b);
+                    assert_eq!(a, bits[..i + j].into());
+                    assert!(b.is_empty());
+
+                    let mut a: OrderedBitVec<O> = bits[..i].into();
+                    let b: OrderedBitVec<O> = bits[..].into();
+                    a.extend_from_bit_slice(b.slice(i..i + j));
+                    assert_eq!(a, bits[..i + j].into());
+                }
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn collect_extend() {
+        let v: BitVec = (0..20).map(|i| i % 3 == 0).collect();
+        assert_eq!(v.len(), 20);
+        assert_eq!(v, (0..20).map(|i| i % 3 == 0).collect::<Vec<_>>().into());
+        let mut w: BitVec = v.iter().take(5).collect();
+        w.extend(v.iter().skip(5));
+        assert_eq!(w, v);
+        w.extend(&[true, false]);
+        assert_eq!(w.len(), 22);
+        assert_eq!(w.last(), Some(false));
+    }
+
+    #[test]
+    fn set_range() {
+        fn check<O: BitOrder>() {
+            let bits: Vec<bool> = (0..200).map(|i| i % 3 == 0).collect();
+            for &(start, end) in [
+                (0, 0),
+                (0, 200),
+                (3, 5),
+                (5, 64),
+                (63, 65),
+                (1, 199),
+                (64, 128),
+            ]
+            .iter()
+            {
+                for &value in [false, true].iter() {
+                    let mut v: OrderedBitVec<O> = bits.clone().into();
+                    v.set_range(start..end, value);
+                    let mut expected = bits.clone();
+                    for b in expected[start..end].iter_mut() {
+                        *b = value;
+                    }
+                    assert_eq!(v, expected.into());
+                }
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn insert_remove() {
+        fn check<O: BitOrder>() {
+            let bits: Vec<bool> = (0..30).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+            for len in 0..30 {
+                for index in 0..=len {
+                    for &value in [false, true].iter() {
+                        let mut v: OrderedBitVec<O> = bits[..len].into();
+                        v.insert(index, value);
+                        let mut expected = bits[..len].to_vec();
+                        expected.insert(index, value);
+                        assert_eq!(v, expected.clone().into());
+                        assert_eq!(v.remove(index), value);
+                        assert_eq!(v, bits[..len].into());
+                    }
+                }
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn truncate() {
+        let mut v: MsbBitVec = vec![true; 20].into();
+        v.truncate(21);
+        assert_eq!(v.len(), 20);
+        v.truncate(11);
+        assert_eq!(v.clone().into_bytes(), vec![0xff, 0b1110_0000]);
+        v.truncate(8);
+        assert_eq!(v, vec![true; 8].into());
+        v.truncate(0);
+        assert_eq!(v, MsbBitVec::new());
+    }
+
+    #[test]
+    fn drain() {
+        let bits: Vec<bool> = (0..30).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+        for start in 0..30 {
+            for end in start..=30 {
+                let mut v: BitVec = bits.clone().into();
+                let drained: Vec<bool> = v.drain(start..end).collect();
+                assert_eq!(drained, bits[start..end].to_vec());
+                let mut rest = bits.clone();
+                rest.drain(start..end);
+                assert_eq!(v, rest.into());
+            }
+        }
+    }
+
+    #[test]
+    fn split_off() {
+        let bits: Vec<bool> = (0..30).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+        for len in 0..30 {
+            for at in 0..=len {
+                let mut v: BitVec = bits[..len].into();
+                let tail = v.split_off(at);
+                assert_eq!(v, bits[..at].into());
+                assert_eq!(tail, bits[at..len].into());
+            }
+        }
+    }
+
+    #[test]
+    fn reverse() {
+        fn check<O: BitOrder>() {
+            let bits: Vec<bool> = (0..30).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+            for len in 0..30 {
+                let mut v: OrderedBitVec<O> = bits[..len].into();
+                v.reverse();
+                let mut expected = bits[..len].to_vec();
+                expected.reverse();
+                assert_eq!(v, expected.into());
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn rotate() {
+        let bits: Vec<bool> = (0..30).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+        for len in 0..30 {
+            for n in 0..2 * len + 2 {
+                let mut v: BitVec = bits[..len].into();
+                v.rotate_left(n);
+                let mut expected = bits[..len].to_vec();
+                if len > 0 {
+                    expected.rotate_left(n % len);
+                }
+                assert_eq!(v, expected.into());
+                v.rotate_right(n);
+                assert_eq!(v, bits[..len].into());
+            }
+        }
+    }
+
+    #[test]
+    fn display_from_str() {
+        let v: BitVec = (0..20).map(|i| i % 3 == 0).collect();
+        let s = v.to_string();
+        assert_eq!(s, "10010010010010010010");
+        assert_eq!(s.parse::<BitVec>(), Ok(v.clone()));
+        assert_eq!(v.slice(1..5).to_string(), "0010");
+        assert_eq!(BitVec::new().to_string(), "");
+        assert_eq!("".parse::<MsbBitVec>(), Ok(MsbBitVec::new()));
+        assert_eq!("1001 0010\t0100_1001\n0010".parse::<BitVec>(), Ok(v));
+        assert_eq!(
+            "01x".parse::<BitVec>(),
+            Err(ParseBitVecError {
+                index: 2,
+                invalid: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn double_ended() {
+        let bits: Vec<bool> = (0..20).map(|i| i % 3 == 0).collect();
+        let v: BitVec = bits.clone().into();
+        let reversed: Vec<bool> = bits.iter().rev().cloned().collect();
+        assert_eq!(v.iter().rev().collect::<Vec<_>>(), reversed);
+        assert_eq!(v.clone().into_iter().rev().collect::<Vec<_>>(), reversed);
+        assert_eq!(v.slice(3..9).iter().len(), 6);
+
+        let mut iter = v.iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 18);
+        assert_eq!(iter.peek_bits(18), Some(v.read_bits(1, 18)));
+        assert_eq!((&v).into_iter().collect::<Vec<_>>(), bits);
+        assert_eq!(v.into_iter().collect::<Vec<_>>(), bits);
+    }
+
+    #[test]
+    fn zero_padding() {
+        fn check<O: BitOrder>() {
+            let padded = |v: &OrderedBitVec<O>| {
+                OrderedBitVec::<O>::from_bytes(v.as_bytes().to_vec(), v.len()).is_ok()
+            };
+            let mut v: OrderedBitVec<O> = vec![true; 13].into();
+            assert!(padded(&v));
+            v.truncate(10);
+            assert!(padded(&v));
+            v.pop();
+            assert!(padded(&v));
+            v.remove(2);
+            assert!(padded(&v));
+            v = !v;
+            assert!(padded(&v));
+            v = !v;
+            v.reverse();
+            assert!(padded(&v));
+            v.rotate_left(3);
+            assert!(padded(&v));
+            let _ = v.split_off(5);
+            assert!(padded(&v));
+            v.drain(1..2);
+            assert!(padded(&v));
+            assert_eq!(v, vec![true; 4].into());
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn word_boundaries() {
+        fn pack<O: BitOrder>(bits: &[bool]) -> Vec<u8> {
+            bits.chunks(8)
+                .map(|chunk| {
+                    let b = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |acc, (i, &bit)| acc | (bit as u8) << i);
+                    O::convert_byte(b)
+                })
+                .collect()
+        }
+        fn check<O: BitOrder>() {
+            let bits: Vec<bool> = (0..300u64)
+                .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62 == 0)
+                .collect();
+            for &len in [0, 1, 63, 64, 65, 127, 128, 129, 200, 300].iter() {
+                let bits = &bits[..len];
+                let v: OrderedBitVec<O> = bits.into();
+                assert_eq!(v.as_bytes(), &pack::<O>(bits)[..]);
+                assert_eq!(
+                    OrderedBitVec::<O>::from_bytes(pack::<O>(bits), len),
+                    Ok(v.clone())
+                );
+                assert_eq!(v.iter().collect::<Vec<_>>(), bits);
+                assert_eq!(v.count_ones(), bits.iter().filter(|&&b| b).count());
+                for &at in [0, 1, 63, 64, 65, 128, 129].iter().filter(|&&at| at <= len) {
+                    let mut head = v.clone();
+                    let tail = head.split_off(at);
+                    assert_eq!(head, bits[..at].into());
+                    assert_eq!(tail, bits[at..].into());
+                    head.extend_from_bitvec(&tail);
+                    assert_eq!(head, v);
+                    let mut w = v.clone();
+                    w.insert(at, true);
+                    assert!(w.remove(at));
+                    assert_eq!(w, v);
+                }
+                let mut w = v.clone();
+                w.reverse();
+                w.reverse();
+                assert_eq!(w, v);
+                let mut w = OrderedBitVec::<O>::new();
+                w.extend_from_bit_slice(v.slice(..));
+                assert_eq!(w, v);
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn write_read() {
+        let mut out = Vec::new();
+        let vs: Vec<MsbBitVec> = vec![
+            MsbBitVec::new(),
+            vec![true; 7].into(),
+            (0..1000).map(|i| i % 3 == 0).collect(),
+        ];
+        for v in vs.iter() {
+            v.write_to(&mut out).unwrap();
+        }
+        assert_eq!(&out[..4], &[0, 7, 0b1111_1110, 0xe8]);
+        let mut input = &out[..];
+        for v in vs.iter() {
+            assert_eq!(&MsbBitVec::read_from(&mut input).unwrap(), v);
+        }
+        assert!(input.is_empty());
+
+        let err = MsbBitVec::read_from(&[0x80][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = MsbBitVec::read_from(&[0xff; 10][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = MsbBitVec::read_from(&[7, 0xff][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = MsbBitVec::read_from(&[0xff, 0xff, 0xff, 0x7f][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn iter_ones() {
+        fn check<O: BitOrder>() {
+            for &len in [0, 1, 63, 64, 65, 200].iter() {
+                let bits: Vec<bool> = (0..len).map(|i| i % 7 == 0 || i > 150).collect();
+                let v: OrderedBitVec<O> = bits.clone().into();
+                let ones: Vec<usize> = (0..len).filter(|&i| bits[i]).collect();
+                let zeros: Vec<usize> = (0..len).filter(|&i| !bits[i]).collect();
+                assert_eq!(v.iter_ones().collect::<Vec<_>>(), ones);
+                assert_eq!(v.iter_zeros().collect::<Vec<_>>(), zeros);
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn windows() {
+        fn check<O: BitOrder>() {
+            let v: OrderedBitVec<O> = (0..150u64)
+                .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62 == 0)
+                .collect();
+            for &n in [1, 2, 13, 63, 64].iter() {
+                let expected: Vec<u64> = (0..=150 - n).map(|pos| v.read_bits(pos, n)).collect();
+                assert_eq!(v.windows(n).len(), expected.len());
+                assert_eq!(v.windows(n).collect::<Vec<_>>(), expected);
+            }
+            assert_eq!(v.slice(..3).to_bit_vec().windows(4).next(), None);
+            assert_eq!(v.slice(..3).to_bit_vec().windows(3).count(), 1);
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
     }
 
     #[test]
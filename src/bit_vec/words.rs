@@ -0,0 +1,226 @@
+//! Word storage of `BitVec` with a small-buffer optimization.
+
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+
+/// Number of words stored inline, without allocating.
+const INLINE: usize = 2;
+
+/// A `Vec<u64>` work-alike that keeps up to `INLINE` words inside the struct
+/// and only allocates once it grows beyond that.
+#[derive(Clone)]
+pub(super) enum Words {
+    Inline { len: u8, buf: [u64; INLINE] },
+    Heap(Vec<u64>),
+}
+
+impl Words {
+    pub(super) fn with_capacity(capacity: usize) -> Words {
+        if capacity <= INLINE {
+            Words::Inline {
+                len: 0,
+                buf: [0; INLINE],
+            }
+        } else {
+            Words::Heap(Vec::with_capacity(capacity))
+        }
+    }
+
+    pub(super) fn capacity(&self) -> usize {
+        match self {
+            Words::Inline { .. } => INLINE,
+            Words::Heap(vec) => vec.capacity(),
+        }
+    }
+
+    /// Moves the words to the heap, with room for `additional` more.
+    fn spill(&mut self, additional: usize) -> &mut Vec<u64> {
+        if let Words::Inline { len, buf } = self {
+            let mut vec = Vec::with_capacity(*len as usize + additional);
+            vec.extend_from_slice(&buf[..*len as usize]);
+            *self = Words::Heap(vec);
+        }
+        match self {
+            Words::Heap(vec) => vec,
+            Words::Inline { .. } => unreachable!(),
+        }
+    }
+
+    pub(super) fn reserve(&mut self, additional: usize) {
+        match self {
+            Words::Inline { len, .. } if *len as usize + additional <= INLINE => {}
+            Words::Inline { .. } => {
+                self.spill(additional);
+            }
+            Words::Heap(vec) => vec.reserve(additional),
+        }
+    }
+
+    pub(super) fn reserve_exact(&mut self, additional: usize) {
+        match self {
+            Words::Heap(vec) => vec.reserve_exact(additional),
+            _ => self.reserve(additional),
+        }
+    }
+
+    pub(super) fn shrink_to_fit(&mut self) {
+        if let Words::Heap(vec) = self {
+            if vec.len() <= INLINE {
+                let mut buf = [0; INLINE];
+                buf[..vec.len()].copy_from_slice(vec);
+                *self = Words::Inline {
+                    len: vec.len() as u8,
+                    buf,
+                };
+            } else {
+                vec.shrink_to_fit();
+            }
+        }
+    }
+
+    pub(super) fn push(&mut self, word: u64) {
+        match self {
+            Words::Inline { len, buf } if (*len as usize) < INLINE => {
+                buf[*len as usize] = word;
+                *len += 1;
+            }
+            Words::Inline { .. } => self.spill(1).push(word),
+            Words::Heap(vec) => vec.push(word),
+        }
+    }
+
+    pub(super) fn pop(&mut self) -> Option<u64> {
+        match self {
+            Words::Inline { len: 0, .. } => None,
+            Words::Inline { len, buf } => {
+                *len -= 1;
+                let word = buf[*len as usize];
+                buf[*len as usize] = 0;
+                Some(word)
+            }
+            Words::Heap(vec) => vec.pop(),
+        }
+    }
+
+    pub(super) fn truncate(&mut self, new_len: usize) {
+        match self {
+            Words::Inline { len, buf } => {
+                if new_len < *len as usize {
+                    for word in buf[new_len..].iter_mut() {
+                        *word = 0;
+                    }
+                    *len = new_len as u8;
+                }
+            }
+            Words::Heap(vec) => vec.truncate(new_len),
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub(super) fn resize(&mut self, new_len: usize, value: u64) {
+        if new_len <= self.len() {
+            self.truncate(new_len);
+        } else {
+            self.reserve(new_len - self.len());
+            while self.len() < new_len {
+                self.push(value);
+            }
+        }
+    }
+
+    pub(super) fn extend_from_slice(&mut self, words: &[u64]) {
+        self.reserve(words.len());
+        match self {
+            Words::Inline { len, buf } => {
+                buf[*len as usize..*len as usize + words.len()].copy_from_slice(words);
+                *len += words.len() as u8;
+            }
+            Words::Heap(vec) => vec.extend_from_slice(words),
+        }
+    }
+
+    pub(super) fn split_off(&mut self, at: usize) -> Words {
+        let mut tail = Words::with_capacity(self.len() - at);
+        tail.extend_from_slice(&self[at..]);
+        self.truncate(at);
+        tail
+    }
+
+    pub(super) fn append(&mut self, other: &mut Words) {
+        self.extend_from_slice(other);
+        other.clear();
+    }
+}
+
+impl Deref for Words {
+    type Target = [u64];
+    fn deref(&self) -> &[u64] {
+        match self {
+            Words::Inline { len, buf } => &buf[..*len as usize],
+            Words::Heap(vec) => vec,
+        }
+    }
+}
+
+impl DerefMut for Words {
+    fn deref_mut(&mut self) -> &mut [u64] {
+        match self {
+            Words::Inline { len, buf } => &mut buf[..*len as usize],
+            Words::Heap(vec) => vec,
+        }
+    }
+}
+
+impl PartialEq for Words {
+    fn eq(&self, other: &Words) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Words {}
+
+impl Hash for Words {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl std::fmt::Debug for Words {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spill_and_shrink() {
+        let mut words = Words::with_capacity(0);
+        words.push(1);
+        words.push(2);
+        assert!(matches!(words, Words::Inline { len: 2, .. }));
+        words.push(3);
+        assert!(matches!(words, Words::Heap(_)));
+        assert_eq!(&*words, &[1, 2, 3]);
+        assert_eq!(words.pop(), Some(3));
+        words.shrink_to_fit();
+        assert!(matches!(words, Words::Inline { len: 2, .. }));
+        assert_eq!(words, {
+            let mut heap = Words::with_capacity(10);
+            heap.extend_from_slice(&[1, 2]);
+            heap
+        });
+        let tail = words.split_off(1);
+        assert_eq!(&*words, &[1]);
+        assert_eq!(&*tail, &[2]);
+        words.resize(4, 7);
+        assert_eq!(&*words, &[1, 7, 7, 7]);
+        words.truncate(0);
+        assert_eq!(words.pop(), None);
+    }
+}
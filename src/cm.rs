@@ -0,0 +1,542 @@
+//! Context mixing, a compressor of byte strings that codes every bit with
+//! the mixed predictions of several context models.
+//!
+//! Every bit of a byte, from the highest, is predicted by a counter in each
+//! of five contexts: the bits of the byte before it, and the one to four
+//! bytes before those. Unlike a `BitModel`, a counter adapts fast while its
+//! context is new, which matters for the many contexts seen only a few
+//! times. The orders 2 to 4 are hashed into tables of a
+//! fixed size. A [`Mixer`] combines the predictions in the logistic domain
+//! with weights it learns by gradient descent on the coding cost. Two
+//! [`Apm`]s, in the contexts of the orders 0 and 1, refine the mixed
+//! probability, and the [`BinaryEncoder`] codes the bit with an average of
+//! the three.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rust_compress::cm;
+//! let data = b"how much wood would a woodchuck chuck if a woodchuck could chuck wood".repeat(10);
+//! let compressed = cm::compress(&data);
+//! assert!(compressed.len() < data.len() / 8);
+//! assert_eq!(cm::decompress(&compressed).unwrap(), data);
+//! ```
+
+use std::io;
+
+use crate::arithmetic::{BinaryDecoder, BinaryEncoder, PROBABILITY_BITS};
+use crate::varint;
+
+/// The number of bits of the index of a hashed table.
+const TABLE_BITS: u32 = 18;
+
+/// The number of context models mixed.
+const INPUTS: usize = 5;
+
+/// The bound of the magnitude of a weight of a [`Mixer`], of 16 in units of
+/// `2^-16`.
+const MAX_WEIGHT: i32 = 1 << 20;
+
+/// The count past which a [`Counter`] adapts at a constant rate.
+const LIMIT: u16 = 20;
+
+/// The base 2 logarithm of the inverse of the rate at which an [`Apm`]
+/// adapts.
+const APM_RATE: u32 = 6;
+
+/// The probability of `squash(x)` for `x` at every multiple of 128, from
+/// `-2048`.
+const SQUASH: [i32; 33] = [
+    1, 2, 3, 6, 10, 16, 27, 45, 73, 120, 194, 310, 488, 747, 1101, 1546, 2047, 2549, 2994, 3348,
+    3607, 3785, 3901, 3975, 4022, 4050, 4068, 4079, 4085, 4089, 4092, 4093, 4094,
+];
+
+/// Returns `4096 / (1 + e^(-x / 256))`, the probability in units of
+/// `2^-12` of the logit `x / 256`, interpolated from a table so that it is
+/// exactly the same everywhere.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::cm::squash;
+/// assert_eq!(squash(0), 2047);
+/// assert_eq!(squash(-5000), 0);
+/// assert_eq!(squash(5000), 4095);
+/// ```
+pub fn squash(x: i32) -> i32 {
+    if x > 2047 {
+        return 4095;
+    }
+    if x < -2047 {
+        return 0;
+    }
+    let w = x & 127;
+    let i = ((x >> 7) + 16) as usize;
+    (SQUASH[i] * (128 - w) + SQUASH[i + 1] * w + 64) >> 7
+}
+
+/// Returns the logit of the probability `p` in units of `2^-12`, in units
+/// of `1/256` between `-2047` and `2047`: the inverse of [`squash`].
+///
+/// # Panics
+///
+/// Panics if `p` is not less than `2^12`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::cm::{squash, stretch};
+/// assert_eq!(stretch(2047), 0);
+/// assert_eq!(stretch(1), -2047);
+/// assert_eq!(stretch(4095), 2047);
+/// ```
+pub fn stretch(p: u16) -> i32 {
+    let p = i32::from(p);
+    assert!(p < 1 << PROBABILITY_BITS, "probability {} out of range", p);
+    // the smallest x squashed to at least p
+    let (mut low, mut high): (i32, i32) = (-2047, 2047);
+    while low < high {
+        let mid = (low + high) >> 1;
+        if squash(mid) < p {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// A mixer of predictions, which adds up their logits with weights learned
+/// from the bits coded, a set of weights per context selected by the
+/// caller.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::cm::{stretch, Mixer};
+/// let mut mixer = Mixer::new(2, 1);
+/// // the first input is right, the second is wrong
+/// for _ in 0..100 {
+///     mixer.add(stretch(3500));
+///     mixer.add(stretch(500));
+///     mixer.mix(0);
+///     mixer.update(true);
+/// }
+/// mixer.add(stretch(3500));
+/// mixer.add(stretch(500));
+/// assert!(mixer.mix(0) > 3500);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mixer {
+    /// The weights of every context, in units of `2^-16`.
+    weights: Vec<i32>,
+    inputs: Vec<i32>,
+    len: usize,
+    /// The first weight of the context of the last mix.
+    selected: usize,
+    p: i32,
+}
+
+impl Mixer {
+    /// Creates a mixer of `len` inputs in `contexts` contexts, with weights
+    /// of `1 / len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero.
+    pub fn new(len: usize, contexts: usize) -> Mixer {
+        assert!(len > 0, "a mixer needs inputs");
+        Mixer {
+            weights: vec![(1 << 16) / len as i32; len * contexts],
+            inputs: Vec::with_capacity(len),
+            len,
+            selected: 0,
+            p: 1 << (PROBABILITY_BITS - 1),
+        }
+    }
+
+    /// Adds the logit of a prediction, as given by [`stretch`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if all the inputs have been added already.
+    pub fn add(&mut self, x: i32) {
+        assert!(self.inputs.len() < self.len, "too many inputs");
+        self.inputs.push(x);
+    }
+
+    /// Returns the probability of a 1 mixed from the inputs with the weights
+    /// of `context`, between 1 and `2^12 - 1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not all the inputs have been added, or if `context` is out
+    /// of range.
+    pub fn mix(&mut self, context: usize) -> u16 {
+        assert!(self.inputs.len() == self.len, "missing inputs");
+        self.selected = context * self.len;
+        let weights = &self.weights[self.selected..self.selected + self.len];
+        let dot: i64 = weights
+            .iter()
+            .zip(self.inputs.iter())
+            .map(|(&w, &x)| i64::from(w) * i64::from(x))
+            .sum();
+        let x = (dot >> 16).clamp(-2047, 2047) as i32;
+        self.p = squash(x).clamp(1, 4095);
+        self.p as u16
+    }
+
+    /// Moves the weights of the last mix towards the inputs that predicted
+    /// `bit` best, and clears the inputs.
+    ///
+    /// The weights stay between -16 and 16. The mixed probability saturates
+    /// short of 0 and 1, so a run of the same bit pushes the weights forever,
+    /// and they would overflow otherwise.
+    pub fn update(&mut self, bit: bool) {
+        let err = ((bit as i32) << PROBABILITY_BITS) - self.p;
+        let weights = &mut self.weights[self.selected..self.selected + self.len];
+        for (w, &x) in weights.iter_mut().zip(self.inputs.iter()) {
+            *w = (*w + ((x * err) >> 10)).clamp(-MAX_WEIGHT, MAX_WEIGHT);
+        }
+        self.inputs.clear();
+    }
+}
+
+/// An adaptive probability map, which refines a probability given a
+/// context: secondary symbol estimation.
+///
+/// Every context has a curve mapping the logit of the input probability to
+/// the output probability, known at 33 points of the logit and linearly
+/// interpolated between them, which starts as the identity. After every bit
+/// the point nearest the input moves towards it, so the curve learns how
+/// often a 1 really follows an input in the context, and corrects a model
+/// that is off in a way the context tells. It fits between any model and
+/// the [`BinaryEncoder`].
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::cm::Apm;
+/// let mut apm = Apm::new(1);
+/// assert!((997..=1003).contains(&apm.refine(1000, 0)));
+/// // an input of 1000 is always followed by a 1
+/// for _ in 0..100 {
+///     apm.refine(1000, 0);
+///     apm.update(true);
+/// }
+/// assert!(apm.refine(1000, 0) > 2500);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Apm {
+    /// The probabilities of the points of every context, in units of
+    /// `2^-16`.
+    table: Vec<u16>,
+    /// The point nearest the input of the last refinement.
+    index: usize,
+}
+
+impl Apm {
+    /// Creates a map of `contexts` contexts.
+    pub fn new(contexts: usize) -> Apm {
+        let curve: Vec<u16> = (0..33)
+            .map(|i| (squash((i - 16) * 128) * 16) as u16)
+            .collect();
+        Apm {
+            table: curve.repeat(contexts),
+            index: 0,
+        }
+    }
+
+    /// Returns the probability `p` of a 1, in units of `2^-12`, refined in
+    /// `context`, between 1 and `2^12 - 1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not less than `2^12`, or if `context` is out of
+    /// range.
+    pub fn refine(&mut self, p: u16, context: usize) -> u16 {
+        let x = stretch(p) + 2048;
+        let w = x & 127;
+        let i = context * 33 + (x >> 7) as usize;
+        self.index = i + (w >> 6) as usize;
+        let low = i32::from(self.table[i]);
+        let high = i32::from(self.table[i + 1]);
+        ((low * (128 - w) + high * w) >> 11).clamp(1, 4095) as u16
+    }
+
+    /// Moves the point nearest the input of the last refinement towards
+    /// `bit`.
+    pub fn update(&mut self, bit: bool) {
+        let target = if bit { 0xffff } else { 0 };
+        let p = &mut self.table[self.index];
+        *p = (i32::from(*p) + ((target - i32::from(*p)) >> APM_RATE)) as u16;
+    }
+}
+
+/// The probability of a 1 in a context, which adapts fast while the
+/// context is new and slower as it gets older.
+#[derive(Clone, Copy, Debug)]
+struct Counter {
+    /// The probability in units of `2^-16`.
+    p: u16,
+    /// The number of bits seen, up to `LIMIT`.
+    n: u16,
+}
+
+impl Counter {
+    fn new() -> Counter {
+        Counter { p: 1 << 15, n: 0 }
+    }
+
+    fn probability(&self) -> u16 {
+        (self.p >> (16 - PROBABILITY_BITS)).clamp(1, (1 << PROBABILITY_BITS) - 1)
+    }
+
+    /// Moves the probability by `1 / (n + 1.5)` of the way towards `bit`.
+    fn update(&mut self, bit: bool) {
+        let target = if bit { 0xffff } else { 0 };
+        let p = i32::from(self.p);
+        self.p = (p + (target - p) * 2 / (2 * i32::from(self.n) + 3)) as u16;
+        self.n = (self.n + 1).min(LIMIT);
+    }
+}
+
+/// The models of the compressor, shared by the decompressor.
+struct Predictor {
+    /// The models of the orders 0 and 1, indexed exactly, then the hashed
+    /// tables of the orders 2 to 4.
+    tables: Vec<Vec<Counter>>,
+    mixer: Mixer,
+    /// The maps of the mixed probability in the contexts of the orders 0
+    /// and 1.
+    apms: [Apm; 2],
+    /// The logit of every probability, as `stretch` is slow.
+    stretch: Vec<i32>,
+    /// The bits of the current byte so far, after a leading 1.
+    partial: u32,
+    /// The last four bytes, the last one lowest.
+    history: u32,
+    /// The index of the model of every order for the next bit.
+    indices: [usize; INPUTS],
+}
+
+impl Predictor {
+    fn new() -> Predictor {
+        let mut tables = vec![vec![Counter::new(); 1 << 8], vec![Counter::new(); 1 << 16]];
+        for _ in 2..INPUTS {
+            tables.push(vec![Counter::new(); 1 << TABLE_BITS]);
+        }
+        let mut predictor = Predictor {
+            tables,
+            mixer: Mixer::new(INPUTS, 256),
+            apms: [Apm::new(1 << 8), Apm::new(1 << 16)],
+            stretch: (0..1 << PROBABILITY_BITS).map(stretch).collect(),
+            partial: 1,
+            history: 0,
+            indices: [0; INPUTS],
+        };
+        predictor.index();
+        predictor
+    }
+
+    /// Returns the probability that the next bit is 1.
+    fn p(&mut self) -> u16 {
+        for (table, &index) in self.tables.iter().zip(self.indices.iter()) {
+            self.mixer
+                .add(self.stretch[table[index].probability() as usize]);
+        }
+        let p = self.mixer.mix(self.partial as usize);
+        let p0 = self.apms[0].refine(p, self.indices[0]);
+        let p1 = self.apms[1].refine(p, self.indices[1]);
+        // the mixed probability keeps the maps from going too far
+        ((u32::from(p) + u32::from(p0) + 2 * u32::from(p1) + 2) >> 2) as u16
+    }
+
+    fn update(&mut self, bit: bool) {
+        for (table, &index) in self.tables.iter_mut().zip(self.indices.iter()) {
+            table[index].update(bit);
+        }
+        self.mixer.update(bit);
+        for apm in self.apms.iter_mut() {
+            apm.update(bit);
+        }
+        self.partial = (self.partial << 1) | bit as u32;
+        if self.partial >= 1 << 8 {
+            self.history = (self.history << 8) | (self.partial & 0xff);
+            self.partial = 1;
+        }
+        self.index();
+    }
+
+    /// Finds the models of the next bit.
+    fn index(&mut self) {
+        let partial = self.partial as usize;
+        self.indices[0] = partial;
+        self.indices[1] = ((self.history as usize & 0xff) << 8) | partial;
+        for order in 2..INPUTS {
+            let context = match order {
+                4 => self.history,
+                _ => self.history & ((1 << (8 * order)) - 1),
+            };
+            // the order in the hash keeps the orders apart
+            let hash = (context.wrapping_add(order as u32).wrapping_mul(0x9e37_79b1)
+                ^ self.partial)
+                .wrapping_mul(0x2545_f491);
+            self.indices[order] = (hash >> (32 - TABLE_BITS)) as usize;
+        }
+    }
+}
+
+/// Compresses `data` into a self-describing stream that `decompress` reads
+/// back.
+///
+/// The stream starts with the length of `data` as a varint, followed by
+/// the code of the binary arithmetic coder. The models take about 7.5 MiB.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    varint::encode_u64(data.len() as u64, &mut out);
+    let mut predictor = Predictor::new();
+    let mut encoder = BinaryEncoder::new(out);
+    for &byte in data.iter() {
+        for i in (0..8).rev() {
+            let bit = (byte >> i) & 1 == 1;
+            encoder.encode(bit, predictor.p()).unwrap();
+            predictor.update(bit);
+        }
+    }
+    encoder.finish().unwrap()
+}
+
+/// Decompresses a stream made by `compress`.
+///
+/// Fails with `ErrorKind::UnexpectedEof` if the stream is cut short. The
+/// code reads as zeros a little past its end, so a stream cut short by a
+/// few bytes or with data left after it may decode to wrong data instead.
+pub fn decompress(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let (len, pos) = varint::decode_u64(compressed)?;
+    let mut predictor = Predictor::new();
+    let mut decoder = BinaryDecoder::new(&compressed[pos..])?;
+    // a byte takes a few bits but for the longest repetitions, which
+    // bounds the memory an honest length needs at once
+    let mut data = Vec::with_capacity(len.min(64 * compressed.len() as u64) as usize);
+    for _ in 0..len {
+        let mut byte = 0;
+        for _ in 0..8 {
+            let bit = decoder.decode(predictor.p())?;
+            predictor.update(bit);
+            byte = (byte << 1) | bit as u8;
+        }
+        data.push(byte);
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppm;
+
+    #[test]
+    fn squash_stretch() {
+        let mut last = 0;
+        for x in -2047..=2047 {
+            let p = squash(x);
+            assert!(p >= last);
+            last = p;
+        }
+        for p in 1..4096 {
+            let x = stretch(p);
+            assert!(x == 2047 || squash(x) >= i32::from(p));
+            assert!(x == -2047 || squash(x - 1) < i32::from(p));
+        }
+        assert_eq!(stretch(4095), 2047);
+        assert_eq!(stretch(4095), -stretch(1));
+    }
+
+    #[test]
+    fn mixer_saturates() {
+        // the same bit from a sure input, which grows its weight a little
+        // at a time once the probability saturates
+        let mut mixer = Mixer::new(1, 1);
+        for _ in 0..1 << 21 {
+            mixer.add(2047);
+            mixer.mix(0);
+            mixer.update(true);
+        }
+        assert_eq!(mixer.weights[0], MAX_WEIGHT);
+        mixer.add(2047);
+        assert_eq!(i32::from(mixer.mix(0)), squash(2047));
+    }
+
+    #[test]
+    fn apm() {
+        let mut apm = Apm::new(2);
+        // close to the identity at first
+        for p in 1..4096 {
+            let refined = apm.refine(p, 1);
+            assert!(
+                (i32::from(refined) - i32::from(p)).abs() <= 3,
+                "{} {}",
+                p,
+                refined
+            );
+        }
+        // a 1 half the time after 3000 in the context 0, and always in 1
+        for i in 0..1000 {
+            apm.refine(3000, 0);
+            apm.update(i % 2 == 0);
+            apm.refine(3000, 1);
+            apm.update(true);
+        }
+        let p = apm.refine(3000, 0);
+        assert!((1900..2200).contains(&p), "{}", p);
+        assert!(apm.refine(3000, 1) > 4000);
+        // the points far from the input have not moved
+        assert_eq!(apm.refine(100, 1), Apm::new(1).refine(100, 0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            vec![7],
+            vec![0; 1000],
+            (0..=255).collect(),
+            (0..3000u64)
+                .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8)
+                .collect(),
+        ];
+        for data in inputs.iter() {
+            assert_eq!(&decompress(&compress(data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn ratio() {
+        let words = [
+            "the ", "then ", "that ", "this ", "there ", "and ", "a ", "of ",
+        ];
+        let data: Vec<u8> = (0..5000u64)
+            .flat_map(|i| words[(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61) as usize].bytes())
+            .collect();
+        let compressed = compress(&data);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        let ppm = ppm::compress_with(&data, 2, ppm::DEFAULT_MEMORY).len();
+        assert!(compressed.len() < ppm, "{} {}", compressed.len(), ppm);
+    }
+
+    #[test]
+    fn errors() {
+        // a length far too large for the input
+        let err = decompress(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1, 2, 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let data: Vec<u8> = (0..2000u64)
+            .map(|i| b"abracadabra"[(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61) as usize])
+            .collect();
+        let compressed = compress(&data);
+        for len in (0..compressed.len() - 16).step_by(97) {
+            let err = decompress(&compressed[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", len);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Elias–Fano encoding of non-decreasing sequences of integers.
+
+use crate::bit_vec::{select_in_word, BitVec, IterOnes};
+use crate::packed_int_vec::{self, PackedIntVec};
+
+/// Number of ones (or zeros) of the upper bits between select samples.
+const SAMPLE: usize = 256;
+
+/// A non-decreasing sequence of `u64` stored in Elias–Fano form.
+///
+/// Each value is split into `low_bits()` low bits, stored verbatim, and the
+/// remaining high bits, stored as a unary-coded sequence of gaps. For `n`
+/// values up to `u` the encoding takes at most `3 + floor(log2(u / n))` bits
+/// per element, plus a small select index.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::elias_fano::EliasFano;
+/// let ef = EliasFano::new(&[3, 4, 7, 13, 14, 15, 21, 43]);
+/// assert_eq!(ef.len(), 8);
+/// assert_eq!(ef.access(3), 13);
+/// assert_eq!(ef.next_geq(16), Some((6, 21)));
+/// assert_eq!(ef.next_geq(44), None);
+/// assert_eq!(ef.iter().collect::<Vec<_>>(), vec![3, 4, 7, 13, 14, 15, 21, 43]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct EliasFano {
+    low: PackedIntVec,
+    high: BitVec,
+    /// Positions in `high` of every `SAMPLE`-th one.
+    one_samples: Vec<usize>,
+    /// Positions in `high` of every `SAMPLE`-th zero.
+    zero_samples: Vec<usize>,
+}
+
+impl EliasFano {
+    /// Encodes `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is not sorted in non-decreasing order.
+    pub fn new(values: &[u64]) -> EliasFano {
+        assert!(
+            values.windows(2).all(|w| w[0] <= w[1]),
+            "values must be sorted in non-decreasing order"
+        );
+        let n = values.len() as u64;
+        let low_bits = match values.last() {
+            Some(&max) if max / n > 0 => 63 - (max / n).leading_zeros() as usize,
+            _ => 0,
+        };
+        let mut low = PackedIntVec::with_capacity(low_bits, values.len());
+        let mut high = BitVec::new();
+        let mut bucket = 0;
+        for &value in values {
+            low.push(value & low.max_value());
+            let h = value >> low_bits;
+            for _ in bucket..h {
+                high.push(false);
+            }
+            high.push(true);
+            bucket = h;
+        }
+        let mut one_samples = Vec::new();
+        let mut zero_samples = Vec::new();
+        let (mut ones, mut zeros) = (0, 0);
+        for (pos, bit) in high.iter().enumerate() {
+            let (count, samples) = if bit {
+                (&mut ones, &mut one_samples)
+            } else {
+                (&mut zeros, &mut zero_samples)
+            };
+            if *count % SAMPLE == 0 {
+                samples.push(pos);
+            }
+            *count += 1;
+        }
+        EliasFano {
+            low,
+            high,
+            one_samples,
+            zero_samples,
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.low.len()
+    }
+
+    /// Returns `true` if the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.low.is_empty()
+    }
+
+    /// Returns the number of low bits stored verbatim for each value.
+    pub fn low_bits(&self) -> usize {
+        self.low.bit_width()
+    }
+
+    /// Returns the size of the encoding in bits, excluding the select index.
+    pub fn size_in_bits(&self) -> usize {
+        self.low.as_bit_vec().len() + self.high.len()
+    }
+
+    /// Returns the value at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn access(&self, index: usize) -> u64 {
+        assert!(
+            index < self.len(),
+            "index out of bounds: the len is {} but the index is {}",
+            self.len(),
+            index
+        );
+        let h = (self.select(index, true) - index) as u64;
+        (h << self.low_bits()) | self.low.get(index).unwrap()
+    }
+
+    /// Returns the index and value of the first value greater than or equal
+    /// to `x`, or `None` if every value is smaller.
+    pub fn next_geq(&self, x: u64) -> Option<(usize, u64)> {
+        let h = (x >> self.low_bits()) as usize;
+        // The elements with high part below `h` are the ones before the
+        // `h`-th zero.
+        let mut index = if h == 0 {
+            0
+        } else if h - 1 < self.zeros() {
+            self.select(h - 1, false) - (h - 1)
+        } else {
+            return None;
+        };
+        while index < self.len() {
+            let value = self.access(index);
+            if value >= x {
+                return Some((index, value));
+            }
+            index += 1;
+        }
+        None
+    }
+
+    /// Returns an iterator over the values.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            ones: self.high.iter_ones(),
+            low: self.low.iter(),
+            index: 0,
+            low_bits: self.low_bits(),
+        }
+    }
+
+    fn zeros(&self) -> usize {
+        self.high.len() - self.len()
+    }
+
+    /// Returns the position in `high` of the `k`-th one or zero, which must
+    /// exist.
+    fn select(&self, k: usize, value: bool) -> usize {
+        let samples = if value {
+            &self.one_samples
+        } else {
+            &self.zero_samples
+        };
+        let mut pos = samples[k / SAMPLE];
+        let mut rest = k % SAMPLE;
+        loop {
+            let n = (self.high.len() - pos).min(64);
+            let mut word = self.high.read_bits(pos, n);
+            if !value {
+                word = !word & (u64::MAX >> (64 - n));
+            }
+            let count = word.count_ones() as usize;
+            if rest < count {
+                return pos + select_in_word(word, rest);
+            }
+            rest -= count;
+            pos += n;
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a EliasFano {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of an [`EliasFano`] sequence.
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    ones: IterOnes<'a>,
+    low: packed_int_vec::Iter<'a>,
+    index: usize,
+    low_bits: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = u64;
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.ones.next()?;
+        let h = (pos - self.index) as u64;
+        self.index += 1;
+        Some((h << self.low_bits) | self.low.next()?)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.low.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(n: u64, step: u64) -> Vec<u64> {
+        let mut value = 0;
+        (0..n)
+            .map(|i| {
+                value += (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 60) & step;
+                value
+            })
+            .collect()
+    }
+
+    #[test]
+    fn access_iter() {
+        for &(n, step) in [(0, 0), (1, 0), (5, 0), (1000, 1), (1000, 15), (3000, 7)].iter() {
+            let values = sequence(n, step);
+            let ef = EliasFano::new(&values);
+            assert_eq!(ef.len(), values.len());
+            assert_eq!(ef.iter().collect::<Vec<_>>(), values);
+            for (i, &value) in values.iter().enumerate() {
+                assert_eq!(ef.access(i), value);
+            }
+        }
+    }
+
+    #[test]
+    fn next_geq() {
+        let values = sequence(3000, 15);
+        let ef = EliasFano::new(&values);
+        let max = *values.last().unwrap();
+        for x in 0..=max + 2 {
+            let expected = values.iter().position(|&v| v >= x).map(|i| (i, values[i]));
+            assert_eq!(ef.next_geq(x), expected);
+        }
+        assert_eq!(EliasFano::new(&[]).next_geq(0), None);
+        assert_eq!(
+            EliasFano::new(&[u64::MAX]).next_geq(u64::MAX),
+            Some((0, u64::MAX))
+        );
+    }
+
+    #[test]
+    fn large_values() {
+        let values = [0, 1 << 40, (1 << 40) + 1, u64::MAX - 1, u64::MAX];
+        let ef = EliasFano::new(&values);
+        assert_eq!(ef.iter().collect::<Vec<_>>(), values);
+        assert!(ef.size_in_bits() <= values.len() * (3 + ef.low_bits()));
+    }
+
+    #[test]
+    #[should_panic(expected = "sorted")]
+    fn unsorted() {
+        EliasFano::new(&[1, 3, 2]);
+    }
+}
@@ -0,0 +1,195 @@
+//! A Fenwick tree, or binary indexed tree, of frequencies: prefix sums and
+//! updates in `O(log n)` time.
+//!
+//! The node `i`, counting from 1, holds the sum of the frequencies of the
+//! `i & i.wrapping_neg()` values up to the `i`th, so a prefix sum adds up
+//! the nodes of the set bits of its length, and the value of a cumulative
+//! frequency is found by descending through the powers of two.
+
+/// Frequencies of the values `0..n` with their prefix sums.
+///
+/// The frequencies are unsigned, and must never go below zero nor their
+/// total above `u32::MAX`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::fenwick::Fenwick;
+/// let mut fenwick = Fenwick::from_slice(&[3, 0, 5, 1]);
+/// assert_eq!(fenwick.prefix_sum(3), 8);
+/// assert_eq!(fenwick.find_by_cum(3), 2);
+/// fenwick.add(1, 2);
+/// assert_eq!(fenwick.get(1), 2);
+/// assert_eq!(fenwick.find_by_cum(3), 1);
+/// assert_eq!(fenwick.total(), 11);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fenwick {
+    /// The nodes, from 1; the first entry is unused.
+    tree: Vec<u32>,
+}
+
+impl Fenwick {
+    /// Creates a tree of `len` values of frequency zero.
+    pub fn new(len: usize) -> Fenwick {
+        Fenwick {
+            tree: vec![0; len + 1],
+        }
+    }
+
+    /// Creates a tree of the given frequencies in `O(n)` time.
+    pub fn from_slice(frequencies: &[u32]) -> Fenwick {
+        let mut tree = Vec::with_capacity(frequencies.len() + 1);
+        tree.push(0);
+        tree.extend_from_slice(frequencies);
+        for i in 1..tree.len() {
+            let parent = i + (i & i.wrapping_neg());
+            if parent < tree.len() {
+                tree[parent] += tree[i];
+            }
+        }
+        Fenwick { tree }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    /// Returns `true` if there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds `delta` to the frequency of `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn add(&mut self, index: usize, delta: i32) {
+        assert!(
+            index < self.len(),
+            "index {} out of range for length {}",
+            index,
+            self.len()
+        );
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] = self.tree[i].wrapping_add(delta as u32);
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Returns the sum of the frequencies of the values before `end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end` is greater than the length.
+    pub fn prefix_sum(&self, end: usize) -> u32 {
+        assert!(
+            end <= self.len(),
+            "end {} out of range for length {}",
+            end,
+            self.len()
+        );
+        let mut sum: u32 = 0;
+        let mut i = end;
+        while i > 0 {
+            sum = sum.wrapping_add(self.tree[i]);
+            i &= i - 1;
+        }
+        sum
+    }
+
+    /// Returns the frequency of `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn get(&self, index: usize) -> u32 {
+        assert!(
+            index < self.len(),
+            "index {} out of range for length {}",
+            index,
+            self.len()
+        );
+        self.prefix_sum(index + 1)
+            .wrapping_sub(self.prefix_sum(index))
+    }
+
+    /// Returns the sum of all frequencies.
+    pub fn total(&self) -> u32 {
+        self.prefix_sum(self.len())
+    }
+
+    /// Returns the value whose range of cumulative frequencies holds `cum`,
+    /// the one `i` with `prefix_sum(i) <= cum < prefix_sum(i + 1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cum` is not less than the total.
+    pub fn find_by_cum(&self, cum: u32) -> usize {
+        assert!(
+            cum < self.total(),
+            "cumulative frequency {} out of range",
+            cum
+        );
+        let mut pos = 0;
+        let mut rest = cum;
+        let mut step = match self.len() {
+            0 => 0,
+            len => 1 << (usize::BITS - 1 - len.leading_zeros()),
+        };
+        while step > 0 {
+            if pos + step <= self.len() && self.tree[pos + step] <= rest {
+                pos += step;
+                rest -= self.tree[pos];
+            }
+            step /= 2;
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn against_prefix_sums() {
+        for len in 0..40 {
+            let mut frequencies: Vec<u32> = (0..len as u64)
+                .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61) as u32)
+                .collect();
+            let mut fenwick = Fenwick::from_slice(&frequencies);
+            let mut incremental = Fenwick::new(len);
+            for (i, &f) in frequencies.iter().enumerate() {
+                incremental.add(i, f as i32);
+            }
+            assert_eq!(fenwick, incremental);
+            for step in 0..len {
+                let index = step * 7 % len;
+                let delta = if frequencies[index] > 2 { -2 } else { 3 };
+                frequencies[index] = (frequencies[index] as i32 + delta) as u32;
+                fenwick.add(index, delta);
+
+                let mut sum = 0;
+                for (i, &f) in frequencies.iter().enumerate() {
+                    assert_eq!(fenwick.prefix_sum(i), sum);
+                    assert_eq!(fenwick.get(i), f);
+                    for cum in sum..sum + f {
+                        assert_eq!(fenwick.find_by_cum(cum), i);
+                    }
+                    sum += f;
+                }
+                assert_eq!(fenwick.total(), sum);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cumulative frequency 4 out of range")]
+    fn cum_out_of_range() {
+        Fenwick::from_slice(&[1, 0, 3, 0]).find_by_cum(4);
+    }
+}
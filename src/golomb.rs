@@ -0,0 +1,298 @@
+//! Golomb, Golomb–Rice and exponential-Golomb codes for non-negative
+//! integers.
+//!
+//! A number `n` is split into a quotient `n / m`, written in unary (as by
+//! `BitSink::write_unary`), and a remainder `n % m`. Rice codes restrict `m` to
+//! a power of two `2^k`, so the remainder is simply the low `k` bits of `n`.
+//! Exponential-Golomb codes instead grow the bucket size geometrically.
+//!
+//! The codes work on any [`BitSink`] or [`BitSource`]. Decoders fail with
+//! `ErrorKind::UnexpectedEof` if the bits run out and with
+//! `ErrorKind::InvalidData` if the value does not fit in a `u64`.
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::bit_io::{BitSink, BitSource};
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "encoded value overflows u64")
+}
+
+/// Writes the Rice code of `n` with parameter `k`: `n >> k` in unary
+/// followed by the low `k` bits of `n` as an integer field.
+///
+/// # Panics
+///
+/// Panics if `k` is greater than 63.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::MsbBitVec;
+/// # use rust_compress::golomb::{decode_rice, encode_rice};
+/// let mut v = MsbBitVec::new();
+/// encode_rice(&mut v, 11, 2).unwrap();
+/// assert_eq!(v.to_string(), "11011");
+/// assert_eq!(decode_rice(&mut v.iter(), 2).unwrap(), 11);
+/// ```
+pub fn encode_rice<S: BitSink>(sink: &mut S, n: u64, k: usize) -> io::Result<()> {
+    assert!(k < 64, "rice parameter must be less than 64, but is {}", k);
+    sink.write_unary((n >> k) as usize)?;
+    sink.write_bits(n & ((1 << k) - 1), k)
+}
+
+/// Reads a Rice code with parameter `k`.
+///
+/// # Panics
+///
+/// Panics if `k` is greater than 63.
+pub fn decode_rice<S: BitSource>(source: &mut S, k: usize) -> io::Result<u64> {
+    assert!(k < 64, "rice parameter must be less than 64, but is {}", k);
+    let q = source.read_unary()? as u64;
+    if q > u64::MAX >> k {
+        return Err(too_large());
+    }
+    Ok((q << k) | source.read_bits(k)?)
+}
+
+/// Writes the Golomb code of `n` with parameter `m`: `n / m` in unary
+/// followed by `n % m` in truncated binary.
+///
+/// With `b = ceil(log2(m))`, the remainder takes `b - 1` bits if it is
+/// below `2^b - m` and `b` bits otherwise. The extra bit comes last, so the
+/// code is the classic one in an MSB-first stream. For a power of two `m`
+/// this is the Rice code.
+///
+/// # Panics
+///
+/// Panics if `m` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::MsbBitVec;
+/// # use rust_compress::golomb::{decode_golomb, encode_golomb};
+/// let mut v = MsbBitVec::new();
+/// encode_golomb(&mut v, 7, 5).unwrap(); // 1 in unary, remainder 2 in 2 bits
+/// encode_golomb(&mut v, 4, 5).unwrap(); // 0 in unary, remainder 4 in 3 bits
+/// assert_eq!(v.to_string(), "10100111");
+/// let mut iter = v.iter();
+/// assert_eq!(decode_golomb(&mut iter, 5).unwrap(), 7);
+/// assert_eq!(decode_golomb(&mut iter, 5).unwrap(), 4);
+/// ```
+pub fn encode_golomb<S: BitSink>(sink: &mut S, n: u64, m: u64) -> io::Result<()> {
+    assert!(m != 0, "golomb parameter must not be zero");
+    sink.write_unary((n / m) as usize)?;
+    let r = n % m;
+    if m == 1 {
+        return Ok(());
+    }
+    let (b, cutoff) = truncated_binary(m);
+    if r < cutoff {
+        sink.write_bits(r, b - 1)
+    } else {
+        let r = r + cutoff;
+        sink.write_bits(r >> 1, b - 1)?;
+        sink.write_bit(r & 1 == 1)
+    }
+}
+
+/// Reads a Golomb code with parameter `m`.
+///
+/// # Panics
+///
+/// Panics if `m` is zero.
+pub fn decode_golomb<S: BitSource>(source: &mut S, m: u64) -> io::Result<u64> {
+    assert!(m != 0, "golomb parameter must not be zero");
+    let q = source.read_unary()? as u64;
+    let r = if m == 1 {
+        0
+    } else {
+        let (b, cutoff) = truncated_binary(m);
+        let x = source.read_bits(b - 1)?;
+        if x < cutoff {
+            x
+        } else {
+            ((x << 1) | source.read_bit()? as u64) - cutoff
+        }
+    };
+    q.checked_mul(m)
+        .and_then(|n| n.checked_add(r))
+        .ok_or_else(too_large)
+}
+
+/// Writes the exponential-Golomb code of order `k` of `n`, as used by the
+/// H.264 and H.265 bitstream syntax: with `v = n + 2^k` having `N + 1`
+/// significant bits, `N - k` zeros followed by a one and the low `N` bits of
+/// `v` as an integer field.
+///
+/// In an MSB-first stream this is the code of the standards, in which the
+/// one is the leading bit of `v`; order 0 is the `ue(v)` descriptor.
+///
+/// # Panics
+///
+/// Panics if `k` is greater than 63.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::MsbBitVec;
+/// # use rust_compress::golomb::{decode_exp_golomb, encode_exp_golomb};
+/// let mut v = MsbBitVec::new();
+/// encode_exp_golomb(&mut v, 0, 0).unwrap();
+/// encode_exp_golomb(&mut v, 3, 0).unwrap();
+/// encode_exp_golomb(&mut v, 3, 1).unwrap();
+/// assert_eq!(v.to_string(), "1001000101");
+/// let mut iter = v.iter();
+/// assert_eq!(decode_exp_golomb(&mut iter, 0).unwrap(), 0);
+/// assert_eq!(decode_exp_golomb(&mut iter, 0).unwrap(), 3);
+/// assert_eq!(decode_exp_golomb(&mut iter, 1).unwrap(), 3);
+/// ```
+pub fn encode_exp_golomb<S: BitSink>(sink: &mut S, n: u64, k: usize) -> io::Result<()> {
+    assert!(
+        k < 64,
+        "exp-golomb order must be less than 64, but is {}",
+        k
+    );
+    let v = n as u128 + (1 << k);
+    let len = 127 - v.leading_zeros() as usize;
+    sink.write_bits(0, len - k)?;
+    sink.write_bit(true)?;
+    sink.write_bits(v as u64, len)
+}
+
+/// Reads an exponential-Golomb code of order `k`.
+///
+/// # Panics
+///
+/// Panics if `k` is greater than 63.
+pub fn decode_exp_golomb<S: BitSource>(source: &mut S, k: usize) -> io::Result<u64> {
+    assert!(
+        k < 64,
+        "exp-golomb order must be less than 64, but is {}",
+        k
+    );
+    let mut len = k;
+    while !source.read_bit()? {
+        len += 1;
+        if len > 64 {
+            return Err(too_large());
+        }
+    }
+    let v = (1u128 << len) | source.read_bits(len)? as u128;
+    u64::try_from(v - (1 << k)).map_err(|_| too_large())
+}
+
+/// Returns `ceil(log2(m))` and the number of remainders that take one bit
+/// less, for `m` of at least 2.
+fn truncated_binary(m: u64) -> (usize, u64) {
+    let b = 64 - (m - 1).leading_zeros() as usize;
+    let cutoff = if b == 64 {
+        0u64.wrapping_sub(m)
+    } else {
+        (1 << b) - m
+    };
+    (b, cutoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::{BitOrder, LsbFirst, MsbFirst};
+    use crate::bit_vec::{BitVec, MsbBitVec, OrderedBitVec};
+
+    fn check<O: BitOrder>() {
+        let values: Vec<u64> = (0..300)
+            .chain([1000, 4095, 4096, 65537].iter().cloned())
+            .collect();
+        for &k in [0, 1, 3, 8].iter() {
+            let mut v = OrderedBitVec::<O>::new();
+            for &n in values.iter() {
+                encode_rice(&mut v, n, k).unwrap();
+            }
+            let mut iter = v.iter();
+            for &n in values.iter() {
+                assert_eq!(decode_rice(&mut iter, k).unwrap(), n);
+            }
+            assert_eq!(iter.len(), 0);
+        }
+        for &m in [1, 2, 3, 5, 7, 8, 10, 100, 255, 256].iter() {
+            let mut v = OrderedBitVec::<O>::new();
+            for &n in values.iter() {
+                encode_golomb(&mut v, n, m).unwrap();
+            }
+            let mut iter = v.iter();
+            for &n in values.iter() {
+                assert_eq!(decode_golomb(&mut iter, m).unwrap(), n);
+            }
+            assert_eq!(iter.len(), 0);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn exp_golomb() {
+        fn check<O: BitOrder>() {
+            let mut values: Vec<u64> = (0..300).collect();
+            values.extend([u64::MAX - 1, u64::MAX, 1 << 63, (1 << 63) - 1].iter());
+            for &k in [0, 1, 2, 5, 63].iter() {
+                let mut v = OrderedBitVec::<O>::new();
+                for &n in values.iter() {
+                    let start = v.len();
+                    encode_exp_golomb(&mut v, n, k).unwrap();
+                    let len = 127 - (n as u128 + (1 << k)).leading_zeros() as usize;
+                    assert_eq!(v.len() - start, 2 * len + 1 - k);
+                }
+                let mut iter = v.iter();
+                for &n in values.iter() {
+                    assert_eq!(decode_exp_golomb(&mut iter, k).unwrap(), n);
+                }
+                assert_eq!(iter.len(), 0);
+            }
+        }
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+
+        // order 0 is gamma of `n + 1`
+        for n in 1..100 {
+            let mut exp = MsbBitVec::new();
+            let mut gamma = MsbBitVec::new();
+            encode_exp_golomb(&mut exp, n - 1, 0).unwrap();
+            crate::universal_codes::encode_gamma(&mut gamma, n).unwrap();
+            assert_eq!(exp, gamma);
+        }
+
+        let too_long: BitVec = std::iter::repeat_n(false, 65).chain(Some(true)).collect();
+        assert!(decode_exp_golomb(&mut too_long.iter(), 0).is_err());
+    }
+
+    #[test]
+    fn golomb_matches_rice() {
+        for n in 0..200 {
+            let mut rice = MsbBitVec::new();
+            let mut golomb = MsbBitVec::new();
+            encode_rice(&mut rice, n, 4).unwrap();
+            encode_golomb(&mut golomb, n, 16).unwrap();
+            assert_eq!(rice, golomb);
+        }
+    }
+
+    #[test]
+    fn large_parameters() {
+        let mut v = BitVec::new();
+        encode_rice(&mut v, u64::MAX, 63).unwrap();
+        encode_golomb(&mut v, u64::MAX, u64::MAX).unwrap();
+        encode_golomb(&mut v, u64::MAX - 1, u64::MAX).unwrap();
+        let mut iter = v.iter();
+        assert_eq!(decode_rice(&mut iter, 63).unwrap(), u64::MAX);
+        assert_eq!(decode_golomb(&mut iter, u64::MAX).unwrap(), u64::MAX);
+        assert_eq!(decode_golomb(&mut iter, u64::MAX).unwrap(), u64::MAX - 1);
+        assert_eq!(iter.len(), 0);
+    }
+}
@@ -1,130 +1,1036 @@
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
 
+use crate::bit_io::{BitSink, BitSource};
+use crate::bit_order::{BitOrder, LsbFirst};
 use crate::bit_vec::BitVec;
 
+mod alphabetic;
+mod codec;
+pub mod deflate;
+mod shannon_fano;
+mod sparse;
+mod table;
+
+pub use self::codec::{compress, compress_blocks, decompress, decompress_blocks};
+pub use self::sparse::{SparseDecoder, SparseEncoder, SparseHuffmanTree};
+pub use self::table::TableDecoder;
+
 #[derive(Clone, Debug)]
-struct Node {
-    count: u128,
+struct Node<C> {
+    count: C,
     value: Option<usize>,
-    left: Option<Box<Node>>,
-    right: Option<Box<Node>>,
+    /// The child of the bit 1, by index in the arena.
+    left: Option<usize>,
+    /// The child of the bit 0, by index in the arena.
+    right: Option<usize>,
 }
 
-impl PartialEq for Node {
-    fn eq(&self, other: &Node) -> bool {
-        self.count == other.count
-    }
+/// The nodes of a Huffman tree, stored flat with children before their
+/// parents and the root last. The tree is walked with explicit stacks, so
+/// that skewed trees of long codes take no deep recursion.
+#[derive(Clone, Debug)]
+struct Arena<C> {
+    nodes: Vec<Node<C>>,
 }
 
-impl Eq for Node {}
+impl<C: Count> Arena<C> {
+    fn with_capacity(capacity: usize) -> Arena<C> {
+        Arena {
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds the leaf of `value` and returns its index.
+    fn leaf(&mut self, value: usize, count: C) -> usize {
+        self.nodes.push(Node {
+            count,
+            value: Some(value),
+            left: None,
+            right: None,
+        });
+        self.nodes.len() - 1
+    }
 
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
-        Some(self.cmp(other))
+    /// Adds the parent of `one` and `zero`, the children of the bits 1 and
+    /// 0, and returns its index.
+    fn join(&mut self, one: Option<usize>, zero: Option<usize>) -> usize {
+        let count = one.iter().chain(zero.iter()).fold(C::default(), |sum, &n| {
+            sum.checked_add(self.nodes[n].count)
+                .expect("the total count overflows the count type")
+        });
+        self.nodes.push(Node {
+            count,
+            value: None,
+            left: one,
+            right: zero,
+        });
+        self.nodes.len() - 1
     }
-}
 
-impl Ord for Node {
-    fn cmp(&self, other: &Node) -> Ordering {
-        // reverse the order, to pop minimum value from BinaryHeap
-        other.count.cmp(&self.count)
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
     }
-}
 
-impl Node {
-    fn make_encoder(&self, map: &mut Vec<BitVec>, current: &mut Vec<bool>) {
-        if let Some(value) = self.value {
-            map[value] = current.as_slice().into();
-        } else {
-            if let Some(ref child) = self.left {
-                current.push(true);
-                child.make_encoder(map, current);
-                current.pop();
+    /// Sets the codes of the leaves, in stream order.
+    fn make_encoder(&self, map: &mut [(u64, u8)]) {
+        let mut stack = vec![(self.root(), 0u64, 0)];
+        while let Some((idx, code, len)) = stack.pop() {
+            let node = &self.nodes[idx];
+            if let Some(value) = node.value {
+                map[value] = (code, len as u8);
+                continue;
+            }
+            if node.left.is_some() || node.right.is_some() {
+                assert!(len < 64, "codes longer than 64 bits are not supported");
             }
-            if let Some(ref child) = self.right {
-                current.push(false);
-                child.make_encoder(map, current);
-                current.pop();
+            if let Some(child) = node.left {
+                stack.push((child, code | (1 << len), len + 1));
+            }
+            if let Some(child) = node.right {
+                stack.push((child, code, len + 1));
+            }
+        }
+    }
+
+    fn collect_leaves(&self, lengths: &mut [usize], counts: &mut [C]) {
+        let mut stack = vec![(self.root(), 0)];
+        while let Some((idx, depth)) = stack.pop() {
+            let node = &self.nodes[idx];
+            if let Some(value) = node.value {
+                lengths[value] = depth;
+                counts[value] = node.count;
+            }
+            for &child in node.left.iter().chain(node.right.iter()) {
+                stack.push((child, depth + 1));
             }
         }
     }
 
+    /// Builds the tree of the canonical code with the given lengths, in
+    /// which the codes of each length are consecutive numbers, assigned in
+    /// the order of the values, and shorter codes come before longer ones:
+    /// the first code is all zeros.
+    ///
+    /// The lengths must describe a complete code, as checked by
+    /// `check_lengths`. Values of length zero get no code.
+    fn canonical(lengths: &[usize], counts: &[C]) -> Arena<C> {
+        let mut values: Vec<usize> = (0..lengths.len()).filter(|&v| lengths[v] > 0).collect();
+        values.sort_by_key(|&v| (lengths[v], v));
+        let max_len = values.last().map_or(0, |&v| lengths[v]);
+        let mut arena = Arena::with_capacity(2 * values.len() + 1);
+        // the nodes at the current depth, in increasing order of their codes
+        let mut level: Vec<usize> = Vec::new();
+        for depth in (1..=max_len).rev() {
+            let start = values.partition_point(|&v| lengths[v] < depth);
+            let mut next = Vec::with_capacity(values.len() - start + level.len() / 2);
+            for value in values.drain(start..) {
+                next.push(arena.leaf(value, counts[value]));
+            }
+            let mut pairs = level.into_iter();
+            while let Some(zero) = pairs.next() {
+                let one = pairs.next().expect("code lengths are incomplete");
+                next.push(arena.join(Some(one), Some(zero)));
+            }
+            level = next;
+        }
+        // a single code is the bit 0, and the root of no code is empty
+        let mut pairs = level.into_iter();
+        let zero = pairs.next();
+        let one = pairs.next();
+        assert!(pairs.next().is_none(), "code lengths are oversubscribed");
+        arena.join(one, zero);
+        arena
+    }
+
+    /// Lays the tree out for `HuffmanDecoder`, from the root down, the
+    /// child of the bit 1 right after its parent.
     fn make_decoder(&self, map: &mut Vec<DecoderNode>) {
-        if let Some(value) = self.value {
-            map.push(DecoderNode::Value(value));
-        } else {
-            let idx = map.len();
-            map.push(DecoderNode::Jump(0));
-            if let Some(ref child) = self.left {
-                child.make_decoder(map);
+        enum Step {
+            Visit(Option<usize>),
+            /// Points the jump at `map[idx]` to the next node laid out.
+            Patch(usize),
+        }
+
+        let mut stack = vec![Step::Visit(Some(self.root()))];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Visit(None) => map.push(DecoderNode::Invalid),
+                Step::Visit(Some(idx)) => {
+                    let node = &self.nodes[idx];
+                    if let Some(value) = node.value {
+                        map.push(DecoderNode::Value(value));
+                    } else {
+                        stack.push(Step::Visit(node.right));
+                        stack.push(Step::Patch(map.len()));
+                        stack.push(Step::Visit(node.left));
+                        map.push(DecoderNode::Jump(0));
+                    }
+                }
+                Step::Patch(idx) => map[idx] = DecoderNode::Jump(map.len()),
             }
-            map[idx] = DecoderNode::Jump(map.len());
-            if let Some(ref child) = self.right {
-                child.make_decoder(map);
+        }
+    }
+}
+
+/// A type of values coded by a Huffman tree, mapped to the dense indices
+/// `0..n` of an alphabet of `n` values, the positions in the counts from
+/// which the tree is built.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::huffman::{HuffmanTree, Symbol};
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// enum Suit {
+///     Clubs,
+///     Diamonds,
+///     Hearts,
+///     Spades,
+/// }
+///
+/// impl Symbol for Suit {
+///     fn to_index(self) -> usize {
+///         self as usize
+///     }
+///
+///     fn from_index(index: usize) -> Option<Suit> {
+///         [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+///             .get(index)
+///             .copied()
+///     }
+/// }
+///
+/// let tree = HuffmanTree::new(&[1, 2, 4, 8]).with_symbol::<Suit>();
+/// let (encoder, decoder) = (tree.encoder(), tree.decoder());
+/// let bits = encoder.encode_all(&[Suit::Spades, Suit::Clubs]);
+/// assert_eq!(decoder.decode(&mut bits.iter()), Suit::Spades);
+/// ```
+pub trait Symbol: Copy {
+    /// Returns the index of the value in the alphabet.
+    fn to_index(self) -> usize;
+
+    /// Returns the value of an index, or `None` if there is none.
+    fn from_index(index: usize) -> Option<Self>;
+}
+
+impl Symbol for usize {
+    fn to_index(self) -> usize {
+        self
+    }
+
+    fn from_index(index: usize) -> Option<usize> {
+        Some(index)
+    }
+}
+
+macro_rules! impl_symbol {
+    ($($t:ty)*) => {$(
+        impl Symbol for $t {
+            fn to_index(self) -> usize {
+                self as usize
+            }
+
+            fn from_index(index: usize) -> Option<$t> {
+                <$t>::try_from(index).ok()
             }
         }
+    )*};
+}
+
+impl_symbol!(u8 u16 u32);
+
+impl Symbol for char {
+    fn to_index(self) -> usize {
+        self as usize
+    }
+
+    fn from_index(index: usize) -> Option<char> {
+        u32::try_from(index).ok().and_then(char::from_u32)
     }
 }
 
+/// An unsigned integer type of the counts a Huffman tree is built from.
+///
+/// The nodes of a tree hold the sums of the counts under them, up to the
+/// total count, which must fit in the type. `HuffmanTree::new` takes `u128`
+/// counts, which never overflow in practice; `HuffmanTree::from_counts`
+/// takes narrower ones, which take less memory and compare faster on small
+/// targets.
+pub trait Count: Copy + Ord + Default {
+    /// Returns the sum of two counts, or `None` if it overflows.
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// Returns the count as a `u128`, which holds any count.
+    fn to_u128(self) -> u128;
+}
+
+macro_rules! impl_count {
+    ($($t:ty)*) => {$(
+        impl Count for $t {
+            fn checked_add(self, other: $t) -> Option<$t> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn to_u128(self) -> u128 {
+                self as u128
+            }
+        }
+    )*};
+}
+
+impl_count!(u8 u16 u32 u64 u128 usize);
+
+/// A Huffman tree of values of type `T`, by default the indices themselves.
+///
+/// The constructors take counts indexed by `usize`; `with_symbol` turns the
+/// tree into one of another type. The counts are of type `C`, by default
+/// `u128`, which the tree keeps for `canonical` and `stats`.
 #[derive(Clone, Debug)]
-pub struct HuffmanTree {
+pub struct HuffmanTree<T: Symbol = usize, C: Count = u128> {
     elements: usize,
-    nodes: Node,
+    eof: Option<usize>,
+    nodes: Arena<C>,
+    symbol: PhantomData<T>,
 }
 
 impl HuffmanTree {
+    /// Builds the Huffman tree of `counts`, the number of occurrences of
+    /// every value.
+    ///
+    /// Every value gets a code, even one that does not occur. The only
+    /// value of an alphabet of one gets the one-bit code `0`, and an empty
+    /// alphabet gets a tree without codes, whose decoder rejects any input.
+    /// Ties between equal counts are broken by the values, so the codes are
+    /// the same across runs and platforms.
+    ///
+    /// The tree is built by the two-queue algorithm on the values sorted by
+    /// count, which takes linear time if the counts are in increasing order
+    /// already, and is dominated by the sort otherwise.
+    ///
+    /// Of the nodes of equal count, the values are merged first and then
+    /// the merged nodes in the order they were made. This is the classic
+    /// tie-break that gives, among all the trees of least average length,
+    /// the one whose code lengths vary least, which is also the one of the
+    /// shortest longest code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::new(&[42]);
+    /// assert_eq!(tree.encoder().encode(0).to_string(), "0");
+    /// // not [1, 2, 3, 4, 4], as long on average
+    /// let tree = HuffmanTree::new(&[4, 2, 2, 1, 1]);
+    /// assert_eq!(tree.to_lengths(), vec![2, 2, 2, 3, 3]);
+    /// ```
     pub fn new(counts: &[u128]) -> HuffmanTree {
-        assert!(counts.len() > 1);
-        let mut heap = BinaryHeap::with_capacity(counts.len());
-        for (value, count) in counts.iter().enumerate() {
-            heap.push(Node {
-                count: *count,
-                value: Some(value),
-                left: None,
-                right: None,
-            });
-        }
-        while heap.len() > 1 {
-            let left = heap.pop().unwrap();
-            let right = heap.pop().unwrap();
-            heap.push(Node {
-                count: left.count + right.count,
-                value: None,
-                left: Some(Box::new(left)),
-                right: Some(Box::new(right)),
-            });
+        HuffmanTree::from_counts(counts)
+    }
+
+    /// Builds the tree of `counts` with an extra end-of-stream value, which
+    /// gets the value `counts.len()` and a count of one.
+    ///
+    /// Writing the end-of-stream code after the data lets the decoder stop
+    /// without knowing the number of values, with `decode_until_eof`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::with_eof(&[10, 1, 4]);
+    /// assert_eq!(tree.eof(), Some(3));
+    /// let (encoder, decoder) = (tree.encoder(), tree.decoder());
+    /// let mut bits = encoder.encode_all(&[0, 2, 0, 1]);
+    /// bits.extend_from_bitvec(&encoder.encode_eof());
+    /// // anything after the end-of-stream code is ignored
+    /// bits.push_bits(0b1010, 4);
+    /// let values = decoder.decode_until_eof(&mut bits.iter()).unwrap();
+    /// assert_eq!(values, vec![0, 2, 0, 1]);
+    /// ```
+    pub fn with_eof(counts: &[u128]) -> HuffmanTree {
+        let mut with_eof = counts.to_vec();
+        with_eof.push(1);
+        let mut tree = HuffmanTree::new(&with_eof);
+        tree.set_eof(counts.len());
+        tree
+    }
+
+    /// Builds the tree of the bytes of `data`, with an alphabet of all 256
+    /// byte values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::from_data(b"abracadabra");
+    /// let encoder = tree.encoder();
+    /// assert_eq!(encoder.encode(b'a').len(), 1);
+    /// assert!(encoder.encode(b'z').len() > 8);
+    /// ```
+    pub fn from_data(data: &[u8]) -> HuffmanTree<u8> {
+        let mut counts = [0; 256];
+        for &byte in data.iter() {
+            counts[byte as usize] += 1;
+        }
+        HuffmanTree::new(&counts).with_symbol()
+    }
+
+    /// Builds an optimal tree among those whose codes are at most `max_len`
+    /// bits long, using the package-merge algorithm, and returns it in
+    /// canonical form.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `2^max_len` counts, or any at all when
+    /// `max_len` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let counts = [1, 1, 2, 3, 5, 8, 13, 21];
+    /// assert_eq!(HuffmanTree::new(&counts).to_lengths()[0], 7);
+    /// let tree = HuffmanTree::new_limited(&counts, 4);
+    /// assert_eq!(tree.to_lengths(), vec![4, 4, 4, 4, 3, 3, 2, 2]);
+    /// ```
+    pub fn new_limited(counts: &[u128], max_len: usize) -> HuffmanTree {
+        assert!(
+            counts.is_empty() || (max_len > 0 && (max_len >= 64 || counts.len() <= 1 << max_len)),
+            "{} values do not fit in codes of {} bits",
+            counts.len(),
+            max_len
+        );
+        if counts.len() < 2 {
+            return HuffmanTree::new(counts);
+        }
+        let mut leaves: Vec<usize> = (0..counts.len()).collect();
+        leaves.sort_by_key(|&value| counts[value]);
+        // the list of coins of each denomination, from 2^-max_len up; a
+        // package of the list at `level` consists of two coins of the list
+        // at `level - 1`
+        let mut levels: Vec<Vec<(u128, Coin)>> = Vec::with_capacity(max_len);
+        for level in 0..max_len.min(counts.len()) {
+            let mut packages = Vec::new();
+            if level > 0 {
+                let prev = &levels[level - 1];
+                for i in 0..prev.len() / 2 {
+                    let weight = prev[2 * i].0 + prev[2 * i + 1].0;
+                    packages.push((weight, Coin::Package(i)));
+                }
+            }
+            let mut list = Vec::with_capacity(leaves.len() + packages.len());
+            let mut packages = packages.into_iter().peekable();
+            for &value in leaves.iter() {
+                while let Some(package) = packages.next_if(|p| p.0 < counts[value]) {
+                    list.push(package);
+                }
+                list.push((counts[value], Coin::Leaf(value)));
+            }
+            list.extend(packages);
+            levels.push(list);
+        }
+        let mut lengths = vec![0; counts.len()];
+        let top = levels.len() - 1;
+        for i in 0..2 * counts.len() - 2 {
+            spend(&levels, top, i, &mut lengths);
         }
         HuffmanTree {
             elements: counts.len(),
-            nodes: heap.pop().unwrap(),
+            eof: None,
+            nodes: Arena::canonical(&lengths, counts),
+            symbol: PhantomData,
+        }
+    }
+
+    /// Builds the canonical tree with the given code lengths, as returned by
+    /// `to_lengths`.
+    ///
+    /// A length of zero means that the value does not occur; it gets an
+    /// empty code. Fails if the lengths do not describe a complete prefix
+    /// code, except that a single code may be one bit long, as made by
+    /// `new` for an alphabet of one, and that there may be no code at all.
+    /// The counts of the values are unknown and taken to be zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::{CodeLengthsError, HuffmanTree};
+    /// let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]).canonical();
+    /// let rebuilt = HuffmanTree::from_lengths(&tree.to_lengths()).unwrap();
+    /// assert_eq!(rebuilt.encoder().encode(4), tree.encoder().encode(4));
+    ///
+    /// let err = HuffmanTree::from_lengths(&[1, 2, 3]).unwrap_err();
+    /// assert_eq!(err, CodeLengthsError::Incomplete);
+    /// ```
+    pub fn from_lengths(lengths: &[usize]) -> Result<HuffmanTree, CodeLengthsError> {
+        check_lengths(lengths)?;
+        Ok(HuffmanTree {
+            elements: lengths.len(),
+            eof: None,
+            nodes: Arena::canonical(lengths, &vec![0; lengths.len()]),
+            symbol: PhantomData,
+        })
+    }
+}
+
+impl<C: Count> HuffmanTree<usize, C> {
+    /// Builds the Huffman tree of counts of any unsigned integer type, the
+    /// same as `new` does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let counts: [u16; 4] = [1, 2, 4, 8];
+    /// let tree = HuffmanTree::from_counts(&counts);
+    /// assert_eq!(tree.to_lengths(), HuffmanTree::new(&[1, 2, 4, 8]).to_lengths());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sum of the counts overflows `C`.
+    pub fn from_counts(counts: &[C]) -> HuffmanTree<usize, C> {
+        if counts.len() < 2 {
+            return HuffmanTree {
+                elements: counts.len(),
+                eof: None,
+                nodes: Arena::canonical(&vec![1; counts.len()], counts),
+                symbol: PhantomData,
+            };
+        }
+        // the two-queue algorithm: the leaves sorted by count and the merged
+        // nodes, which are made in increasing order of count, the latter
+        // being the nodes of the arena after the leaves
+        let mut arena = Arena::with_capacity(2 * counts.len() - 1);
+        let mut leaves: Vec<usize> = counts
+            .iter()
+            .enumerate()
+            .map(|(value, &count)| arena.leaf(value, count))
+            .collect();
+        // stable, and linear on counts that are already sorted
+        leaves.sort_by_key(|&leaf| counts[leaf]);
+        let mut next_leaf = 0;
+        let mut next_merged = counts.len();
+        // the least count first, and of equal counts a leaf first
+        let mut pop = |arena: &Arena<C>| -> usize {
+            let leaf = leaves.get(next_leaf).copied();
+            let merged = arena.nodes.get(next_merged).map(|node| node.count);
+            match (leaf, merged) {
+                (Some(leaf), Some(count)) if arena.nodes[leaf].count > count => {}
+                (Some(leaf), _) => {
+                    next_leaf += 1;
+                    return leaf;
+                }
+                _ => {}
+            }
+            next_merged += 1;
+            next_merged - 1
+        };
+        for _ in 1..counts.len() {
+            let left = pop(&arena);
+            let right = pop(&arena);
+            arena.join(Some(left), Some(right));
+        }
+        HuffmanTree {
+            elements: counts.len(),
+            eof: None,
+            nodes: arena,
+            symbol: PhantomData,
+        }
+    }
+
+    /// Returns the same tree coding values of type `U`.
+    ///
+    /// Decoding a value that `U` cannot represent fails as an invalid code.
+    pub fn with_symbol<U: Symbol>(self) -> HuffmanTree<U, C> {
+        HuffmanTree {
+            elements: self.elements,
+            eof: self.eof,
+            nodes: self.nodes,
+            symbol: PhantomData,
+        }
+    }
+}
+
+impl<T: Symbol> HuffmanTree<T> {
+    /// Builds the tree of the values yielded by `symbols`, with an alphabet
+    /// of the indices up to the largest one, and of at least two values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::from_symbols(vec![3usize, 1, 3, 3]);
+    /// assert_eq!(tree.to_lengths(), vec![3, 2, 3, 1]);
+    /// let tree = HuffmanTree::from_symbols("abracadabra".chars());
+    /// assert_eq!(tree.encoder().encode('a').len(), 1);
+    /// ```
+    pub fn from_symbols<I: IntoIterator<Item = T>>(symbols: I) -> HuffmanTree<T> {
+        let mut counts = vec![0; 2];
+        for symbol in symbols {
+            let index = symbol.to_index();
+            if index >= counts.len() {
+                counts.resize(index + 1, 0);
+            }
+            counts[index] += 1;
+        }
+        HuffmanTree::new(&counts).with_symbol()
+    }
+}
+
+impl<T: Symbol, C: Count> HuffmanTree<T, C> {
+    /// Returns the end-of-stream value, if any.
+    pub fn eof(&self) -> Option<usize> {
+        self.eof
+    }
+
+    /// Makes `value` the end-of-stream value, for instance in a tree rebuilt
+    /// with `from_lengths` from one made by `with_eof`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range.
+    pub fn set_eof(&mut self, value: usize) {
+        assert!(
+            value < self.elements,
+            "value {} out of range for alphabet of size {}",
+            value,
+            self.elements
+        );
+        self.eof = Some(value);
+    }
+
+    /// Returns the canonical Huffman tree with the same code lengths.
+    ///
+    /// Canonical codes are determined by their lengths alone, so a decoder
+    /// only needs the length of every code to rebuild them, as in DEFLATE.
+    /// The codes of each length are consecutive binary numbers assigned in
+    /// the order of the values, and each length starts after the codes of
+    /// the shorter ones, the first code being all zeros.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::new(&[1, 2, 4, 8]).canonical();
+    /// let encoder = tree.encoder();
+    /// assert_eq!(encoder.encode(3).to_string(), "0");
+    /// assert_eq!(encoder.encode(2).to_string(), "10");
+    /// assert_eq!(encoder.encode(0).to_string(), "110");
+    /// assert_eq!(encoder.encode(1).to_string(), "111");
+    /// ```
+    pub fn canonical(&self) -> HuffmanTree<T, C> {
+        let (lengths, counts) = self.leaves();
+        HuffmanTree {
+            elements: self.elements,
+            eof: self.eof,
+            nodes: Arena::canonical(&lengths, &counts),
+            symbol: PhantomData,
+        }
+    }
+
+    /// Returns the length of the code of every value, from which
+    /// `from_lengths` rebuilds the canonical tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::new(&[1, 2, 4, 8]);
+    /// assert_eq!(tree.to_lengths(), vec![3, 3, 2, 1]);
+    /// ```
+    pub fn to_lengths(&self) -> Vec<usize> {
+        self.leaves().0
+    }
+
+    /// Returns the length of the longest code, zero if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// assert_eq!(HuffmanTree::new(&[1, 2, 4, 8]).max_len(), 3);
+    /// ```
+    pub fn max_len(&self) -> usize {
+        self.to_lengths().into_iter().max().unwrap_or(0)
+    }
+
+    /// Returns statistics of the code over the counts it was built from,
+    /// or `None` if they are unknown, as in a tree from `from_lengths`, or
+    /// all zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let stats = HuffmanTree::new(&[1, 1, 2, 4]).stats().unwrap();
+    /// assert_eq!(stats.entropy(), 1.75);
+    /// assert_eq!(stats.average_len(), 1.75);
+    /// let stats = HuffmanTree::new(&[1, 1, 1]).stats().unwrap();
+    /// assert!(stats.redundancy() > 0.05);
+    /// ```
+    pub fn stats(&self) -> Option<CodeStats> {
+        let (lengths, counts) = self.leaves();
+        let total: u128 = counts.iter().map(|count| count.to_u128()).sum();
+        if total == 0 {
+            return None;
+        }
+        let total = total as f64;
+        let mut entropy = 0.0;
+        let mut average_len = 0.0;
+        for (&len, &count) in lengths.iter().zip(counts.iter()) {
+            let count = count.to_u128();
+            if count > 0 {
+                let p = count as f64 / total;
+                entropy -= p * p.log2();
+                average_len += p * len as f64;
+            }
+        }
+        Some(CodeStats {
+            entropy,
+            average_len,
+        })
+    }
+
+    /// Returns the tree in the DOT language of Graphviz, for drawing it.
+    ///
+    /// Inner nodes are labeled with their counts, and the leaves, drawn as
+    /// boxes, with their values, counts and codes. Edges are labeled with
+    /// their bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let dot = HuffmanTree::new(&[1, 2]).to_dot();
+    /// assert_eq!(
+    ///     dot,
+    ///     r#"digraph huffman {
+    ///     n2 [label="3"];
+    ///     n2 -> n1 [label="0"];
+    ///     n2 -> n0 [label="1"];
+    ///     n1 [shape=box, label="1\n2\n0"];
+    ///     n0 [shape=box, label="0\n1\n1"];
+    /// }
+    /// "#
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::from("digraph huffman {\n");
+        let mut stack = vec![(self.nodes.root(), String::new())];
+        while let Some((idx, code)) = stack.pop() {
+            let node = &self.nodes.nodes[idx];
+            let count = node.count.to_u128();
+            match node.value {
+                Some(value) => writeln!(
+                    out,
+                    "    n{} [shape=box, label=\"{}\\n{}\\n{}\"];",
+                    idx, value, count, code
+                ),
+                None => writeln!(out, "    n{} [label=\"{}\"];", idx, count),
+            }
+            .unwrap();
+            for &(bit, child) in [('0', node.right), ('1', node.left)].iter() {
+                if let Some(child) = child {
+                    writeln!(out, "    n{} -> n{} [label=\"{}\"];", idx, child, bit).unwrap();
+                }
+            }
+            // the child of the bit 0 is written first
+            for &(bit, child) in [('1', node.left), ('0', node.right)].iter() {
+                if let Some(child) = child {
+                    stack.push((child, format!("{}{}", code, bit)));
+                }
+            }
         }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Returns the code length and the count of every value.
+    fn leaves(&self) -> (Vec<usize>, Vec<C>) {
+        let mut lengths = vec![0; self.elements];
+        let mut counts = vec![C::default(); self.elements];
+        self.nodes.collect_leaves(&mut lengths, &mut counts);
+        (lengths, counts)
     }
 
-    pub fn encoder(&self) -> HuffmanEncoder {
-        let mut map = Vec::with_capacity(self.elements);
-        for _ in 0..self.elements {
-            map.push(BitVec::new());
+    /// Returns the encoder of the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a code is longer than 64 bits, which takes counts of more
+    /// than `2^44`; see `new_limited` to bound the lengths.
+    pub fn encoder(&self) -> HuffmanEncoder<T> {
+        let mut map = vec![(0, 0); self.elements];
+        self.nodes.make_encoder(&mut map);
+        HuffmanEncoder {
+            map,
+            eof: self.eof,
+            symbol: PhantomData,
         }
-        self.nodes.make_encoder(&mut map, &mut Vec::new());
-        HuffmanEncoder { map }
     }
 
-    pub fn decoder(&self) -> HuffmanDecoder {
+    pub fn decoder(&self) -> HuffmanDecoder<T> {
         let mut map = Vec::with_capacity(self.elements * 2 + 1);
         self.nodes.make_decoder(&mut map);
-        HuffmanDecoder { map }
+        HuffmanDecoder {
+            map,
+            eof: self.eof,
+            symbol: PhantomData,
+        }
+    }
+
+    /// Returns a decoder looking up `bits` bits of input at a time, see
+    /// [`TableDecoder`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is 0 or greater than 56.
+    pub fn table_decoder(&self, bits: usize) -> TableDecoder<T> {
+        TableDecoder::new(self, bits)
+    }
+}
+
+/// Statistics of a Huffman code, returned by `HuffmanTree::stats`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CodeStats {
+    entropy: f64,
+    average_len: f64,
+}
+
+impl CodeStats {
+    /// Returns the Shannon entropy of the counts in bits per value, the
+    /// least average length of any code, which arithmetic coding nears.
+    pub fn entropy(&self) -> f64 {
+        self.entropy
+    }
+
+    /// Returns the average code length in bits per value, weighted by the
+    /// counts.
+    pub fn average_len(&self) -> f64 {
+        self.average_len
+    }
+
+    /// Returns the bits per value spent over the entropy, from zero when
+    /// all counts are powers of two in proportion, to less than one.
+    pub fn redundancy(&self) -> f64 {
+        self.average_len - self.entropy
+    }
+}
+
+/// A coin of the package-merge algorithm: a value, or a package of two
+/// coins of the previous denomination.
+#[derive(Clone, Copy, Debug)]
+enum Coin {
+    Leaf(usize),
+    Package(usize),
+}
+
+/// Adds one to the code length of every value in the `i`-th coin of `level`.
+fn spend(levels: &[Vec<(u128, Coin)>], level: usize, i: usize, lengths: &mut [usize]) {
+    match levels[level][i].1 {
+        Coin::Leaf(value) => lengths[value] += 1,
+        Coin::Package(j) => {
+            spend(levels, level - 1, 2 * j, lengths);
+            spend(levels, level - 1, 2 * j + 1, lengths);
+        }
+    }
+}
+
+/// Checks that the nonzero lengths describe a complete prefix code, that
+/// is, that the sum of `2^-len` is one, or are a single length of one or
+/// none at all.
+fn check_lengths(lengths: &[usize]) -> Result<(), CodeLengthsError> {
+    let mut sorted: Vec<usize> = lengths.iter().copied().filter(|&l| l > 0).collect();
+    if sorted.is_empty() || sorted == [1] {
+        return Ok(());
+    }
+    sorted.sort_unstable();
+    // the number of unassigned codes of the current length; once it exceeds
+    // the number of values left, some of them must stay unassigned
+    let mut free: usize = 1;
+    let mut rest = &sorted[..];
+    for depth in 1.. {
+        if rest.is_empty() {
+            break;
+        }
+        if free == 0 {
+            return Err(CodeLengthsError::Oversubscribed);
+        }
+        let used = rest.iter().take_while(|&&l| l == depth).count();
+        rest = &rest[used..];
+        free = match (2 * free).checked_sub(used) {
+            Some(free) => free,
+            None => return Err(CodeLengthsError::Oversubscribed),
+        };
+        if free > rest.len() {
+            return Err(CodeLengthsError::Incomplete);
+        }
+    }
+    if free == 0 {
+        Ok(())
+    } else {
+        Err(CodeLengthsError::Incomplete)
+    }
+}
+
+/// An error returned by `HuffmanTree::from_lengths` and
+/// `SparseHuffmanTree::from_lengths` for lengths that do not describe a
+/// Huffman code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CodeLengthsError {
+    /// The codes of the given lengths do not fit: the lengths are too short.
+    Oversubscribed,
+    /// Some codes are left unused: the lengths are too long.
+    Incomplete,
+    /// The value of this index is given two lengths.
+    Duplicate(usize),
+}
+
+impl fmt::Display for CodeLengthsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodeLengthsError::Oversubscribed => write!(f, "code lengths are oversubscribed"),
+            CodeLengthsError::Incomplete => write!(f, "code lengths are incomplete"),
+            CodeLengthsError::Duplicate(value) => write!(f, "value {} given twice", value),
+        }
     }
 }
 
+impl error::Error for CodeLengthsError {}
+
 #[derive(Clone, Debug)]
-pub struct HuffmanEncoder {
-    map: Vec<BitVec>,
+pub struct HuffmanEncoder<T: Symbol = usize> {
+    /// The code and the code length of every value, the first bit of the
+    /// code in bit 0.
+    map: Vec<(u64, u8)>,
+    eof: Option<usize>,
+    symbol: PhantomData<T>,
 }
 
-impl HuffmanEncoder {
-    pub fn encode(&self, value: usize) -> &BitVec {
-        self.map.get(value).unwrap()
+impl<T: Symbol> HuffmanEncoder<T> {
+    /// Returns the code of `value`; see `encode_into` to append it to a bit
+    /// vector without making a new one.
+    pub fn encode(&self, value: T) -> BitVec {
+        let mut code = BitVec::new();
+        self.encode_into(value, &mut code);
+        code
+    }
+
+    /// Returns the code and the code length of every value, by index. The
+    /// code is in the low bits in stream order, the first bit in bit 0, as
+    /// read by `BitPeek::peek`; a value without a code has a length of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let encoder = HuffmanTree::new(&[1, 2, 4, 8]).canonical().encoder();
+    /// assert_eq!(encoder.encode(0).to_string(), "110");
+    /// assert_eq!(encoder.codes(), [(0b011, 3), (0b111, 3), (0b01, 2), (0b0, 1)]);
+    /// ```
+    pub fn codes(&self) -> &[(u64, u8)] {
+        &self.map
+    }
+
+    /// Returns the length of the longest code, zero if there is none.
+    pub fn max_len(&self) -> usize {
+        self.map
+            .iter()
+            .map(|&(_, len)| len as usize)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Appends the code of `value` to `out` in one piece.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let encoder = HuffmanTree::new(&[1, 2, 4, 8]).encoder();
+    /// let mut out = BitVec::new();
+    /// encoder.encode_into(3, &mut out);
+    /// encoder.encode_into(0, &mut out);
+    /// assert_eq!(out.len(), 4);
+    /// ```
+    pub fn encode_into(&self, value: T, out: &mut BitVec) {
+        let (code, len) = self.map[value.to_index()];
+        out.push_bits(code, len as usize);
+    }
+
+    /// Writes the code of `value` to `sink`, such as a [`BitWriter`] over
+    /// a file, so that large inputs can be compressed in constant memory.
+    ///
+    /// [`BitWriter`]: crate::bit_io::BitWriter
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_io::BitWriter;
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let encoder = HuffmanTree::new(&[1, 2, 4, 8]).canonical().encoder();
+    /// let mut writer = BitWriter::new(Vec::new());
+    /// for &value in [3, 3, 0].iter() {
+    ///     encoder.encode_to(value, &mut writer).unwrap();
+    /// }
+    /// assert_eq!(writer.finish().unwrap(), [0b0000_1100]);
+    /// ```
+    pub fn encode_to<S: BitSink>(&self, value: T, sink: &mut S) -> io::Result<()> {
+        // the sink lays out fields in its own order, so write bit by bit
+        let (code, len) = self.map[value.to_index()];
+        for i in 0..len {
+            sink.write_bit((code >> i) & 1 == 1)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the code of the end-of-stream value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has no end-of-stream value.
+    pub fn encode_eof(&self) -> BitVec {
+        let (code, len) = self.map[self.eof.expect("tree has no end-of-stream value")];
+        let mut bits = BitVec::new();
+        bits.push_bits(code, len as usize);
+        bits
+    }
+
+    /// Returns the concatenated codes of `values`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let encoder = HuffmanTree::new(&[1, 2, 4, 8]).canonical().encoder();
+    /// assert_eq!(encoder.encode_all(&[3, 1, 2]).to_string(), "011110");
+    /// ```
+    pub fn encode_all(&self, values: &[T]) -> BitVec {
+        let len = values
+            .iter()
+            .map(|&value| self.map[value.to_index()].1 as usize)
+            .sum();
+        let mut out = BitVec::with_capacity(len);
+        for &value in values.iter() {
+            self.encode_into(value, &mut out);
+        }
+        out
+    }
+
+    /// Writes the codes of `values` to `sink`.
+    pub fn encode_all_to<S: BitSink>(&self, values: &[T], sink: &mut S) -> io::Result<()> {
+        for &value in values.iter() {
+            self.encode_to(value, sink)?;
+        }
+        Ok(())
     }
 }
 
@@ -132,29 +1038,359 @@ impl HuffmanEncoder {
 enum DecoderNode {
     Jump(usize),
     Value(usize),
+    /// The end of a bit sequence that is not a code.
+    Invalid,
 }
 
 #[derive(Clone, Debug)]
-pub struct HuffmanDecoder {
+pub struct HuffmanDecoder<T: Symbol = usize> {
     map: Vec<DecoderNode>,
+    eof: Option<usize>,
+    symbol: PhantomData<T>,
 }
 
-impl HuffmanDecoder {
-    pub fn decode<I>(&self, input: &mut I) -> usize
-    where
-        I: Iterator<Item = bool>,
-    {
+impl<T: Symbol> HuffmanDecoder<T> {
+    /// Decodes a value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if decoding fails; see `try_decode` for the fallible version.
+    pub fn decode<S: BitSource>(&self, input: &mut S) -> T {
+        self.try_decode(input).unwrap()
+    }
+
+    /// Decodes a value, or fails if the input ends before a whole code is
+    /// read or holds a bit sequence that is not a code. The code of a value
+    /// that `T` cannot represent, such as the end-of-stream value of a tree
+    /// of bytes, is invalid too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::bit_vec::BitVec;
+    /// # use rust_compress::huffman::{DecodeError, HuffmanTree};
+    /// let decoder = HuffmanTree::new(&[1, 2, 4, 8]).canonical().decoder();
+    /// let bits: BitVec = "11".parse().unwrap();
+    /// let err = decoder.try_decode(&mut bits.iter()).unwrap_err();
+    /// assert!(matches!(err, DecodeError::UnexpectedEnd));
+    /// ```
+    pub fn try_decode<S: BitSource>(&self, input: &mut S) -> Result<T, DecodeError> {
+        to_symbol(self.decode_index(input)?)
+    }
+
+    /// Decodes exactly `n` values, which must make up the whole input.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the input ends before the
+    /// `n`-th value, and with `ErrorKind::InvalidData` if bits are left
+    /// after it. Inputs padded to whole bytes, as written by a `BitWriter`,
+    /// should be decoded value by value instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::new(&[1, 2, 4, 8]);
+    /// let (encoder, decoder) = (tree.encoder(), tree.decoder());
+    /// let bits = encoder.encode_all(&[3, 1, 2]);
+    /// assert_eq!(decoder.decode_all(&mut bits.iter(), 3).unwrap(), vec![3, 1, 2]);
+    /// let err = decoder.decode_all(&mut bits.iter(), 4).unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    /// let err = decoder.decode_all(&mut bits.iter(), 2).unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    /// ```
+    pub fn decode_all<S: BitSource>(&self, input: &mut S, n: usize) -> io::Result<Vec<T>> {
+        let mut values = Vec::with_capacity(n);
+        for _ in 0..n {
+            values.push(to_symbol(self.read(input)?)?);
+        }
+        match input.read_bit() {
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing data after the last Huffman code",
+            )),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(values),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Decodes values up to the end-of-stream value, which is consumed but
+    /// not returned.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the input ends first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has no end-of-stream value.
+    pub fn decode_until_eof<S: BitSource>(&self, input: &mut S) -> io::Result<Vec<T>> {
+        let eof = self.eof.expect("tree has no end-of-stream value");
+        let mut values = Vec::new();
+        loop {
+            match self.read(input)? {
+                value if value == eof => return Ok(values),
+                value => values.push(to_symbol(value)?),
+            }
+        }
+    }
+
+    /// Returns an iterator of the values decoded from `input`, read lazily
+    /// as values are taken, that ends when the input does at the end of a
+    /// code.
+    ///
+    /// If the input ends in the middle of a code, or holds a bit sequence
+    /// that is not a code, the iterator yields the error and ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::{DecodeError, HuffmanTree};
+    /// let tree = HuffmanTree::new(&[1, 2, 4, 8]);
+    /// let (encoder, decoder) = (tree.encoder(), tree.decoder());
+    /// let bits = encoder.encode_all(&[3, 1, 2, 0]);
+    /// let values: Result<Vec<usize>, _> = decoder.decode_iter(bits.iter()).collect();
+    /// assert_eq!(values.unwrap(), vec![3, 1, 2, 0]);
+    /// // the last code is cut short
+    /// let input = bits.iter().take(bits.len() - 1);
+    /// let last = decoder.decode_iter(input).last();
+    /// assert!(matches!(last, Some(Err(DecodeError::UnexpectedEnd))));
+    /// ```
+    pub fn decode_iter<I: Iterator<Item = bool>>(&self, input: I) -> DecodeIter<'_, I, T> {
+        DecodeIter {
+            decoder: self,
+            input,
+            done: false,
+        }
+    }
+
+    /// Decodes the index of a value.
+    fn decode_index<S: BitSource>(&self, input: &mut S) -> Result<usize, DecodeError> {
         let mut idx = 0;
-        while let DecoderNode::Jump(right) = self.map[idx] {
-            if input.next().unwrap() {
-                idx += 1;
-            } else {
-                idx = right;
+        loop {
+            match self.map[idx] {
+                DecoderNode::Jump(right) => {
+                    if input.read_bit()? {
+                        idx += 1;
+                    } else {
+                        idx = right;
+                    }
+                }
+                DecoderNode::Value(value) => return Ok(value),
+                DecoderNode::Invalid => return Err(DecodeError::InvalidCode),
             }
         }
-        match self.map[idx] {
-            DecoderNode::Value(value) => value,
+    }
+
+    fn read<S: BitSource>(&self, input: &mut S) -> io::Result<usize> {
+        Ok(self.decode_index(input)?)
+    }
+}
+
+/// Returns the value of a decoded index, which is invalid if `T` cannot
+/// represent it.
+fn to_symbol<T: Symbol>(index: usize) -> Result<T, DecodeError> {
+    T::from_index(index).ok_or(DecodeError::InvalidCode)
+}
+
+/// An iterator of the values decoded from an iterator of bits, returned by
+/// `HuffmanDecoder::decode_iter`.
+#[derive(Clone, Debug)]
+pub struct DecodeIter<'a, I, T: Symbol = usize> {
+    decoder: &'a HuffmanDecoder<T>,
+    input: I,
+    /// Whether the input has ended or failed.
+    done: bool,
+}
+
+impl<'a, I: Iterator<Item = bool>, T: Symbol> Iterator for DecodeIter<'a, I, T> {
+    type Item = Result<T, DecodeError>;
+
+    fn next(&mut self) -> Option<Result<T, DecodeError>> {
+        if self.done {
+            return None;
+        }
+        let mut idx = 0;
+        loop {
+            match self.decoder.map[idx] {
+                DecoderNode::Jump(right) => match self.input.next() {
+                    Some(true) => idx += 1,
+                    Some(false) => idx = right,
+                    None => {
+                        self.done = true;
+                        return if idx == 0 {
+                            None
+                        } else {
+                            Some(Err(DecodeError::UnexpectedEnd))
+                        };
+                    }
+                },
+                DecoderNode::Value(value) => {
+                    let symbol = to_symbol(value);
+                    self.done = symbol.is_err();
+                    return Some(symbol);
+                }
+                DecoderNode::Invalid => {
+                    self.done = true;
+                    return Some(Err(DecodeError::InvalidCode));
+                }
+            }
+        }
+    }
+}
+
+/// A decoder fed with the input in chunks of any size, as it arrives, that
+/// keeps the state of a partly read code between chunks.
+///
+/// Bytes are unpacked in the order `O`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::huffman::{HuffmanTree, StreamDecoder};
+/// let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+/// let (encoder, decoder) = (tree.encoder(), tree.decoder());
+/// let bytes = encoder.encode_all(&[1, 4, 0, 5, 3, 3, 2, 1]).into_bytes();
+/// let mut stream = StreamDecoder::new(&decoder);
+/// let mut values = Vec::new();
+/// for chunk in bytes.chunks(2) {
+///     stream.feed(chunk, &mut values).unwrap();
+/// }
+/// assert_eq!(&values[..8], &[1, 4, 0, 5, 3, 3, 2, 1]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamDecoder<'a, O: BitOrder = LsbFirst, T: Symbol = usize> {
+    decoder: &'a HuffmanDecoder<T>,
+    /// The node reached by the bits of the current code so far.
+    idx: usize,
+    order: PhantomData<O>,
+}
+
+impl<'a, T: Symbol> StreamDecoder<'a, LsbFirst, T> {
+    /// Creates a streaming decoder of LSB-first bytes.
+    pub fn new(decoder: &'a HuffmanDecoder<T>) -> StreamDecoder<'a, LsbFirst, T> {
+        StreamDecoder::with_order(decoder)
+    }
+}
+
+impl<'a, O: BitOrder, T: Symbol> StreamDecoder<'a, O, T> {
+    /// Creates a streaming decoder of bytes packed in the order `O`.
+    pub fn with_order(decoder: &'a HuffmanDecoder<T>) -> StreamDecoder<'a, O, T> {
+        StreamDecoder {
+            decoder,
+            idx: 0,
+            order: PhantomData,
+        }
+    }
+
+    /// Returns `true` if no code is partly read.
+    pub fn is_at_boundary(&self) -> bool {
+        self.idx == 0
+    }
+
+    /// Decodes the bits of `bytes`, appending the completed values to `out`
+    /// and returning their number.
+    ///
+    /// On an invalid code, the values decoded before it are kept in `out`,
+    /// the rest of `bytes` is dropped and the decoder starts afresh with
+    /// the next chunk.
+    pub fn feed(&mut self, bytes: &[u8], out: &mut Vec<T>) -> Result<usize, DecodeError> {
+        let start = out.len();
+        for &byte in bytes.iter() {
+            let byte = O::convert_byte(byte);
+            for i in 0..8 {
+                self.step((byte >> i) & 1 == 1, out)?;
+            }
+        }
+        Ok(out.len() - start)
+    }
+
+    /// Decodes a single bit, returning the value it completes, if any.
+    pub fn feed_bit(&mut self, bit: bool) -> Result<Option<T>, DecodeError> {
+        let mut out = Vec::new();
+        self.step(bit, &mut out)?;
+        Ok(out.pop())
+    }
+
+    /// Checks that the input ended on a code boundary.
+    ///
+    /// The padding of the last byte may hold the start of a code, so
+    /// streams of whole bytes are better ended by an end-of-stream value
+    /// or a known number of values.
+    pub fn finish(self) -> Result<(), DecodeError> {
+        if self.is_at_boundary() {
+            Ok(())
+        } else {
+            Err(DecodeError::UnexpectedEnd)
+        }
+    }
+
+    fn step(&mut self, bit: bool, out: &mut Vec<T>) -> Result<(), DecodeError> {
+        let map = &self.decoder.map;
+        self.idx = match map[self.idx] {
+            DecoderNode::Jump(_) if bit => self.idx + 1,
+            DecoderNode::Jump(right) => right,
             _ => unreachable!(),
+        };
+        match map[self.idx] {
+            DecoderNode::Jump(_) => {}
+            DecoderNode::Value(value) => {
+                self.idx = 0;
+                out.push(to_symbol(value)?);
+            }
+            DecoderNode::Invalid => {
+                self.idx = 0;
+                return Err(DecodeError::InvalidCode);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error returned when decoding a Huffman code fails.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input ends in the middle of a code, or before it.
+    UnexpectedEnd,
+    /// The input holds a bit sequence that is not a code of the tree.
+    InvalidCode,
+    /// Reading the input failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "Huffman code ends unexpectedly"),
+            DecodeError::InvalidCode => write!(f, "invalid Huffman code"),
+            DecodeError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DecodeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(e: io::Error) -> DecodeError {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            DecodeError::UnexpectedEnd
+        } else {
+            DecodeError::Io(e)
+        }
+    }
+}
+
+impl From<DecodeError> for io::Error {
+    fn from(e: DecodeError) -> io::Error {
+        match e {
+            DecodeError::UnexpectedEnd => io::ErrorKind::UnexpectedEof.into(),
+            DecodeError::InvalidCode => io::Error::new(io::ErrorKind::InvalidData, e),
+            DecodeError::Io(e) => e,
         }
     }
 }
@@ -162,6 +1398,7 @@ impl HuffmanDecoder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bit_io::{BitReader, BitWriter};
 
     #[test]
     fn encode_uniform() {
@@ -195,4 +1432,658 @@ mod tests {
         assert_eq!(decoder.decode(&mut encoder.encode(4).iter()), 4);
         assert_eq!(decoder.decode(&mut encoder.encode(5).iter()), 5);
     }
+
+    #[test]
+    fn canonical() {
+        let counts = [10, 100, 20, 50, 60, 10, 0, 3, 3, 200];
+        let tree = HuffmanTree::new(&counts);
+        let canonical = tree.canonical();
+        let encoder = tree.encoder();
+        let canonical_encoder = canonical.encoder();
+        let mut codes: Vec<_> = (0..counts.len())
+            .map(|value| {
+                let code = canonical_encoder.encode(value);
+                assert_eq!(code.len(), encoder.encode(value).len());
+                (
+                    code.len(),
+                    value,
+                    code.iter().fold(0, |c, bit| c * 2 + bit as u64),
+                )
+            })
+            .collect();
+        codes.sort();
+        assert_eq!(codes[0].2, 0);
+        for pair in codes.windows(2) {
+            let (len, _, code) = pair[0];
+            let (next_len, _, next_code) = pair[1];
+            assert_eq!(next_code, (code + 1) << (next_len - len));
+        }
+        let (last_len, _, last_code) = codes[codes.len() - 1];
+        assert_eq!(last_code, (1 << last_len) - 1);
+        let decoder = canonical.decoder();
+        for value in 0..counts.len() {
+            let code = canonical_encoder.encode(value);
+            assert_eq!(decoder.decode(&mut code.iter()), value);
+        }
+    }
+
+    #[test]
+    fn lengths_round_trip() {
+        let counts: Vec<u128> = (0..300u64)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 54) as u128)
+            .collect();
+        let tree = HuffmanTree::new(&counts).canonical();
+        let lengths = tree.to_lengths();
+        let rebuilt = HuffmanTree::from_lengths(&lengths).unwrap();
+        assert_eq!(rebuilt.to_lengths(), lengths);
+        let (encoder, rebuilt_encoder) = (tree.encoder(), rebuilt.encoder());
+        assert_eq!(encoder.codes(), rebuilt_encoder.codes());
+        let max_len = *lengths.iter().max().unwrap();
+        assert_eq!(tree.max_len(), max_len);
+        assert_eq!(encoder.max_len(), max_len);
+        assert!(encoder
+            .codes()
+            .iter()
+            .map(|&(_, len)| len as usize)
+            .eq(lengths));
+        for value in 0..counts.len() {
+            assert_eq!(encoder.encode(value), rebuilt_encoder.encode(value));
+        }
+    }
+
+    #[test]
+    fn long_codes() {
+        let mut lengths: Vec<usize> = (1..=64).collect();
+        lengths.push(64);
+        let tree = HuffmanTree::from_lengths(&lengths).unwrap();
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        assert_eq!(encoder.codes()[63], (!0 >> 1, 64));
+        assert_eq!(encoder.codes()[64], (!0, 64));
+        let values = [64, 0, 63, 5];
+        let bits = encoder.encode_all(&values);
+        assert_eq!(bits.len(), 64 + 1 + 64 + 6);
+        assert_eq!(decoder.decode_all(&mut bits.iter(), 4).unwrap(), values);
+    }
+
+    #[test]
+    #[should_panic(expected = "codes longer than 64 bits")]
+    fn too_long_codes() {
+        let mut lengths: Vec<usize> = (1..=65).collect();
+        lengths.push(65);
+        HuffmanTree::from_lengths(&lengths).unwrap().encoder();
+    }
+
+    #[test]
+    fn deep_tree() {
+        // one more level for every value, far beyond the codes an encoder
+        // can return
+        let counts: Vec<u128> = (0..=120).map(|i| 1 << i).collect();
+        let tree = HuffmanTree::new(&counts).canonical();
+        let lengths = tree.to_lengths();
+        assert_eq!(lengths[0], 120);
+        assert_eq!(lengths[1], 120);
+        assert_eq!(tree.max_len(), 120);
+        let decoder = tree.decoder();
+        for (value, &len) in lengths.iter().enumerate() {
+            // the canonical codes are runs of ones ended by a zero, but for
+            // the last one
+            let mut code = "1".repeat(len - 1);
+            code.push(if value == 1 { '1' } else { '0' });
+            let bits: BitVec = code.parse().unwrap();
+            assert_eq!(decoder.decode(&mut bits.iter()), value);
+        }
+    }
+
+    #[test]
+    fn invalid_lengths() {
+        use CodeLengthsError::*;
+        assert_eq!(HuffmanTree::from_lengths(&[0, 2]).unwrap_err(), Incomplete);
+        assert_eq!(
+            HuffmanTree::from_lengths(&[1, 1, 2]).unwrap_err(),
+            Oversubscribed
+        );
+        assert_eq!(
+            HuffmanTree::from_lengths(&[1, 1, 1]).unwrap_err(),
+            Oversubscribed
+        );
+        assert_eq!(
+            HuffmanTree::from_lengths(&[2, 2, 2]).unwrap_err(),
+            Incomplete
+        );
+        assert_eq!(
+            HuffmanTree::from_lengths(&[1, 1 << 60]).unwrap_err(),
+            Incomplete
+        );
+        assert_eq!(
+            HuffmanTree::from_lengths(&[1, 2, 2, 1 << 60]).unwrap_err(),
+            Oversubscribed
+        );
+        let tree = HuffmanTree::from_lengths(&[2, 0, 1, 2, 0]).unwrap();
+        let encoder = tree.encoder();
+        assert_eq!(encoder.encode(2).to_string(), "0");
+        assert_eq!(encoder.encode(0).to_string(), "10");
+        assert_eq!(encoder.encode(3).to_string(), "11");
+        assert!(encoder.encode(1).is_empty());
+        let decoder = tree.decoder();
+        assert_eq!(decoder.decode(&mut encoder.encode(3).iter()), 3);
+    }
+
+    #[test]
+    fn stats() {
+        let counts = [10, 100, 20, 50, 60, 10];
+        let tree = HuffmanTree::new(&counts);
+        let stats = tree.stats().unwrap();
+        let total: u128 = counts.iter().sum();
+        let bits: usize = counts
+            .iter()
+            .zip(tree.to_lengths())
+            .map(|(&count, len)| count as usize * len)
+            .sum();
+        assert!((stats.average_len() - bits as f64 / total as f64).abs() < 1e-12);
+        assert!((stats.entropy() - 2.150_308_332_669).abs() < 1e-9);
+        assert!(stats.redundancy() >= 0.0 && stats.redundancy() < 1.0);
+        let stats = HuffmanTree::new(&[7, 0]).stats().unwrap();
+        assert_eq!((stats.entropy(), stats.average_len()), (0.0, 1.0));
+        assert!(HuffmanTree::new(&[0, 0]).stats().is_none());
+        assert!(HuffmanTree::from_lengths(&[1, 1])
+            .unwrap()
+            .stats()
+            .is_none());
+    }
+
+    #[test]
+    fn ties() {
+        let codes = |counts: &[u128]| -> Vec<String> {
+            let encoder = HuffmanTree::new(counts).encoder();
+            (0..counts.len())
+                .map(|value| encoder.encode(value).to_string())
+                .collect()
+        };
+        assert_eq!(codes(&[1, 1, 1, 1]), ["11", "10", "01", "00"]);
+        assert_eq!(codes(&[2, 1, 1]), ["1", "01", "00"]);
+        // a merged node goes after the values of the same count
+        assert_eq!(codes(&[1, 1, 2, 2]), ["11", "10", "01", "00"]);
+    }
+
+    #[test]
+    fn two_queues() {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+
+        // the construction with a heap, of the same tie-break
+        fn with_heap(counts: &[u128]) -> HuffmanTree {
+            let mut arena = Arena::with_capacity(2 * counts.len() - 1);
+            let mut heap = BinaryHeap::new();
+            for (value, &count) in counts.iter().enumerate() {
+                heap.push(Reverse((count, arena.leaf(value, count))));
+            }
+            while heap.len() > 1 {
+                let Reverse((left_count, left)) = heap.pop().unwrap();
+                let Reverse((right_count, right)) = heap.pop().unwrap();
+                let node = arena.join(Some(left), Some(right));
+                heap.push(Reverse((left_count + right_count, node)));
+            }
+            HuffmanTree {
+                elements: counts.len(),
+                eof: None,
+                nodes: arena,
+                symbol: PhantomData,
+            }
+        }
+
+        for seed in 0..200u64 {
+            let n = 2 + (seed % 40) as usize;
+            let mut counts: Vec<u128> = (0..n as u64)
+                .map(|i| {
+                    let r = (seed * 131 + i).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 40;
+                    (if seed % 3 == 0 { r % 5 } else { r % 10_000 }) as u128
+                })
+                .collect();
+            if seed % 4 == 1 {
+                counts.sort_unstable();
+            }
+            let (tree, expected) = (HuffmanTree::new(&counts), with_heap(&counts));
+            assert_eq!(
+                tree.encoder().codes(),
+                expected.encoder().codes(),
+                "{:?}",
+                counts
+            );
+        }
+    }
+
+    #[test]
+    fn count_types() {
+        let counts: Vec<u128> = (0..300u64)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58) as u128)
+            .collect();
+        let tree = HuffmanTree::new(&counts);
+        let narrow: Vec<u16> = counts.iter().map(|&c| c as u16).collect();
+        let wide: Vec<u64> = counts.iter().map(|&c| c as u64).collect();
+        let trees = (
+            HuffmanTree::from_counts(&narrow),
+            HuffmanTree::from_counts(&wide),
+        );
+        assert_eq!(trees.0.to_lengths(), tree.to_lengths());
+        assert_eq!(trees.1.to_lengths(), tree.to_lengths());
+        let canonical = trees.0.canonical().with_symbol::<u16>();
+        assert_eq!(canonical.stats(), tree.stats());
+        assert_eq!(
+            canonical.encoder().encode(7),
+            tree.canonical().encoder().encode(7)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows the count type")]
+    fn count_overflow() {
+        HuffmanTree::from_counts(&[200u8, 100, 1]);
+    }
+
+    #[test]
+    fn min_variance() {
+        // all the complete codes of n values, as vectors of lengths
+        fn complete(n: usize, lengths: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+            if lengths.len() == n {
+                if HuffmanTree::from_lengths(lengths).is_ok() {
+                    out.push(lengths.clone());
+                }
+                return;
+            }
+            for len in 1..n {
+                lengths.push(len);
+                complete(n, lengths, out);
+                lengths.pop();
+            }
+        }
+
+        for n in 2..=6 {
+            let mut codes = Vec::new();
+            complete(n, &mut Vec::new(), &mut codes);
+            for seed in 0..50u64 {
+                let counts: Vec<u128> = (0..n as u64)
+                    .map(|i| ((seed * 31 + i).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62) as u128)
+                    .collect();
+                let moment = |lengths: &[usize], power: u32| -> u128 {
+                    counts
+                        .iter()
+                        .zip(lengths)
+                        .map(|(&c, &l)| c * (l as u128).pow(power))
+                        .sum()
+                };
+                let lengths = HuffmanTree::new(&counts).to_lengths();
+                let cost = codes.iter().map(|l| moment(l, 1)).min().unwrap();
+                assert_eq!(moment(&lengths, 1), cost, "{:?}", counts);
+                // of equal average length, the least variance is the least
+                // sum of squares
+                let optimal = codes.iter().filter(|l| moment(l, 1) == cost);
+                let squares = optimal.map(|l| moment(l, 2)).min().unwrap();
+                assert_eq!(moment(&lengths, 2), squares, "{:?}", counts);
+            }
+        }
+    }
+
+    #[test]
+    fn to_dot() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let encoder = tree.encoder();
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph huffman {\n") && dot.ends_with("}\n"));
+        assert_eq!(dot.matches(" -> ").count(), 10);
+        let mut leaves = 0;
+        for line in dot.lines().filter(|line| line.contains("shape=box")) {
+            let label = line.split('"').nth(1).unwrap();
+            let fields: Vec<&str> = label.split("\\n").collect();
+            let value: usize = fields[0].parse().unwrap();
+            assert_eq!(fields[2], encoder.encode(value).to_string());
+            leaves += 1;
+        }
+        assert_eq!(leaves, 6);
+        assert!(HuffmanTree::new(&[]).to_dot().contains("n0 [label=\"0\"];"));
+    }
+
+    #[test]
+    fn degenerate() {
+        let one: BitVec = "1".parse().unwrap();
+        let trees = [
+            HuffmanTree::new(&[3]),
+            HuffmanTree::new_limited(&[3], 1),
+            HuffmanTree::from_lengths(&[0, 1, 0]).unwrap(),
+        ];
+        for tree in trees.iter() {
+            let value = tree.to_lengths().iter().position(|&l| l == 1).unwrap();
+            let (encoder, decoder) = (tree.encoder(), tree.decoder());
+            let code = encoder.encode(value);
+            assert_eq!(code.to_string(), "0");
+            assert_eq!(decoder.decode(&mut code.iter()), value);
+            assert_eq!(
+                tree.table_decoder(4).decode(&mut code.iter()).unwrap(),
+                value
+            );
+            let err = decoder.try_decode(&mut one.iter()).unwrap_err();
+            assert!(matches!(err, DecodeError::InvalidCode));
+            assert_eq!(tree.canonical().to_lengths(), tree.to_lengths());
+        }
+
+        let trees = [
+            HuffmanTree::new(&[]),
+            HuffmanTree::new_limited(&[], 0),
+            HuffmanTree::from_lengths(&[0, 0]).unwrap(),
+        ];
+        for tree in trees.iter() {
+            assert!(tree.to_lengths().iter().all(|&l| l == 0));
+            let decoder = tree.decoder();
+            let err = decoder.try_decode(&mut BitVec::new().iter()).unwrap_err();
+            assert!(matches!(err, DecodeError::UnexpectedEnd));
+            let err = decoder.try_decode(&mut one.iter()).unwrap_err();
+            assert!(matches!(err, DecodeError::InvalidCode));
+            let err = tree.table_decoder(4).decode(&mut one.iter()).unwrap_err();
+            assert!(matches!(err, DecodeError::InvalidCode));
+        }
+
+        let tree = HuffmanTree::with_eof(&[]);
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let values = decoder.decode_until_eof(&mut encoder.encode_eof().iter());
+        assert!(values.unwrap().is_empty());
+
+        let tree = HuffmanTree::new(&[0, 0, 0]);
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let bits = encoder.encode_all(&[2, 0, 1]);
+        assert_eq!(
+            decoder.decode_all(&mut bits.iter(), 3).unwrap(),
+            vec![2, 0, 1]
+        );
+    }
+
+    #[test]
+    fn limited() {
+        let cost = |counts: &[u128], lengths: &[usize]| -> u128 {
+            counts
+                .iter()
+                .zip(lengths)
+                .map(|(&c, &l)| c * l as u128)
+                .sum()
+        };
+        let sets: [&[u128]; 4] = [
+            &[1, 1, 2, 3, 5, 8],
+            &[10, 100, 20, 50, 60, 10],
+            &[0, 0, 0, 7, 1, 1],
+            &[5, 5, 5, 5, 5, 1],
+        ];
+        for counts in sets.iter() {
+            for max_len in 3..6 {
+                let lengths = HuffmanTree::new_limited(counts, max_len).to_lengths();
+                assert!(lengths.iter().all(|&l| l <= max_len));
+                assert!(HuffmanTree::from_lengths(&lengths).is_ok());
+                // compare against all complete codes within the limit
+                let mut best = u128::MAX;
+                for mut k in 0..max_len.pow(6) {
+                    let mut candidate = [0; 6];
+                    for l in candidate.iter_mut() {
+                        *l = k % max_len + 1;
+                        k /= max_len;
+                    }
+                    if HuffmanTree::from_lengths(&candidate).is_ok() {
+                        best = best.min(cost(counts, &candidate));
+                    }
+                }
+                assert_eq!(cost(counts, &lengths), best);
+            }
+            let unlimited = HuffmanTree::new(counts).to_lengths();
+            let lengths = HuffmanTree::new_limited(counts, 64).to_lengths();
+            assert_eq!(cost(counts, &lengths), cost(counts, &unlimited));
+        }
+        let lengths = HuffmanTree::new_limited(&[1; 8], 3).to_lengths();
+        assert_eq!(lengths, vec![3; 8]);
+    }
+
+    #[test]
+    fn from_data() {
+        let data = b"mississippi river";
+        let mut counts = vec![0; 256];
+        for &byte in data.iter() {
+            counts[byte as usize] += 1;
+        }
+        let lengths = HuffmanTree::new(&counts).to_lengths();
+        assert_eq!(HuffmanTree::from_data(data).to_lengths(), lengths);
+        let symbols = data.iter().map(|&byte| byte as usize);
+        let lengths = HuffmanTree::new(&counts[..=b'v' as usize]).to_lengths();
+        assert_eq!(HuffmanTree::from_symbols(symbols).to_lengths(), lengths);
+        assert_eq!(
+            HuffmanTree::from_symbols(None::<usize>).to_lengths(),
+            vec![1, 1]
+        );
+    }
+
+    #[test]
+    fn symbols() {
+        let text = "ʻohana means family";
+        let tree = HuffmanTree::from_symbols(text.chars());
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let values: Vec<char> = text.chars().collect();
+        let bits = encoder.encode_all(&values);
+        assert_eq!(
+            decoder.decode_all(&mut bits.iter(), values.len()).unwrap(),
+            values
+        );
+        let bytes = bits.into_bytes();
+        let mut stream = StreamDecoder::new(&decoder);
+        let mut out = Vec::new();
+        stream.feed(&bytes, &mut out).unwrap();
+        assert_eq!(&out[..values.len()], &values[..]);
+        let mut reader = crate::bit_io::SliceBitReader::new(&bytes);
+        let table = tree.table_decoder(6);
+        for &value in values.iter() {
+            assert_eq!(table.decode(&mut reader).unwrap(), value);
+        }
+
+        // the end-of-stream value of a tree of bytes is not a byte
+        let tree = HuffmanTree::with_eof(&[1; 256]).with_symbol::<u8>();
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let mut bits = encoder.encode_all(b"bytes");
+        bits.extend_from_bitvec(&encoder.encode_eof());
+        assert_eq!(
+            decoder.decode_until_eof(&mut bits.iter()).unwrap(),
+            b"bytes"
+        );
+        let eof = encoder.encode_eof();
+        let err = decoder.try_decode(&mut eof.iter()).unwrap_err();
+        assert!(matches!(err, DecodeError::InvalidCode));
+    }
+
+    #[test]
+    fn encode_all() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let encoder = tree.encoder();
+        let values = [1, 4, 0, 5, 3, 3, 2, 1];
+        let mut expected = BitVec::new();
+        for &value in values.iter() {
+            expected.extend(encoder.encode(value).iter());
+        }
+        assert_eq!(encoder.encode_all(&values), expected);
+        let mut out = BitVec::new();
+        out.push(true);
+        for &value in values.iter() {
+            encoder.encode_into(value, &mut out);
+        }
+        assert!(out.iter().eq(Some(true).into_iter().chain(expected.iter())));
+        let mut writer = BitWriter::new(Vec::new());
+        encoder.encode_all_to(&values, &mut writer).unwrap();
+        assert_eq!(writer.finish().unwrap(), expected.as_bytes());
+        assert!(encoder.encode_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn decode_all() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let values = [1, 4, 0, 5, 3, 3, 2, 1];
+        let mut bits = encoder.encode_all(&values);
+        assert_eq!(decoder.decode_all(&mut bits.iter(), 8).unwrap(), values);
+        assert!(decoder.decode_all(&mut bits.iter(), 0).is_err());
+        assert!(decoder.decode_all(&mut BitVec::new().iter(), 0).is_ok());
+        // a code cut short
+        bits.pop();
+        let err = decoder.decode_all(&mut bits.iter(), 8).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn eof() {
+        let tree = HuffmanTree::with_eof(&[10, 100, 20, 50, 60, 10]);
+        assert_eq!(tree.eof(), Some(6));
+        let values = [1, 4, 0, 5, 3, 3, 2, 1];
+        let encoder = tree.encoder();
+        let mut writer = BitWriter::new(Vec::new());
+        encoder.encode_all_to(&values, &mut writer).unwrap();
+        for bit in encoder.encode_eof() {
+            writer.write_bit(bit).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let mut rebuilt = HuffmanTree::from_lengths(&tree.canonical().to_lengths()).unwrap();
+        assert_eq!(rebuilt.eof(), None);
+        rebuilt.set_eof(6);
+        let bytes_canonical = {
+            let encoder = rebuilt.encoder();
+            let mut bits = encoder.encode_all(&values);
+            bits.extend_from_bitvec(&encoder.encode_eof());
+            bits.into_bytes()
+        };
+        for (tree, bytes) in [(tree, bytes), (rebuilt, bytes_canonical)].iter() {
+            let decoder = tree.decoder();
+            let mut reader = BitReader::new(&bytes[..]);
+            assert_eq!(decoder.decode_until_eof(&mut reader).unwrap(), values);
+            let err = decoder
+                .decode_until_eof(&mut BitReader::new(&bytes[..1]))
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn try_decode() {
+        struct Failing;
+
+        impl BitSource for Failing {
+            fn read_bit(&mut self) -> io::Result<bool> {
+                Err(io::Error::other("broken"))
+            }
+
+            fn read_bits(&mut self, _: usize) -> io::Result<u64> {
+                Err(io::Error::other("broken"))
+            }
+        }
+
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let mut bits = encoder.encode(5).clone();
+        assert_eq!(decoder.try_decode(&mut bits.iter()).unwrap(), 5);
+        bits.pop();
+        let err = decoder.try_decode(&mut bits.iter()).unwrap_err();
+        assert!(matches!(err, DecodeError::UnexpectedEnd));
+        let err = decoder.try_decode(&mut Failing).unwrap_err();
+        assert!(matches!(err, DecodeError::Io(_)));
+        // a tree with a missing branch, as left by a partial code
+        let mut tree = tree;
+        let root = tree.nodes.root();
+        tree.nodes.nodes[root].left = None;
+        let decoder = tree.decoder();
+        let mut invalid = 0;
+        for value in 0..6 {
+            let code = encoder.encode(value);
+            match decoder.try_decode(&mut code.iter()) {
+                Ok(decoded) => assert_eq!(decoded, value),
+                Err(DecodeError::InvalidCode) => invalid += 1,
+                Err(e) => panic!("unexpected error {}", e),
+            }
+        }
+        assert!(invalid > 0 && invalid < 6);
+    }
+
+    #[test]
+    fn decode_iter() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let values: Vec<usize> = (0..500).map(|i| (i * i + i / 3) % 6).collect();
+        let bits = encoder.encode_all(&values);
+        let decoded: Vec<usize> = decoder
+            .decode_iter(bits.iter())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(decoded, values);
+        assert_eq!(decoder.decode_iter(None.into_iter()).count(), 0);
+        // lazily, leaving the rest of the input unread
+        let mut input = bits.iter();
+        let first: Vec<usize> = decoder
+            .decode_iter(&mut input)
+            .take(10)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(first, &values[..10]);
+        assert_eq!(decoder.decode(&mut input), values[10]);
+
+        let code = encoder.encode(0);
+        let mut iter = decoder.decode_iter(code.iter().take(code.len() - 1));
+        assert!(matches!(iter.next(), Some(Err(DecodeError::UnexpectedEnd))));
+        assert!(iter.next().is_none());
+        let tree = HuffmanTree::with_eof(&[1; 256]).with_symbol::<u8>();
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let mut bits = encoder.encode_all(b"ab");
+        bits.extend_from_bitvec(&encoder.encode_eof());
+        bits.extend_from_bitvec(&encoder.encode(b'a'));
+        let mut iter = decoder.decode_iter(bits.iter());
+        assert_eq!(iter.next().unwrap().unwrap(), b'a');
+        assert_eq!(iter.next().unwrap().unwrap(), b'b');
+        assert!(matches!(iter.next(), Some(Err(DecodeError::InvalidCode))));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn stream_decoder() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let (encoder, decoder) = (tree.encoder(), tree.decoder());
+        let values: Vec<usize> = (0..500).map(|i| (i * i + i / 3) % 6).collect();
+        let bits = encoder.encode_all(&values);
+        let bytes = bits.as_bytes();
+        for &chunk in [1, 3, 7, 1000].iter() {
+            let mut stream = StreamDecoder::new(&decoder);
+            let mut decoded = Vec::new();
+            for part in bytes.chunks(chunk) {
+                let n = stream.feed(part, &mut decoded).unwrap();
+                assert!(n <= 8 * part.len());
+            }
+            assert_eq!(&decoded[..values.len()], &values[..]);
+        }
+        let mut stream = StreamDecoder::new(&decoder);
+        let mut decoded = Vec::new();
+        for bit in bits.iter() {
+            decoded.extend(stream.feed_bit(bit).unwrap());
+        }
+        assert_eq!(decoded, values);
+        assert!(stream.finish().is_ok());
+        let mut stream = StreamDecoder::new(&decoder);
+        let longest = (0..6)
+            .map(|value| encoder.encode(value))
+            .max_by_key(|code| code.len());
+        let first = longest.unwrap().get(0).unwrap();
+        assert_eq!(stream.feed_bit(first).unwrap(), None);
+        assert!(matches!(stream.finish(), Err(DecodeError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn encode_decode_stream() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let encoder = tree.encoder();
+        let decoder = tree.decoder();
+        let values = [1, 4, 0, 5, 3, 3, 2, 1];
+        let mut writer = BitWriter::new(Vec::new());
+        for &value in values.iter() {
+            encoder.encode_to(value, &mut writer).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let mut reader = BitReader::new(&bytes[..]);
+        for &value in values.iter() {
+            assert_eq!(decoder.decode(&mut reader), value);
+        }
+    }
 }
@@ -0,0 +1,172 @@
+//! Construction of optimal alphabetic codes.
+
+use std::marker::PhantomData;
+
+use super::{Arena, HuffmanTree};
+
+impl HuffmanTree {
+    /// Builds an optimal alphabetic code of `counts`, one whose codes are
+    /// in the same order as the values when compared bit by bit, like
+    /// strings, so that encoded keys sort like the values.
+    ///
+    /// The code is as short on average as possible under that constraint,
+    /// the one found by the Hu–Tucker algorithm, which is computed here by
+    /// the simpler Garsia–Wachs algorithm. It is no shorter than the
+    /// Huffman code, and equally long when the counts allow it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let tree = HuffmanTree::alphabetic(&[1, 8, 2, 4]);
+    /// let encoder = tree.encoder();
+    /// let codes: Vec<String> = (0..4).map(|v| encoder.encode(v).to_string()).collect();
+    /// assert_eq!(codes, ["00", "01", "10", "11"]);
+    /// // the Huffman code is shorter, but cannot be alphabetic
+    /// assert_eq!(HuffmanTree::new(&[1, 8, 2, 4]).to_lengths(), vec![3, 1, 3, 2]);
+    /// ```
+    pub fn alphabetic(counts: &[u128]) -> HuffmanTree {
+        if counts.len() < 2 {
+            return HuffmanTree::new(counts);
+        }
+        // the leaves with the depths of an optimal tree are laid out from
+        // left to right, joining the last two nodes while they are
+        // siblings, that is, at the same depth
+        let mut arena = Arena::with_capacity(2 * counts.len() - 1);
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        for (value, depth) in garsia_wachs(counts).into_iter().enumerate() {
+            let mut node = arena.leaf(value, counts[value]);
+            let mut depth = depth;
+            while stack.last().is_some_and(|&(_, d)| d == depth) {
+                let (zero, _) = stack.pop().unwrap();
+                node = arena.join(Some(node), Some(zero));
+                depth -= 1;
+            }
+            stack.push((node, depth));
+        }
+        let (root, depth) = stack.pop().unwrap();
+        debug_assert!(stack.is_empty() && depth == 0 && root == arena.root());
+        HuffmanTree {
+            elements: counts.len(),
+            eof: None,
+            nodes: arena,
+            symbol: PhantomData,
+        }
+    }
+}
+
+/// Returns the depth of every value in an optimal alphabetic tree, which
+/// the Garsia–Wachs algorithm finds as that of another, easier to build
+/// tree in which the values may be out of order.
+///
+/// Every value weighs its count and then one leaf, compared in that order,
+/// so that of the trees of the least cost it finds one of the least total
+/// depth: values of a count of zero are spread evenly instead of sinking
+/// to any depth.
+fn garsia_wachs(counts: &[u128]) -> Vec<usize> {
+    let n = counts.len();
+    // the parent of every node, the values first and then the joined ones
+    let mut parent = vec![0; n];
+    let mut items: Vec<((u128, u128), usize)> =
+        counts.iter().map(|&count| (count, 1)).zip(0..).collect();
+    while items.len() > 1 {
+        // the first pair not heavier on the left than the item after it,
+        // the last one being lighter than the sentinel of infinite weight
+        let k = (1..items.len())
+            .find(|&k| k + 1 == items.len() || items[k - 1].0 <= items[k + 1].0)
+            .unwrap();
+        let ((left_count, left_leaves), _) = items[k - 1];
+        let ((right_count, right_leaves), _) = items[k];
+        let weight = (left_count + right_count, left_leaves + right_leaves);
+        let node = parent.len();
+        parent[items[k - 1].1] = node;
+        parent[items[k].1] = node;
+        parent.push(0);
+        items.drain(k - 1..=k);
+        // the joined node moves left, up to an item at least as heavy
+        let j = items[..k - 1]
+            .iter()
+            .rposition(|item| item.0 >= weight)
+            .map_or(0, |j| j + 1);
+        items.insert(j, (weight, node));
+    }
+    // parents come after their children, the root last
+    let mut depths = vec![0; parent.len()];
+    for node in (0..parent.len() - 1).rev() {
+        depths[node] = depths[parent[node]] + 1;
+    }
+    depths.truncate(n);
+    depths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the least cost of an alphabetic code of `counts`.
+    fn best_cost(counts: &[u128]) -> u128 {
+        let n = counts.len();
+        // cost[i][j] of the values i..=j, not counting their common prefix
+        let mut cost = vec![vec![0; n]; n];
+        for len in 2..=n {
+            for i in 0..=n - len {
+                let j = i + len - 1;
+                let weight: u128 = counts[i..=j].iter().sum();
+                cost[i][j] = (i..j).map(|k| cost[i][k] + cost[k + 1][j]).min().unwrap() + weight;
+            }
+        }
+        cost[0][n - 1]
+    }
+
+    #[test]
+    fn optimal_and_ordered() {
+        for seed in 0..300u64 {
+            let n = 2 + (seed % 9) as usize;
+            let counts: Vec<u128> = (0..n as u64)
+                .map(|i| {
+                    let r = (seed * 31 + i).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 40;
+                    // small counts make for many ties
+                    (if seed % 2 == 0 { r % 4 } else { r % 1000 }) as u128
+                })
+                .collect();
+            let tree = HuffmanTree::alphabetic(&counts);
+            let lengths = tree.to_lengths();
+            let cost: u128 = counts
+                .iter()
+                .zip(lengths.iter())
+                .map(|(&c, &l)| c * l as u128)
+                .sum();
+            assert_eq!(cost, best_cost(&counts), "{:?}", counts);
+            let encoder = tree.encoder();
+            let codes: Vec<String> = (0..n).map(|v| encoder.encode(v).to_string()).collect();
+            assert!(
+                codes.windows(2).all(|pair| pair[0] < pair[1]),
+                "{:?}",
+                codes
+            );
+            assert!(HuffmanTree::from_lengths(&lengths).is_ok());
+            let decoder = tree.decoder();
+            let values: Vec<usize> = (0..n).rev().collect();
+            let bits = encoder.encode_all(&values);
+            assert_eq!(decoder.decode_all(&mut bits.iter(), n).unwrap(), values);
+        }
+    }
+
+    #[test]
+    fn sparse() {
+        let mut counts = [0; 256];
+        for &byte in b"hello world, this is some text".iter() {
+            counts[byte as usize] += 1;
+        }
+        let tree = HuffmanTree::alphabetic(&counts);
+        assert!(tree.max_len() <= 20, "{}", tree.max_len());
+        let values: Vec<usize> = (0..256).collect();
+        let bits = tree.encoder().encode_all(&values);
+        assert_eq!(
+            tree.decoder()
+                .decode_all(&mut bits.iter(), values.len())
+                .unwrap(),
+            values
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! A self-contained Huffman compressor of byte strings.
+
+use std::io;
+
+use crate::bit_io::SliceBitReader;
+use crate::varint;
+
+use super::HuffmanTree;
+
+/// The longest code, so that every length fits in four bits.
+const MAX_LEN: usize = 15;
+
+/// The size of the table of code lengths, two lengths per byte.
+const LENGTHS_SIZE: usize = 256 / 2;
+
+/// Compresses `data` into a self-describing stream that `decompress` reads
+/// back.
+///
+/// The stream starts with the length of `data` as a varint, followed by
+/// the code length of every byte value in four bits, the low nibble first,
+/// and then by the canonical codes of the bytes of `data`, LSB first and
+/// padded to a whole byte. Bytes that do not occur get no code, and codes
+/// are at most 15 bits long.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::huffman::{compress, decompress};
+/// let data = b"abracadabra, abracadabra, abracadabra";
+/// let compressed = compress(data);
+/// assert_eq!(decompress(&compressed).unwrap(), &data[..]);
+/// ```
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_block(data, &mut out);
+    out
+}
+
+/// Decompresses a stream made by `compress`.
+///
+/// Fails with `ErrorKind::UnexpectedEof` if the stream is cut short, and
+/// with `ErrorKind::InvalidData` if it is malformed or has data left after
+/// the end.
+pub fn decompress(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let used = read_block(compressed, &mut data)?;
+    check_end(&compressed[used..])?;
+    Ok(data)
+}
+
+/// Compresses `data` in blocks of `block_size` bytes, each with a tree of
+/// its own, into a stream that `decompress_blocks` reads back.
+///
+/// A tree fitted to every block codes data whose statistics change along
+/// the way, such as archives of text and binaries, shorter than a single
+/// tree does. Every block costs a header of about 130 bytes, so blocks of
+/// tens of kilobytes are best for most data.
+///
+/// The stream starts with the length of `data` as a varint, followed by
+/// the blocks, each laid out as a stream of `compress`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::huffman::{compress, compress_blocks, decompress_blocks};
+/// let mut data = vec![b'a'; 4000];
+/// data.extend((0..4000).map(|i| (i % 7) as u8));
+/// let compressed = compress_blocks(&data, 4000);
+/// assert!(compressed.len() < compress(&data).len());
+/// assert_eq!(decompress_blocks(&compressed).unwrap(), data);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `block_size` is zero.
+pub fn compress_blocks(data: &[u8], block_size: usize) -> Vec<u8> {
+    assert!(block_size > 0, "blocks must not be empty");
+    let mut out = Vec::new();
+    varint::encode_u64(data.len() as u64, &mut out);
+    for block in data.chunks(block_size) {
+        write_block(block, &mut out);
+    }
+    out
+}
+
+/// Decompresses a stream made by `compress_blocks`.
+///
+/// Fails as `decompress` does.
+pub fn decompress_blocks(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let (len, mut pos) = varint::decode_u64(compressed)?;
+    // every byte takes at least one bit, which bounds an honest length
+    let mut data = Vec::with_capacity(len.min(8 * compressed.len() as u64) as usize);
+    while (data.len() as u64) < len {
+        pos += read_block(&compressed[pos..], &mut data)?;
+        if data.len() as u64 > len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "blocks longer than the compressed stream",
+            ));
+        }
+    }
+    check_end(&compressed[pos..])?;
+    Ok(data)
+}
+
+/// Appends the stream of `compress` of `data` to `out`.
+fn write_block(data: &[u8], out: &mut Vec<u8>) {
+    let mut counts = [0; 256];
+    for &byte in data.iter() {
+        counts[byte as usize] += 1;
+    }
+    // only the bytes that occur get a code
+    let used: Vec<usize> = (0..256).filter(|&byte| counts[byte] > 0).collect();
+    let used_counts: Vec<u128> = used.iter().map(|&byte| counts[byte]).collect();
+    let used_lengths = HuffmanTree::new_limited(&used_counts, MAX_LEN).to_lengths();
+    let mut lengths = [0; 256];
+    for (&byte, &len) in used.iter().zip(used_lengths.iter()) {
+        lengths[byte] = len;
+    }
+
+    varint::encode_u64(data.len() as u64, out);
+    out.extend(
+        lengths
+            .chunks(2)
+            .map(|pair| (pair[0] | (pair[1] << 4)) as u8),
+    );
+    let tree = HuffmanTree::from_lengths(&lengths)
+        .unwrap()
+        .with_symbol::<u8>();
+    out.extend(tree.encoder().encode_all(data).into_bytes());
+}
+
+/// Decodes a stream of `compress` at the start of `compressed`, appending
+/// the data to `data`, and returns the number of bytes it takes.
+fn read_block(compressed: &[u8], data: &mut Vec<u8>) -> io::Result<usize> {
+    let (len, header) = varint::decode_u64(compressed)?;
+    let table = compressed
+        .get(header..header + LENGTHS_SIZE)
+        .ok_or(io::ErrorKind::UnexpectedEof)?;
+    let mut lengths = Vec::with_capacity(256);
+    for &byte in table.iter() {
+        lengths.push((byte & 0xf) as usize);
+        lengths.push((byte >> 4) as usize);
+    }
+    let tree = HuffmanTree::from_lengths(&lengths)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .with_symbol::<u8>();
+    let decoder = tree.table_decoder(tree.max_len().clamp(1, 10));
+
+    let codes = &compressed[header + LENGTHS_SIZE..];
+    let mut input = SliceBitReader::new(codes);
+    // every byte takes at least one bit, which bounds an honest length
+    data.reserve(len.min(input.remaining()) as usize);
+    for _ in 0..len {
+        data.push(decoder.decode(&mut input)?);
+    }
+    // the padding of the last byte is less than a byte
+    let unread = (input.remaining() / 8) as usize;
+    Ok(compressed.len() - unread)
+}
+
+fn check_end(rest: &[u8]) -> io::Result<()> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "trailing data after the compressed stream",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            vec![7],
+            vec![0; 1000],
+            b"mississippi river".to_vec(),
+            (0..=255).collect(),
+        ];
+        // skewed enough for the length limit to matter
+        let mut fibonacci = Vec::new();
+        let (mut a, mut b) = (1, 1);
+        for byte in 0..20 {
+            fibonacci.resize(fibonacci.len() + a, byte);
+            let next = a + b;
+            a = b;
+            b = next;
+        }
+        inputs.push(fibonacci);
+        inputs.push(
+            (0..5000u64)
+                .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58) as u8)
+                .collect(),
+        );
+        for data in inputs.iter() {
+            let compressed = compress(data);
+            assert_eq!(&decompress(&compressed).unwrap(), data);
+        }
+        assert!(compress(&[0; 1000]).len() < 1000 / 8 + 140);
+    }
+
+    #[test]
+    fn malformed() {
+        let compressed = compress(b"mississippi river");
+        for len in 0..compressed.len() {
+            let err = decompress(&compressed[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", len);
+        }
+        let mut longer = compressed.clone();
+        longer.push(0);
+        let err = decompress(&longer).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut bad_lengths = compressed;
+        bad_lengths[1] = 0x11;
+        let err = decompress(&bad_lengths).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn blocks() {
+        // text, then bytes of another distribution
+        let mut data = b"the quick brown fox jumps over the lazy dog. ".repeat(200);
+        data.extend(
+            (0..9000u64).map(|i| 128 + (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61) as u8),
+        );
+        for &block_size in [1, 100, 4096, 9000, data.len(), 1 << 20].iter() {
+            let compressed = compress_blocks(&data, block_size);
+            assert_eq!(
+                decompress_blocks(&compressed).unwrap(),
+                data,
+                "{}",
+                block_size
+            );
+        }
+        assert!(compress_blocks(&data, 9000).len() < compress(&data).len());
+        assert_eq!(decompress_blocks(&compress_blocks(&[], 10)).unwrap(), []);
+
+        let compressed = compress_blocks(b"mississippi river", 5);
+        for len in 0..compressed.len() {
+            let err = decompress_blocks(&compressed[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", len);
+        }
+        let mut longer = compressed.clone();
+        longer.push(0);
+        let err = decompress_blocks(&longer).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // a total length that ends inside a block
+        let mut shorter = compressed;
+        shorter[0] -= 1;
+        let err = decompress_blocks(&shorter).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,162 @@
+//! The fixed Huffman codes of DEFLATE, RFC 1951 section 3.2.6.
+//!
+//! A DEFLATE block of type 1 codes its literals, lengths and distances with
+//! these codes instead of codes sent in the block. They are canonical, and
+//! a DEFLATE stream is read LSB first, the first bit of a code at the least
+//! significant bit of the first byte, as `BitReader::new` and
+//! `BitWriter::new` do.
+//!
+//! # Examples
+//!
+//! The block of `b"a"`, as written by zlib:
+//!
+//! ```
+//! # use rust_compress::bit_io::{BitSink, BitWriter};
+//! # use rust_compress::huffman::deflate;
+//! let encoder = deflate::fixed_literal_encoder();
+//! let mut writer = BitWriter::new(Vec::new());
+//! // the last block, of fixed codes
+//! writer.write_bits(1, 1).unwrap();
+//! writer.write_bits(1, 2).unwrap();
+//! encoder.encode_to(b'a' as usize, &mut writer).unwrap();
+//! encoder.encode_to(deflate::END_OF_BLOCK, &mut writer).unwrap();
+//! assert_eq!(writer.finish().unwrap(), [0x4b, 0x04, 0x00]);
+//! ```
+
+use super::{HuffmanDecoder, HuffmanEncoder, HuffmanTree};
+
+/// The number of literal/length codes: the 256 bytes, the end of block and
+/// the lengths, of which 286 and 287 never occur in a stream.
+pub const LITERALS: usize = 288;
+
+/// The number of distance codes, of which 30 and 31 never occur in a
+/// stream.
+pub const DISTANCES: usize = 32;
+
+/// The literal/length code of the end of a block.
+pub const END_OF_BLOCK: usize = 256;
+
+/// Returns the code length of every literal/length value.
+pub fn literal_lengths() -> [usize; LITERALS] {
+    let mut lengths = [0; LITERALS];
+    for (value, len) in lengths.iter_mut().enumerate() {
+        *len = match value {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    lengths
+}
+
+/// Returns the code length of every distance value.
+pub fn distance_lengths() -> [usize; DISTANCES] {
+    [5; DISTANCES]
+}
+
+/// Returns the tree of the fixed literal/length code, whose end-of-stream
+/// value is `END_OF_BLOCK`.
+pub fn fixed_literal_tree() -> HuffmanTree {
+    let mut tree = HuffmanTree::from_lengths(&literal_lengths()).unwrap();
+    tree.set_eof(END_OF_BLOCK);
+    tree
+}
+
+/// Returns the tree of the fixed distance code.
+pub fn fixed_distance_tree() -> HuffmanTree {
+    HuffmanTree::from_lengths(&distance_lengths()).unwrap()
+}
+
+pub fn fixed_literal_encoder() -> HuffmanEncoder {
+    fixed_literal_tree().encoder()
+}
+
+pub fn fixed_literal_decoder() -> HuffmanDecoder {
+    fixed_literal_tree().decoder()
+}
+
+pub fn fixed_distance_encoder() -> HuffmanEncoder {
+    fixed_distance_tree().encoder()
+}
+
+pub fn fixed_distance_decoder() -> HuffmanDecoder {
+    fixed_distance_tree().decoder()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_io::{BitReader, BitWriter};
+
+    #[test]
+    fn literal_codes() {
+        let encoder = fixed_literal_encoder();
+        // the first and last codes of each range, from the table of the RFC
+        let vectors = [
+            (0, "00110000"),
+            (143, "10111111"),
+            (144, "110010000"),
+            (255, "111111111"),
+            (256, "0000000"),
+            (279, "0010111"),
+            (280, "11000000"),
+            (287, "11000111"),
+        ];
+        for &(value, code) in vectors.iter() {
+            assert_eq!(encoder.encode(value).to_string(), code, "{}", value);
+        }
+        assert_eq!(encoder.encode_eof().to_string(), "0000000");
+        let decoder = fixed_literal_decoder();
+        let values: Vec<usize> = (0..LITERALS).collect();
+        let bits = encoder.encode_all(&values);
+        assert_eq!(
+            decoder.decode_all(&mut bits.iter(), LITERALS).unwrap(),
+            values
+        );
+    }
+
+    #[test]
+    fn distance_codes() {
+        let encoder = fixed_distance_encoder();
+        assert_eq!(encoder.encode(0).to_string(), "00000");
+        assert_eq!(encoder.encode(5).to_string(), "00101");
+        assert_eq!(encoder.encode(29).to_string(), "11101");
+        let decoder = fixed_distance_decoder();
+        let values: Vec<usize> = (0..DISTANCES).rev().collect();
+        let bits = encoder.encode_all(&values);
+        assert_eq!(
+            decoder.decode_all(&mut bits.iter(), DISTANCES).unwrap(),
+            values
+        );
+    }
+
+    #[test]
+    fn zlib_block() {
+        // "abcabcabc", as compressed by zlib with fixed codes: the
+        // literals "abca", then a match of length 5 (code 259) at distance
+        // 3 (code 2), then the end of block
+        let mut writer = BitWriter::new(Vec::new());
+        let (literals, distances) = (fixed_literal_encoder(), fixed_distance_encoder());
+        writer.write_bits(1, 1).unwrap();
+        writer.write_bits(1, 2).unwrap();
+        for &byte in b"abca".iter() {
+            literals.encode_to(byte as usize, &mut writer).unwrap();
+        }
+        literals.encode_to(259, &mut writer).unwrap();
+        distances.encode_to(2, &mut writer).unwrap();
+        literals.encode_to(END_OF_BLOCK, &mut writer).unwrap();
+        let bytes = writer.finish().unwrap();
+        assert_eq!(bytes, [0x4b, 0x4c, 0x4a, 0x4e, 0x04, 0x23, 0x00]);
+
+        let mut reader = BitReader::new(&bytes[..]);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b011);
+        let (literals, distances) = (fixed_literal_decoder(), fixed_distance_decoder());
+        for &byte in b"abca".iter() {
+            assert_eq!(literals.decode(&mut reader), byte as usize);
+        }
+        assert_eq!(literals.decode(&mut reader), 259);
+        assert_eq!(distances.decode(&mut reader), 2);
+        assert_eq!(literals.decode(&mut reader), END_OF_BLOCK);
+    }
+}
@@ -0,0 +1,160 @@
+//! Construction of Shannon–Fano codes.
+
+use std::cmp::Reverse;
+use std::marker::PhantomData;
+
+use super::{Arena, HuffmanTree};
+
+impl HuffmanTree {
+    /// Builds the Shannon–Fano code of `counts`, for comparison with the
+    /// Huffman code: it is coded and decoded the same way, with `encoder`
+    /// and `decoder`, but may be longer on average.
+    ///
+    /// The values are sorted by decreasing count and split in two halves of
+    /// counts as nearly equal as possible, the first of which gets the bit
+    /// 0 and the second the bit 1, and so on in each half. Ties are broken
+    /// by the values. Values of a count of zero are split evenly, so that
+    /// their codes are no more than about `log2(n)` bits longer than those
+    /// of the others.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::huffman::HuffmanTree;
+    /// let counts = [15, 7, 6, 6, 5];
+    /// let tree = HuffmanTree::shannon_fano(&counts);
+    /// assert_eq!(tree.to_lengths(), vec![2, 2, 2, 3, 3]);
+    /// assert_eq!(tree.encoder().encode(3).to_string(), "110");
+    /// assert_eq!(HuffmanTree::new(&counts).to_lengths(), vec![1, 3, 3, 3, 3]);
+    /// ```
+    pub fn shannon_fano(counts: &[u128]) -> HuffmanTree {
+        if counts.len() < 2 {
+            return HuffmanTree::new(counts);
+        }
+        let mut values: Vec<usize> = (0..counts.len()).collect();
+        values.sort_by_key(|&value| Reverse(counts[value]));
+        let mut arena = Arena::with_capacity(2 * counts.len() - 1);
+        split(&values, counts, &mut arena);
+        HuffmanTree {
+            elements: counts.len(),
+            eof: None,
+            nodes: arena,
+            symbol: PhantomData,
+        }
+    }
+}
+
+/// Adds the tree of `values`, sorted by decreasing count, to `arena` and
+/// returns its root.
+fn split(values: &[usize], counts: &[u128], arena: &mut Arena<u128>) -> usize {
+    enum Step {
+        Split(usize, usize),
+        Join,
+    }
+    // the tree is built in the order of a recursion from the zero half to
+    // the one half, but on a stack of its own however deep it gets
+    let mut steps = vec![Step::Split(0, values.len())];
+    let mut roots = Vec::new();
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Split(start, end) => {
+                if end - start == 1 {
+                    let value = values[start];
+                    roots.push(arena.leaf(value, counts[value]));
+                    continue;
+                }
+                let mid = start + middle(&values[start..end], counts);
+                steps.push(Step::Join);
+                steps.push(Step::Split(mid, end));
+                steps.push(Step::Split(start, mid));
+            }
+            Step::Join => {
+                let one = roots.pop().unwrap();
+                let zero = roots.pop().unwrap();
+                roots.push(arena.join(Some(one), Some(zero)));
+            }
+        }
+    }
+    roots[0]
+}
+
+/// Returns the point splitting `values`, at least two of them, in halves of
+/// counts as nearly equal as possible, the first of equally close ones.
+///
+/// Values of a count of zero, which come last, are split in halves of the
+/// same number instead, so that their codes grow no longer than needed.
+fn middle(values: &[usize], counts: &[u128]) -> usize {
+    let count: u128 = values.iter().map(|&value| counts[value]).sum();
+    if count == 0 {
+        return values.len() / 2;
+    }
+    let mut best = (u128::MAX, 1);
+    let mut first = 0;
+    for (i, &value) in values[..values.len() - 1].iter().enumerate() {
+        first += counts[value];
+        let diff = (2 * first).abs_diff(count);
+        if diff < best.0 {
+            best = (diff, i + 1);
+        }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        let sets: [&[u128]; 5] = [
+            &[15, 7, 6, 6, 5],
+            &[1, 1, 1, 1],
+            &[10, 100, 20, 50, 60, 10],
+            &[0, 0, 3],
+            &[1, 1, 2, 3, 5, 8, 13, 21],
+        ];
+        for counts in sets.iter() {
+            let tree = HuffmanTree::shannon_fano(counts);
+            let lengths = tree.to_lengths();
+            assert!(HuffmanTree::from_lengths(&lengths).is_ok());
+            let cost = |lengths: &[usize]| -> u128 {
+                counts
+                    .iter()
+                    .zip(lengths)
+                    .map(|(&c, &l)| c * l as u128)
+                    .sum()
+            };
+            assert!(cost(&lengths) >= cost(&HuffmanTree::new(counts).to_lengths()));
+            let (encoder, decoder) = (tree.encoder(), tree.decoder());
+            let values: Vec<usize> = (0..counts.len()).rev().collect();
+            let bits = encoder.encode_all(&values);
+            assert_eq!(
+                decoder.decode_all(&mut bits.iter(), values.len()).unwrap(),
+                values
+            );
+        }
+        assert_eq!(
+            HuffmanTree::shannon_fano(&[1, 1, 1, 1]).to_lengths(),
+            vec![2; 4]
+        );
+        assert_eq!(HuffmanTree::shannon_fano(&[5]).to_lengths(), vec![1]);
+    }
+
+    #[test]
+    fn sparse() {
+        let mut counts = [0; 256];
+        for &byte in b"hello world, this is some text".iter() {
+            counts[byte as usize] += 1;
+        }
+        let tree = HuffmanTree::shannon_fano(&counts);
+        assert!(tree.max_len() <= 16, "{}", tree.max_len());
+        let values: Vec<usize> = (0..256).collect();
+        let bits = tree.encoder().encode_all(&values);
+        assert_eq!(
+            tree.decoder()
+                .decode_all(&mut bits.iter(), values.len())
+                .unwrap(),
+            values
+        );
+    }
+}
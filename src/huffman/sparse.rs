@@ -0,0 +1,252 @@
+//! Huffman coding of large alphabets of which few values occur.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::bit_io::{BitSink, BitSource};
+use crate::bit_vec::BitVec;
+
+use super::{to_symbol, CodeLengthsError, DecodeError, HuffmanDecoder, HuffmanEncoder};
+use super::{HuffmanTree, Symbol};
+
+/// A Huffman tree of the values that occur in a large alphabet, such as
+/// the 16-bit length and distance codes of an LZ compressor.
+///
+/// A [`HuffmanTree`] and its encoder take memory for every value up to the
+/// largest one. This tree only keeps the values it was built from, which
+/// it maps to a dense range internally, and its encoder and decoder take
+/// and return the values themselves.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::huffman::SparseHuffmanTree;
+/// let tree = SparseHuffmanTree::from_symbols(vec![60_000u16, 3, 60_000, 999]);
+/// let (encoder, decoder) = (tree.encoder(), tree.decoder());
+/// assert_eq!(encoder.encode(60_000).len(), 1);
+/// let bits = encoder.encode_all(&[3, 60_000, 999]);
+/// let mut input = bits.iter();
+/// assert_eq!(decoder.decode(&mut input), 3);
+/// assert_eq!(decoder.decode(&mut input), 60_000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SparseHuffmanTree<T: Symbol = usize> {
+    tree: HuffmanTree,
+    /// The indices of the values in increasing order, by dense index.
+    values: Vec<usize>,
+    symbol: PhantomData<T>,
+}
+
+impl<T: Symbol> SparseHuffmanTree<T> {
+    /// Builds the tree of the values with the given counts. Every value
+    /// gets a code, even of a count of zero, and the counts of a value
+    /// given more than once add up.
+    pub fn from_counts<I: IntoIterator<Item = (T, u128)>>(counts: I) -> SparseHuffmanTree<T> {
+        let mut map = BTreeMap::new();
+        for (value, count) in counts {
+            *map.entry(value.to_index()).or_insert(0) += count;
+        }
+        let counts: Vec<u128> = map.values().copied().collect();
+        SparseHuffmanTree {
+            tree: HuffmanTree::new(&counts),
+            values: map.into_keys().collect(),
+            symbol: PhantomData,
+        }
+    }
+
+    /// Builds the tree of the values yielded by `symbols`.
+    pub fn from_symbols<I: IntoIterator<Item = T>>(symbols: I) -> SparseHuffmanTree<T> {
+        SparseHuffmanTree::from_counts(symbols.into_iter().map(|symbol| (symbol, 1)))
+    }
+
+    /// Builds the canonical tree in which each value has the given code
+    /// length, as returned by `to_lengths`.
+    ///
+    /// # Errors
+    ///
+    /// Fails as `HuffmanTree::from_lengths` does, or with
+    /// `CodeLengthsError::Duplicate` if a value is given twice.
+    pub fn from_lengths(lengths: &[(T, usize)]) -> Result<SparseHuffmanTree<T>, CodeLengthsError> {
+        let mut pairs: Vec<(usize, usize)> = lengths
+            .iter()
+            .map(|&(value, len)| (value.to_index(), len))
+            .collect();
+        pairs.sort_unstable();
+        if let Some(pair) = pairs.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(CodeLengthsError::Duplicate(pair[0].0));
+        }
+        let dense: Vec<usize> = pairs.iter().map(|&(_, len)| len).collect();
+        Ok(SparseHuffmanTree {
+            tree: HuffmanTree::from_lengths(&dense)?,
+            values: pairs.into_iter().map(|(value, _)| value).collect(),
+            symbol: PhantomData,
+        })
+    }
+
+    /// Returns the tree of the same code lengths in canonical form.
+    pub fn canonical(&self) -> SparseHuffmanTree<T> {
+        SparseHuffmanTree {
+            tree: self.tree.canonical(),
+            values: self.values.clone(),
+            symbol: PhantomData,
+        }
+    }
+
+    /// Returns the values of the tree with the lengths of their codes, in
+    /// increasing order of the values.
+    pub fn to_lengths(&self) -> Vec<(T, usize)> {
+        self.values
+            .iter()
+            .zip(self.tree.to_lengths())
+            .map(|(&value, len)| (T::from_index(value).unwrap(), len))
+            .collect()
+    }
+
+    pub fn encoder(&self) -> SparseEncoder<T> {
+        SparseEncoder {
+            encoder: self.tree.encoder(),
+            values: self.values.clone(),
+            symbol: PhantomData,
+        }
+    }
+
+    pub fn decoder(&self) -> SparseDecoder<T> {
+        SparseDecoder {
+            decoder: self.tree.decoder(),
+            values: self.values.clone(),
+            symbol: PhantomData,
+        }
+    }
+}
+
+/// The encoder of a [`SparseHuffmanTree`].
+#[derive(Clone, Debug)]
+pub struct SparseEncoder<T: Symbol = usize> {
+    encoder: HuffmanEncoder,
+    values: Vec<usize>,
+    symbol: PhantomData<T>,
+}
+
+impl<T: Symbol> SparseEncoder<T> {
+    /// Returns the code of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not in the tree.
+    pub fn encode(&self, value: T) -> BitVec {
+        self.encoder.encode(self.dense(value))
+    }
+
+    /// Writes the code of `value` to `sink`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not in the tree.
+    pub fn encode_to<S: BitSink>(&self, value: T, sink: &mut S) -> io::Result<()> {
+        self.encoder.encode_to(self.dense(value), sink)
+    }
+
+    /// Returns the concatenated codes of `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a value is not in the tree.
+    pub fn encode_all(&self, values: &[T]) -> BitVec {
+        let mut out = BitVec::new();
+        for &value in values.iter() {
+            self.encoder.encode_into(self.dense(value), &mut out);
+        }
+        out
+    }
+
+    /// Returns the dense index of `value`.
+    fn dense(&self, value: T) -> usize {
+        let index = value.to_index();
+        match self.values.binary_search(&index) {
+            Ok(dense) => dense,
+            Err(_) => panic!("value {} is not in the tree", index),
+        }
+    }
+}
+
+/// The decoder of a [`SparseHuffmanTree`].
+#[derive(Clone, Debug)]
+pub struct SparseDecoder<T: Symbol = usize> {
+    decoder: HuffmanDecoder,
+    values: Vec<usize>,
+    symbol: PhantomData<T>,
+}
+
+impl<T: Symbol> SparseDecoder<T> {
+    /// Decodes a value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if decoding fails; see `try_decode` for the fallible version.
+    pub fn decode<S: BitSource>(&self, input: &mut S) -> T {
+        self.try_decode(input).unwrap()
+    }
+
+    /// Decodes a value, or fails as `HuffmanDecoder::try_decode` does.
+    pub fn try_decode<S: BitSource>(&self, input: &mut S) -> Result<T, DecodeError> {
+        let dense = self.decoder.decode_index(input)?;
+        to_symbol(self.values[dense])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_dense_tree() {
+        let values: Vec<u16> = (0..3000u64)
+            .map(|i| {
+                let r = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 40;
+                // a few hundred distinct values spread over the alphabet
+                ((r % 300) * (r % 7 + 1) * 31) as u16
+            })
+            .collect();
+        let sparse = SparseHuffmanTree::from_symbols(values.iter().copied());
+        let dense = HuffmanTree::from_symbols(values.iter().copied());
+        let (encoder, decoder) = (sparse.encoder(), sparse.decoder());
+        let bits = encoder.encode_all(&values);
+        // the dense tree also spends codes on the values that do not occur
+        assert!(bits.len() < dense.encoder().encode_all(&values).len());
+        let mut input = bits.iter();
+        for &value in values.iter() {
+            assert_eq!(decoder.decode(&mut input), value);
+        }
+        assert!(decoder.try_decode(&mut input).is_err());
+
+        let rebuilt = SparseHuffmanTree::from_lengths(&sparse.to_lengths()).unwrap();
+        let canonical = sparse.canonical();
+        assert_eq!(rebuilt.to_lengths(), sparse.to_lengths());
+        for &value in values.iter().take(100) {
+            assert_eq!(
+                rebuilt.encoder().encode(value),
+                canonical.encoder().encode(value)
+            );
+        }
+    }
+
+    #[test]
+    fn counts() {
+        let tree = SparseHuffmanTree::from_counts(vec![(1000usize, 5), (7, 1), (1000, 5), (50, 0)]);
+        let lengths = tree.to_lengths();
+        assert_eq!(lengths, vec![(7, 2), (50, 2), (1000, 1)]);
+        let err = SparseHuffmanTree::from_lengths(&[(5usize, 1), (9, 2)]).unwrap_err();
+        assert_eq!(err, CodeLengthsError::Incomplete);
+        let err = SparseHuffmanTree::from_lengths(&[(5usize, 1), (9, 1), (5, 1)]).unwrap_err();
+        assert_eq!(err, CodeLengthsError::Duplicate(5));
+        assert_eq!(err.to_string(), "value 5 given twice");
+    }
+
+    #[test]
+    #[should_panic(expected = "value 8 is not in the tree")]
+    fn encode_missing() {
+        let tree = SparseHuffmanTree::from_symbols(vec![3usize, 9]);
+        tree.encoder().encode(8);
+    }
+}
@@ -0,0 +1,221 @@
+//! A Huffman decoder looking up several bits at a time.
+
+use crate::bit_io::BitPeek;
+
+use std::marker::PhantomData;
+
+use super::{to_symbol, Arena, Count, DecodeError, HuffmanTree, Symbol};
+
+#[derive(Clone, Copy, Debug)]
+enum Entry {
+    /// A code of `len` bits, at most the width of the table.
+    Value { value: usize, len: u8 },
+    /// A code longer than the width of the table, continued in the table at
+    /// `offset` of width `bits`.
+    Table { offset: usize, bits: u8 },
+    /// Bits that do not start any code.
+    Invalid,
+}
+
+/// A decoder that finds each code with one lookup in a table indexed by the
+/// next bits of the input, rather than walking the tree one bit at a time.
+///
+/// Codes longer than the width of the root table continue in sub-tables,
+/// so the tables stay small even for a few very long codes. The input must
+/// be able to look ahead, see [`BitPeek`].
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_io::SliceBitReader;
+/// # use rust_compress::huffman::HuffmanTree;
+/// let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+/// let encoder = tree.encoder();
+/// let decoder = tree.table_decoder(3);
+/// let bytes = encoder.encode_all(&[1, 4, 0, 5]).into_bytes();
+/// let mut reader = SliceBitReader::new(&bytes);
+/// for &value in [1, 4, 0, 5].iter() {
+///     assert_eq!(decoder.decode(&mut reader).unwrap(), value);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TableDecoder<T: Symbol = usize> {
+    entries: Vec<Entry>,
+    root_bits: usize,
+    symbol: PhantomData<T>,
+}
+
+impl<T: Symbol> TableDecoder<T> {
+    pub(super) fn new<C: Count>(tree: &HuffmanTree<T, C>, bits: usize) -> TableDecoder<T> {
+        assert!(
+            (1..=56).contains(&bits),
+            "table width of {} bits out of range",
+            bits
+        );
+        let mut decoder = TableDecoder {
+            entries: Vec::new(),
+            root_bits: 0,
+            symbol: PhantomData,
+        };
+        decoder.root_bits = decoder.build(&tree.nodes, tree.nodes.root(), bits);
+        decoder
+    }
+
+    /// Returns the width of the root table in bits.
+    pub fn bits(&self) -> usize {
+        self.root_bits
+    }
+
+    /// Decodes a value, or fails if the input ends before a whole code is
+    /// read or holds a bit sequence that is not a code.
+    pub fn decode<S: BitPeek>(&self, input: &mut S) -> Result<T, DecodeError> {
+        let mut offset = 0;
+        let mut bits = self.root_bits;
+        loop {
+            let index = input.peek(bits)? as usize;
+            match self.entries[offset + index] {
+                Entry::Value { value, len } => {
+                    input.consume(len as usize)?;
+                    return to_symbol(value);
+                }
+                Entry::Table {
+                    offset: next,
+                    bits: next_bits,
+                } => {
+                    input.consume(bits)?;
+                    offset = next;
+                    bits = next_bits as usize;
+                }
+                Entry::Invalid => return Err(DecodeError::InvalidCode),
+            }
+        }
+    }
+
+    /// Appends the table of the subtree at `node`, at most `max_bits` wide
+    /// but no wider than the subtree is high, and returns its width.
+    fn build<C: Count>(&mut self, arena: &Arena<C>, node: usize, max_bits: usize) -> usize {
+        let bits = height(arena, node, max_bits);
+        let offset = self.entries.len();
+        self.entries.resize(offset + (1 << bits), Entry::Invalid);
+        for index in 0..1 << bits {
+            // walk down from `node` along the bits of `index`, first bit in
+            // bit 0
+            let mut current = Some(node);
+            let mut len = 0;
+            while let Some(n) = current {
+                let n = &arena.nodes[n];
+                if n.value.is_some() || len == bits {
+                    break;
+                }
+                current = if (index >> len) & 1 == 1 {
+                    n.left
+                } else {
+                    n.right
+                };
+                len += 1;
+            }
+            self.entries[offset + index] = match current {
+                None => Entry::Invalid,
+                Some(n) => match arena.nodes[n].value {
+                    Some(value) => Entry::Value {
+                        value,
+                        len: len as u8,
+                    },
+                    None => {
+                        let next = self.entries.len();
+                        let next_bits = self.build(arena, n, max_bits);
+                        Entry::Table {
+                            offset: next,
+                            bits: next_bits as u8,
+                        }
+                    }
+                },
+            };
+        }
+        bits
+    }
+}
+
+/// Returns the height of the tree at `node`, or `max` if it is higher.
+fn height<C: Count>(arena: &Arena<C>, node: usize, max: usize) -> usize {
+    let node = &arena.nodes[node];
+    if node.value.is_some() || max == 0 {
+        return 0;
+    }
+    let child = |c: Option<usize>| c.map_or(0, |c| height(arena, c, max - 1));
+    1 + child(node.left).max(child(node.right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_io::{BitReader, BitWriter, SliceBitReader};
+    use crate::bit_order::{LsbFirst, MsbFirst};
+
+    #[test]
+    fn matches_tree_decoder() {
+        // Fibonacci counts give codes of up to 19 bits
+        let mut counts = vec![1u128, 1];
+        while counts.len() < 20 {
+            counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
+        }
+        counts.extend((0..40).map(|i| i * 37 % 101));
+        let tree = HuffmanTree::new(&counts);
+        let encoder = tree.encoder();
+        let values: Vec<usize> = (0..2000).map(|i| (i * i + i / 7) % counts.len()).collect();
+        let bytes = encoder.encode_all(&values).into_bytes();
+        for &bits in [1, 4, 9, 19, 25].iter() {
+            let decoder = tree.table_decoder(bits);
+            assert!(decoder.bits() <= bits);
+            let mut reader = SliceBitReader::new(&bytes);
+            for &value in values.iter() {
+                assert_eq!(decoder.decode(&mut reader).unwrap(), value);
+            }
+            let mut reader = BitReader::new(&bytes[..]);
+            for &value in values.iter() {
+                assert_eq!(decoder.decode(&mut reader).unwrap(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn msb_first() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let encoder = tree.encoder();
+        let decoder = tree.table_decoder(3);
+        let values = [1, 4, 0, 5, 3, 3, 2, 1];
+        let mut writer = BitWriter::<_, MsbFirst>::with_order(Vec::new());
+        encoder.encode_all_to(&values, &mut writer).unwrap();
+        let bytes = writer.finish().unwrap();
+        let mut reader = SliceBitReader::<MsbFirst>::with_order(&bytes);
+        for &value in values.iter() {
+            assert_eq!(decoder.decode(&mut reader).unwrap(), value);
+        }
+        let mut reader = SliceBitReader::<LsbFirst>::with_order(&bytes);
+        let decoded: Vec<_> = (0..8)
+            .map(|_| decoder.decode(&mut reader).unwrap())
+            .collect();
+        assert_ne!(decoded, values);
+    }
+
+    #[test]
+    fn errors() {
+        let tree = HuffmanTree::new(&[10, 100, 20, 50, 60, 10]);
+        let encoder = tree.encoder();
+        let decoder = tree.table_decoder(8);
+        let mut code = encoder.encode(5).clone();
+        code.pop();
+        let err = decoder.decode(&mut code.iter()).unwrap_err();
+        assert!(matches!(err, DecodeError::UnexpectedEnd));
+        let mut tree = tree;
+        let root = tree.nodes.root();
+        tree.nodes.nodes[root].right = None;
+        let decoder = tree.table_decoder(2);
+        let code = encoder.encode_all(&[0, 1, 2, 3, 4, 5]);
+        let mut input = code.iter();
+        let result: Vec<_> = (0..6).map(|_| decoder.decode(&mut input)).collect();
+        assert!(result
+            .iter()
+            .any(|r| matches!(r, Err(DecodeError::InvalidCode))));
+    }
+}
@@ -1,2 +1,53 @@
+//! Compression codecs and the data structures they are built of.
+//!
+//! Without the default `std` feature, the crate is `no_std` and has only the
+//! [`Model`](model::Model) trait, the [`DivisionFreeEncoder`] and
+//! [`DivisionFreeDecoder`] of [`range_coder`], and the
+//! [`ArrayBitVec`](array_bit_vec::ArrayBitVec) with its bit orders, which
+//! need no allocator, for microcontrollers.
+//!
+//! [`DivisionFreeEncoder`]: range_coder::DivisionFreeEncoder
+//! [`DivisionFreeDecoder`]: range_coder::DivisionFreeDecoder
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod adaptive_huffman;
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod arithmetic;
+pub mod array_bit_vec;
+#[cfg(feature = "std")]
+pub mod bit_io;
+pub mod bit_order;
+#[cfg(feature = "std")]
 pub mod bit_vec;
+#[cfg(feature = "std")]
+pub mod cm;
+#[cfg(feature = "std")]
+pub mod elias_fano;
+#[cfg(feature = "std")]
+pub mod fenwick;
+#[cfg(feature = "std")]
+pub mod golomb;
+#[cfg(feature = "std")]
 pub mod huffman;
+pub mod model;
+#[cfg(feature = "std")]
+pub mod packed_int_vec;
+#[cfg(feature = "std")]
+pub mod ppm;
+pub mod range_coder;
+#[cfg(feature = "std")]
+pub mod rank_select;
+#[cfg(feature = "std")]
+pub mod rans;
+#[cfg(feature = "std")]
+pub mod rrr;
+#[cfg(feature = "std")]
+pub mod tunstall;
+#[cfg(feature = "std")]
+pub mod universal_codes;
+#[cfg(feature = "std")]
+pub mod varint;
@@ -0,0 +1,61 @@
+//! Models of the values given to the arithmetic and range coders.
+//!
+//! A coder only needs the cumulative frequencies of the value it codes, and
+//! the value of a cumulative frequency when it decodes, which is what a
+//! [`Model`] gives. The static table of [`CumulativeFrequencies`] is a
+//! model, and an [`AdaptiveModel`] learns its frequencies from the values
+//! coded so far, so the decoder rebuilds the same model from the values it
+//! decodes and the frequencies never need to be sent.
+//!
+//! Without the `std` feature, there is only the [`Model`] trait.
+//!
+//! [`CumulativeFrequencies`]: crate::arithmetic::CumulativeFrequencies
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "std")] {
+//! # use rust_compress::arithmetic::{ArithmeticDecoder, ArithmeticEncoder};
+//! # use rust_compress::bit_vec::BitVec;
+//! # use rust_compress::model::AdaptiveModel;
+//! let data = b"abracadabra, abracadabra";
+//! let mut model = AdaptiveModel::new(256);
+//! let mut encoder = ArithmeticEncoder::new(BitVec::new());
+//! for &byte in data.iter() {
+//!     encoder.encode_update(&mut model, byte as usize).unwrap();
+//! }
+//! let bits = encoder.finish().unwrap();
+//!
+//! let mut model = AdaptiveModel::new(256);
+//! let mut decoder = ArithmeticDecoder::new(bits.iter()).unwrap();
+//! for &byte in data.iter() {
+//!     assert_eq!(decoder.decode_update(&mut model).unwrap(), byte as usize);
+//! }
+//! # }
+//! ```
+
+#[cfg(feature = "std")]
+mod adaptive;
+
+#[cfg(feature = "std")]
+pub use self::adaptive::{AdaptiveModel, Order1Model, QuasiStaticModel};
+
+/// The frequencies of the values `0..n` as a coder sees them.
+///
+/// The total frequency must be at most `MAX_TOTAL`. A value of frequency
+/// zero cannot be coded.
+pub trait Model {
+    /// Returns the total frequency.
+    fn total(&self) -> u32;
+
+    /// Returns the range of cumulative frequencies of `value`, from the sum
+    /// of those of the values before it up to the sum including it.
+    fn range(&self, value: usize) -> (u32, u32);
+
+    /// Returns the value whose range holds the cumulative frequency `cum`,
+    /// which is less than the total.
+    fn value_at(&self, cum: u32) -> usize;
+
+    /// Learns from `value`, once it has been coded.
+    fn update(&mut self, value: usize);
+}
@@ -0,0 +1,514 @@
+//! The models that need the standard library: the static table, and the
+//! adaptive models.
+
+use super::Model;
+use crate::arithmetic::{CumulativeFrequencies, MAX_TOTAL};
+use crate::fenwick::Fenwick;
+
+/// A static model, which learns nothing.
+impl Model for CumulativeFrequencies {
+    fn total(&self) -> u32 {
+        CumulativeFrequencies::total(self)
+    }
+
+    fn range(&self, value: usize) -> (u32, u32) {
+        CumulativeFrequencies::range(self, value)
+    }
+
+    fn value_at(&self, cum: u32) -> usize {
+        CumulativeFrequencies::value_at(self, cum)
+    }
+
+    fn update(&mut self, _value: usize) {}
+}
+
+/// The frequency a value gains every time it is coded.
+const INCREMENT: u32 = 32;
+
+/// An adaptive order-0 model, of the frequency of every value so far.
+///
+/// Every value starts at a frequency of one and gains `32` every time it
+/// is coded. Once the total goes past the limit, every frequency is halved,
+/// rounding up so that no value drops to zero, until it fits again. The
+/// model thus slowly forgets old values and follows changing statistics.
+///
+/// The cumulative frequencies are kept in a [`Fenwick`] tree, so a value is
+/// coded and counted in `O(log n)` time. Rescaling takes `O(n)` time, but
+/// happens only every few thousand values.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::model::{AdaptiveModel, Model};
+/// let mut model = AdaptiveModel::new(3);
+/// assert_eq!(model.total(), 3);
+/// model.update(1);
+/// assert_eq!(model.range(1), (1, 34));
+/// assert_eq!(model.value_at(33), 1);
+/// assert_eq!(model.value_at(34), 2);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdaptiveModel {
+    frequencies: Vec<u32>,
+    cum: Fenwick,
+    total: u32,
+    limit: u32,
+}
+
+impl AdaptiveModel {
+    /// Creates a model of `len` values, rescaled past `MAX_TOTAL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero or greater than `MAX_TOTAL`.
+    pub fn new(len: usize) -> AdaptiveModel {
+        AdaptiveModel::with_limit(len, MAX_TOTAL)
+    }
+
+    /// Creates a model of `len` values, rescaled once the total goes past
+    /// `limit`. A lower limit adapts faster.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero or greater than `limit`, or if `limit` is
+    /// greater than `MAX_TOTAL`.
+    pub fn with_limit(len: usize, limit: u32) -> AdaptiveModel {
+        assert!(
+            limit <= MAX_TOTAL,
+            "limit {} greater than the largest total {}",
+            limit,
+            MAX_TOTAL
+        );
+        assert!(
+            len > 0 && len <= limit as usize,
+            "{} values do not fit in a total of {}",
+            len,
+            limit
+        );
+        AdaptiveModel {
+            frequencies: vec![1; len],
+            cum: Fenwick::from_slice(&vec![1; len]),
+            total: len as u32,
+            limit,
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.frequencies.len()
+    }
+
+    /// Returns `true` if there are no values, which never happens.
+    pub fn is_empty(&self) -> bool {
+        self.frequencies.is_empty()
+    }
+
+    /// Returns the frequency of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range.
+    pub fn frequency(&self, value: usize) -> u32 {
+        self.frequencies[value]
+    }
+
+    /// Halves every frequency, rounding up.
+    fn rescale(&mut self) {
+        self.total = 0;
+        for frequency in self.frequencies.iter_mut() {
+            *frequency = frequency.div_ceil(2);
+            self.total += *frequency;
+        }
+        self.cum = Fenwick::from_slice(&self.frequencies);
+    }
+}
+
+impl Model for AdaptiveModel {
+    fn total(&self) -> u32 {
+        self.total
+    }
+
+    fn range(&self, value: usize) -> (u32, u32) {
+        let low = self.cum.prefix_sum(value);
+        (low, low + self.frequencies[value])
+    }
+
+    fn value_at(&self, cum: u32) -> usize {
+        self.cum.find_by_cum(cum)
+    }
+
+    fn update(&mut self, value: usize) {
+        self.frequencies[value] += INCREMENT;
+        self.cum.add(value, INCREMENT as i32);
+        self.total += INCREMENT;
+        while self.total > self.limit {
+            self.rescale();
+        }
+    }
+}
+
+/// A quasi-static model, which counts the values like an adaptive model but
+/// codes them with a static table rebuilt from the counts now and then.
+///
+/// Coding with a [`CumulativeFrequencies`] table is faster than updating
+/// cumulative frequencies after every value, and a table rebuilt often
+/// enough follows the statistics almost as well. The encoder and the
+/// decoder stay in step by following the same rule:
+///
+/// 1. Every value starts with a count of one, and the table is built from
+///    these counts, all values being equally likely.
+/// 2. Every value coded adds one to its count, leaving the table as it is.
+/// 3. After every `period` values, the counts are halved, rounding up,
+///    as long as their total is greater than `MAX_TOTAL`, and the table is
+///    rebuilt from them.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::model::{Model, QuasiStaticModel};
+/// let mut model = QuasiStaticModel::new(2, 3);
+/// model.update(1);
+/// model.update(1);
+/// assert_eq!(model.range(1), (1, 2));
+/// // rebuilt from the counts 1 and 4
+/// model.update(1);
+/// assert_eq!(model.range(1), (1, 5));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuasiStaticModel {
+    counts: Vec<u32>,
+    table: CumulativeFrequencies,
+    period: usize,
+    /// The number of values counted since the table was built.
+    pending: usize,
+}
+
+impl QuasiStaticModel {
+    /// Creates a model of `len` values, whose table is rebuilt after every
+    /// `period` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero or greater than `MAX_TOTAL`, or if `period`
+    /// is zero or not less than `2^31`.
+    pub fn new(len: usize, period: usize) -> QuasiStaticModel {
+        assert!(
+            len > 0 && len <= MAX_TOTAL as usize,
+            "{} values do not fit in a total of {}",
+            len,
+            MAX_TOTAL
+        );
+        // the counts may grow by a period past the largest total
+        assert!(
+            period > 0 && period < 1 << 31,
+            "period {} out of range",
+            period
+        );
+        let counts = vec![1; len];
+        QuasiStaticModel {
+            table: CumulativeFrequencies::new(&counts),
+            counts,
+            period,
+            pending: 0,
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns `true` if there are no values, which never happens.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the table the next value is coded with.
+    pub fn table(&self) -> &CumulativeFrequencies {
+        &self.table
+    }
+
+    fn rebuild(&mut self) {
+        while self.counts.iter().sum::<u32>() > MAX_TOTAL {
+            for count in self.counts.iter_mut() {
+                *count = count.div_ceil(2);
+            }
+        }
+        self.table = CumulativeFrequencies::new(&self.counts);
+        self.pending = 0;
+    }
+}
+
+impl Model for QuasiStaticModel {
+    fn total(&self) -> u32 {
+        self.table.total()
+    }
+
+    fn range(&self, value: usize) -> (u32, u32) {
+        self.table.range(value)
+    }
+
+    fn value_at(&self, cum: u32) -> usize {
+        self.table.value_at(cum)
+    }
+
+    fn update(&mut self, value: usize) {
+        self.counts[value] += 1;
+        self.pending += 1;
+        if self.pending == self.period {
+            self.rebuild();
+        }
+    }
+}
+
+/// An adaptive order-1 model, of the frequency of every value after every
+/// value: a value is coded with the [`AdaptiveModel`] of the context of the
+/// value before it.
+///
+/// The model of a context is only created when a value is first coded in
+/// it, so the few contexts a text uses cost little memory. The first value
+/// is coded in the context of `0`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::model::{Model, Order1Model};
+/// let mut model = Order1Model::new(256);
+/// for &byte in b"ququqq".iter() {
+///     model.update(byte as usize);
+/// }
+/// // after a "q", twice a "u" and once a "q"
+/// assert_eq!(model.context(), b'q' as usize);
+/// assert_eq!(model.total(), 256 + 3 * 32);
+/// let (low, high) = model.range(b'u' as usize);
+/// assert_eq!(high - low, 1 + 2 * 32);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Order1Model {
+    /// The models of the contexts, `None` until a value is coded in them.
+    contexts: Vec<Option<AdaptiveModel>>,
+    /// The model of a context of no values yet.
+    empty: AdaptiveModel,
+    /// The last value coded.
+    context: usize,
+}
+
+impl Order1Model {
+    /// Creates a model of `len` values in as many contexts, each rescaled
+    /// past `MAX_TOTAL`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero or greater than `MAX_TOTAL`.
+    pub fn new(len: usize) -> Order1Model {
+        Order1Model::with_limit(len, MAX_TOTAL)
+    }
+
+    /// Creates a model of `len` values in as many contexts, each rescaled
+    /// once its total goes past `limit`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero or greater than `limit`, or if `limit` is
+    /// greater than `MAX_TOTAL`.
+    pub fn with_limit(len: usize, limit: u32) -> Order1Model {
+        Order1Model {
+            contexts: vec![None; len],
+            empty: AdaptiveModel::with_limit(len, limit),
+            context: 0,
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.empty.len()
+    }
+
+    /// Returns `true` if there are no values, which never happens.
+    pub fn is_empty(&self) -> bool {
+        self.empty.is_empty()
+    }
+
+    /// Returns the context of the next value, the value before it.
+    pub fn context(&self) -> usize {
+        self.context
+    }
+
+    /// Returns the number of contexts whose model has been created.
+    pub fn contexts_used(&self) -> usize {
+        self.contexts.iter().filter(|model| model.is_some()).count()
+    }
+
+    fn current(&self) -> &AdaptiveModel {
+        self.contexts[self.context].as_ref().unwrap_or(&self.empty)
+    }
+}
+
+impl Model for Order1Model {
+    fn total(&self) -> u32 {
+        self.current().total()
+    }
+
+    fn range(&self, value: usize) -> (u32, u32) {
+        self.current().range(value)
+    }
+
+    fn value_at(&self, cum: u32) -> usize {
+        self.current().value_at(cum)
+    }
+
+    fn update(&mut self, value: usize) {
+        let empty = &self.empty;
+        self.contexts[self.context]
+            .get_or_insert_with(|| empty.clone())
+            .update(value);
+        self.context = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::{ArithmeticDecoder, ArithmeticEncoder};
+    use crate::bit_vec::BitVec;
+    use crate::range_coder::{RangeDecoder, RangeEncoder};
+
+    #[test]
+    fn rescale() {
+        let mut model = AdaptiveModel::with_limit(4, 99);
+        model.update(0);
+        model.update(0);
+        assert_eq!(model.total(), 68);
+        // 100 is past the limit: 65, 1, 1, 33 halve to 33, 1, 1, 17
+        model.update(3);
+        assert_eq!(model.total(), 52);
+        assert_eq!(model.range(3), (35, 52));
+        assert_eq!(model.frequency(1), 1);
+        for _ in 0..1000 {
+            model.update(2);
+            assert!(model.total() <= 99);
+        }
+        assert_eq!(model.frequency(0), 1);
+        // the smallest limit
+        let mut model = AdaptiveModel::with_limit(3, 3);
+        model.update(2);
+        assert_eq!(model.total(), 3);
+    }
+
+    #[test]
+    fn adaptive_round_trip() {
+        // a source whose statistics change halfway
+        let values: Vec<usize> = (0..20_000u64)
+            .map(|i| {
+                let r = (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as usize;
+                match (i < 10_000, r) {
+                    (true, 0..=239) => 0,
+                    (false, 0..=239) => 7,
+                    (_, r) => r % 8,
+                }
+            })
+            .collect();
+        let mut model = AdaptiveModel::new(8);
+        let mut encoder = ArithmeticEncoder::new(BitVec::new());
+        for &value in values.iter() {
+            encoder.encode_update(&mut model, value).unwrap();
+        }
+        let bits = encoder.finish().unwrap();
+        // each half has about 0.5 bits of entropy per value, and the values
+        // taken together 1.35 bits: a static model would spend twice as much
+        assert!(bits.len() < values.len() * 7 / 10, "{}", bits.len());
+        let mut model = AdaptiveModel::new(8);
+        let mut decoder = ArithmeticDecoder::new(bits.iter()).unwrap();
+        for &value in values.iter() {
+            assert_eq!(decoder.decode_update(&mut model).unwrap(), value);
+        }
+
+        let mut model = AdaptiveModel::with_limit(8, 1 << 12);
+        let mut encoder = RangeEncoder::new(Vec::new());
+        for &value in values.iter() {
+            encoder.encode_update(&mut model, value).unwrap();
+        }
+        let bytes = encoder.finish().unwrap();
+        let mut model = AdaptiveModel::with_limit(8, 1 << 12);
+        let mut decoder = RangeDecoder::new(&bytes[..]).unwrap();
+        for &value in values.iter() {
+            assert_eq!(decoder.decode_update(&mut model).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn quasi_static() {
+        let mut model = QuasiStaticModel::new(3, 1000);
+        for i in 0..999 {
+            model.update(i % 2);
+            assert_eq!(model.table(), &CumulativeFrequencies::new(&[1, 1, 1]));
+        }
+        model.update(2);
+        assert_eq!(model.table(), &CumulativeFrequencies::new(&[501, 500, 2]));
+        // 70_000 more values, which go past the largest total once
+        for _ in 0..70 {
+            for _ in 0..1000 {
+                model.update(0);
+            }
+        }
+        assert_eq!(
+            model.table(),
+            &CumulativeFrequencies::new(&[37_751, 250, 1])
+        );
+        assert!(model.total() <= MAX_TOTAL);
+
+        let values: Vec<usize> = (0..10_000u64)
+            .map(|i| {
+                ((i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 60) as usize).min(i as usize / 1000)
+            })
+            .collect();
+        let mut model = QuasiStaticModel::new(16, 256);
+        let mut encoder = RangeEncoder::new(Vec::new());
+        for &value in values.iter() {
+            encoder.encode_update(&mut model, value).unwrap();
+        }
+        let bytes = encoder.finish().unwrap();
+        let mut model = QuasiStaticModel::new(16, 256);
+        let mut decoder = RangeDecoder::new(&bytes[..]).unwrap();
+        for &value in values.iter() {
+            assert_eq!(decoder.decode_update(&mut model).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn order1() {
+        // text whose next letter depends on the letter before it
+        let words = ["the ", "then ", "that ", "this ", "there "];
+        let text: Vec<u8> = (0..4000u64)
+            .flat_map(|i| words[(i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61) as usize % 5].bytes())
+            .collect();
+        let encode = |model: &mut dyn Model| {
+            let mut encoder = ArithmeticEncoder::new(BitVec::new());
+            for &byte in text.iter() {
+                encoder.encode_update(model, byte as usize).unwrap();
+            }
+            encoder.finish().unwrap()
+        };
+        let order0 = encode(&mut AdaptiveModel::new(256)).len();
+        let mut model = Order1Model::new(256);
+        let bits = encode(&mut model);
+        // the letters, the space and the first context
+        assert_eq!(model.contexts_used(), 10);
+        assert_eq!(model.context(), b' ' as usize);
+        assert!(bits.len() * 2 < order0, "{} {}", bits.len(), order0);
+
+        let mut model = Order1Model::new(256);
+        let mut decoder = ArithmeticDecoder::new(bits.iter()).unwrap();
+        for &byte in text.iter() {
+            assert_eq!(decoder.decode_update(&mut model).unwrap(), byte as usize);
+        }
+    }
+
+    #[test]
+    fn static_model() {
+        let mut table = CumulativeFrequencies::new(&[1, 2, 3]);
+        let copy = table.clone();
+        Model::update(&mut table, 2);
+        assert_eq!(table, copy);
+    }
+}
@@ -0,0 +1,258 @@
+//! A vector of fixed-width unsigned integers packed into a `BitVec`.
+
+use crate::bit_vec::BitVec;
+
+/// A growable vector of unsigned integers that all occupy the same number
+/// of bits, stored back to back without padding.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::packed_int_vec::PackedIntVec;
+/// let mut v = PackedIntVec::new(5);
+/// v.push(3);
+/// v.push(31);
+/// v.push(17);
+/// assert_eq!(v.len(), 3);
+/// assert_eq!(v.get(1), Some(31));
+/// v.set(1, 8);
+/// assert_eq!(v.iter().collect::<Vec<_>>(), vec![3, 8, 17]);
+/// assert_eq!(v.as_bit_vec().len(), 15);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PackedIntVec {
+    bits: BitVec,
+    bit_width: usize,
+    len: usize,
+}
+
+impl PackedIntVec {
+    /// Constructs a new, empty vector of `bit_width`-bit integers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit_width` is greater than 64.
+    pub fn new(bit_width: usize) -> Self {
+        PackedIntVec::with_capacity(bit_width, 0)
+    }
+
+    /// Constructs a new, empty vector of `bit_width`-bit integers with room
+    /// for at least `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit_width` is greater than 64.
+    pub fn with_capacity(bit_width: usize, capacity: usize) -> Self {
+        assert!(
+            bit_width <= 64,
+            "bit width must be at most 64, but is {}",
+            bit_width
+        );
+        PackedIntVec {
+            bits: BitVec::with_capacity(bit_width * capacity),
+            bit_width,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bits each element occupies.
+    pub fn bit_width(&self) -> usize {
+        self.bit_width
+    }
+
+    /// Returns the largest value an element can hold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_compress::packed_int_vec::PackedIntVec;
+    /// assert_eq!(PackedIntVec::new(0).max_value(), 0);
+    /// assert_eq!(PackedIntVec::new(12).max_value(), 4095);
+    /// assert_eq!(PackedIntVec::new(64).max_value(), u64::MAX);
+    /// ```
+    pub fn max_value(&self) -> u64 {
+        if self.bit_width == 64 {
+            u64::MAX
+        } else {
+            (1 << self.bit_width) - 1
+        }
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends `value` to the back of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` does not fit in `bit_width()` bits.
+    pub fn push(&mut self, value: u64) {
+        self.check_value(value);
+        self.bits.push_bits(value, self.bit_width);
+        self.len += 1;
+    }
+
+    /// Removes the last element and returns it, or `None` if the vector is
+    /// empty.
+    pub fn pop(&mut self) -> Option<u64> {
+        let value = self.get(self.len.checked_sub(1)?)?;
+        self.len -= 1;
+        self.bits.truncate(self.len * self.bit_width);
+        Some(value)
+    }
+
+    /// Returns the element at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index < self.len {
+            Some(self.bits.read_bits(index * self.bit_width, self.bit_width))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the element at `index` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds or `value` does not fit in
+    /// `bit_width()` bits.
+    pub fn set(&mut self, index: usize, value: u64) {
+        assert!(
+            index < self.len,
+            "index out of bounds: the len is {} but the index is {}",
+            self.len,
+            index
+        );
+        self.check_value(value);
+        self.bits
+            .write_bits(index * self.bit_width, value, self.bit_width);
+    }
+
+    /// Clears the vector, removing all elements.
+    pub fn clear(&mut self) {
+        self.bits.clear();
+        self.len = 0;
+    }
+
+    /// Returns the underlying bits, `bit_width()` bits per element.
+    pub fn as_bit_vec(&self) -> &BitVec {
+        &self.bits
+    }
+
+    /// Returns an iterator over the elements.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            vec: self,
+            front: 0,
+            back: self.len,
+        }
+    }
+
+    fn check_value(&self, value: u64) {
+        assert!(
+            value <= self.max_value(),
+            "value {} does not fit in {} bits",
+            value,
+            self.bit_width
+        );
+    }
+}
+
+impl Extend<u64> for PackedIntVec {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a PackedIntVec {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a [`PackedIntVec`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    vec: &'a PackedIntVec,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = u64;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let value = self.vec.get(self.front);
+        self.front += 1;
+        value
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.vec.get(self.back)
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_get_set() {
+        for &width in [0, 1, 7, 13, 63, 64].iter() {
+            let mut v = PackedIntVec::new(width);
+            let values: Vec<u64> = (0..200u64)
+                .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) & v.max_value())
+                .collect();
+            v.extend(values.iter().cloned());
+            assert_eq!(v.len(), 200);
+            assert_eq!(v.as_bit_vec().len(), 200 * width);
+            assert_eq!(v.iter().collect::<Vec<_>>(), values);
+            assert_eq!(
+                v.iter().rev().collect::<Vec<_>>(),
+                values.iter().rev().cloned().collect::<Vec<_>>()
+            );
+            for i in (0..200).step_by(3) {
+                v.set(i, values[199 - i]);
+            }
+            for (i, &value) in values.iter().enumerate() {
+                let expected = if i % 3 == 0 { values[199 - i] } else { value };
+                assert_eq!(v.get(i), Some(expected));
+            }
+            assert_eq!(v.get(200), None);
+            assert_eq!(v.pop(), Some(values[199]));
+            assert_eq!(v.as_bit_vec().len(), 199 * width);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "value 8 does not fit in 3 bits")]
+    fn value_too_wide() {
+        PackedIntVec::new(3).push(8);
+    }
+}
@@ -0,0 +1,425 @@
+//! Prediction by partial matching, a compressor of byte strings that codes
+//! every byte with the statistics of the longest context it has seen it in.
+//!
+//! A byte is predicted from the bytes that followed the last `k` bytes
+//! before, for `k` from the highest order down. A context that has never
+//! seen the byte codes an escape to the next lower order instead, with the
+//! method C of Moffat: the escape counts as often as there are distinct
+//! bytes in the context. The bytes of a context that escaped are excluded
+//! from the lower orders, which could not code them anyway. Below order 0,
+//! every byte not yet excluded is equally likely.
+//!
+//! The values are coded by the [`ArithmeticEncoder`] against tables built
+//! for every context on the way.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rust_compress::ppm;
+//! let data = b"how much wood would a woodchuck chuck if a woodchuck could chuck wood";
+//! let compressed = ppm::compress(data);
+//! assert!(compressed.len() < data.len() * 3 / 5);
+//! assert_eq!(ppm::decompress(&compressed).unwrap(), &data[..]);
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::mem;
+
+use crate::arithmetic::{ArithmeticDecoder, ArithmeticEncoder, CumulativeFrequencies};
+use crate::bit_io::{BitWriter, SliceBitReader};
+use crate::varint;
+
+/// The highest order, so that a context fits in a `u64`.
+pub const MAX_ORDER: usize = 8;
+
+/// The order of `compress`.
+pub const DEFAULT_ORDER: usize = 4;
+
+/// The memory bound of `compress`, in bytes.
+pub const DEFAULT_MEMORY: usize = 64 << 20;
+
+/// The total count past which the counts of a context are halved.
+const MAX_COUNT: u32 = 1 << 13;
+
+/// Compresses `data` at order 4 with statistics of at most 64 MiB.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    compress_with(data, DEFAULT_ORDER, DEFAULT_MEMORY)
+}
+
+/// Compresses `data` into a self-describing stream that `decompress` reads
+/// back, predicting bytes from up to `max_order` bytes before them.
+///
+/// The statistics take `memory` bytes at most, counting the slots the
+/// hash table and the lists of counts have allocated, used or not: once
+/// they go over, they are thrown away and learned again from scratch. The
+/// decompressor then takes as much memory.
+///
+/// The stream starts with the length of `data` as a varint, `max_order` as
+/// a byte and `memory` as a varint, followed by the arithmetic code, LSB
+/// first.
+///
+/// # Panics
+///
+/// Panics if `max_order` is greater than `MAX_ORDER`.
+pub fn compress_with(data: &[u8], max_order: usize, memory: usize) -> Vec<u8> {
+    assert!(
+        max_order <= MAX_ORDER,
+        "order {} greater than {}",
+        max_order,
+        MAX_ORDER
+    );
+    let mut out = Vec::new();
+    varint::encode_u64(data.len() as u64, &mut out);
+    out.push(max_order as u8);
+    varint::encode_u64(memory as u64, &mut out);
+
+    let mut model = Ppm::new(max_order, memory);
+    let mut encoder = ArithmeticEncoder::new(BitWriter::new(out));
+    for &byte in data.iter() {
+        let mut excluded = [false; 256];
+        let mut coded = false;
+        for order in (0..=model.orders()).rev() {
+            let (symbols, table) = match model.table(order, &excluded) {
+                Some(found) => found,
+                None => continue,
+            };
+            match symbols.iter().position(|&symbol| symbol == byte) {
+                Some(index) => {
+                    encoder.encode(&table, index).unwrap();
+                    coded = true;
+                    break;
+                }
+                None => {
+                    encoder.encode(&table, symbols.len()).unwrap();
+                    for &symbol in symbols.iter() {
+                        excluded[symbol as usize] = true;
+                    }
+                }
+            }
+        }
+        if !coded {
+            encoder.encode(&uniform(&excluded), byte as usize).unwrap();
+        }
+        model.update(byte);
+    }
+    encoder.finish().unwrap().finish().unwrap()
+}
+
+/// Decompresses a stream made by `compress` or `compress_with`.
+///
+/// Fails with `ErrorKind::UnexpectedEof` if the stream is cut short, and
+/// with `ErrorKind::InvalidData` if it is malformed. The arithmetic code
+/// reads as zeros a little past its end, so a stream cut short by a few
+/// bytes or with data left after it may decode to wrong data instead.
+pub fn decompress(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let (len, mut pos) = varint::decode_u64(compressed)?;
+    let max_order = *compressed.get(pos).ok_or(io::ErrorKind::UnexpectedEof)? as usize;
+    if max_order > MAX_ORDER {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("order {} greater than {}", max_order, MAX_ORDER),
+        ));
+    }
+    let (memory, used) = varint::decode_u64(&compressed[pos + 1..])?;
+    pos += 1 + used;
+    let memory = usize::try_from(memory).map_err(|_| invalid("memory bound out of range"))?;
+
+    let mut model = Ppm::new(max_order, memory);
+    let mut decoder = ArithmeticDecoder::new(SliceBitReader::new(&compressed[pos..]))?;
+    // a byte takes at least a few bits but for the longest repetitions,
+    // which bounds the memory an honest length needs at once
+    let mut data = Vec::with_capacity(len.min(64 * compressed.len() as u64) as usize);
+    for _ in 0..len {
+        let mut excluded = [false; 256];
+        let mut found = None;
+        for order in (0..=model.orders()).rev() {
+            let (symbols, table) = match model.table(order, &excluded) {
+                Some(found) => found,
+                None => continue,
+            };
+            let index = decoder.decode(&table)?;
+            if index < symbols.len() {
+                found = Some(symbols[index]);
+                break;
+            }
+            if index > symbols.len() {
+                return Err(invalid("byte out of range"));
+            }
+            for &symbol in symbols.iter() {
+                excluded[symbol as usize] = true;
+            }
+        }
+        let byte = match found {
+            Some(byte) => byte,
+            None => {
+                // garbage escapes past every byte
+                if excluded.iter().all(|&e| e) {
+                    return Err(invalid("escape with no byte left"));
+                }
+                let byte = decoder.decode(&uniform(&excluded))?;
+                if byte > 255 || excluded[byte] {
+                    return Err(invalid("byte out of range"));
+                }
+                byte as u8
+            }
+        };
+        data.push(byte);
+        model.update(byte);
+    }
+    Ok(data)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The table of the bytes not excluded, all equally likely.
+fn uniform(excluded: &[bool; 256]) -> CumulativeFrequencies {
+    let frequencies: Vec<u32> = excluded.iter().map(|&e| (!e) as u32).collect();
+    CumulativeFrequencies::new(&frequencies)
+}
+
+/// The statistics of the contexts, shared by the compressor and the
+/// decompressor.
+struct Ppm {
+    max_order: usize,
+    memory: usize,
+    /// The bytes seen in every context with their counts, by order and by
+    /// the bytes of the context.
+    contexts: HashMap<(usize, u64), Vec<(u8, u32)>>,
+    /// The memory of the lists of counts in `contexts`, in bytes.
+    size: usize,
+    /// The last eight bytes, the last one lowest.
+    history: u64,
+    /// The number of bytes seen since the statistics were last reset.
+    len: usize,
+}
+
+impl Ppm {
+    fn new(max_order: usize, memory: usize) -> Ppm {
+        Ppm {
+            max_order,
+            memory,
+            contexts: HashMap::new(),
+            size: 0,
+            history: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the highest order of a context the next byte may have.
+    fn orders(&self) -> usize {
+        self.len.min(self.max_order)
+    }
+
+    fn key(&self, order: usize) -> (usize, u64) {
+        let mask = match order {
+            MAX_ORDER => !0,
+            _ => (1 << (8 * order)) - 1,
+        };
+        (order, self.history & mask)
+    }
+
+    /// Returns the bytes of the context of `order` that are not excluded,
+    /// and the table of their counts followed by that of the escape, or
+    /// `None` if there are no such bytes.
+    fn table(
+        &self,
+        order: usize,
+        excluded: &[bool; 256],
+    ) -> Option<(Vec<u8>, CumulativeFrequencies)> {
+        let counts = self.contexts.get(&self.key(order))?;
+        let (symbols, mut frequencies): (Vec<u8>, Vec<u32>) = counts
+            .iter()
+            .filter(|&&(symbol, _)| !excluded[symbol as usize])
+            .cloned()
+            .unzip();
+        if symbols.is_empty() {
+            return None;
+        }
+        frequencies.push(symbols.len() as u32);
+        Some((symbols, CumulativeFrequencies::new(&frequencies)))
+    }
+
+    /// Returns the memory of the statistics in bytes: the lists of counts,
+    /// and the hash table of which a slot takes an entry and a control
+    /// byte and at most 7 in 8 slots are used.
+    fn memory_used(&self) -> usize {
+        let slot = mem::size_of::<((usize, u64), Vec<(u8, u32)>)>() + 1;
+        self.size + self.contexts.capacity() * slot * 8 / 7
+    }
+
+    /// Counts `byte` in all its contexts, and moves past it.
+    fn update(&mut self, byte: u8) {
+        for order in 0..=self.orders() {
+            let key = self.key(order);
+            let counts = self.contexts.entry(key).or_default();
+            let capacity = counts.capacity();
+            match counts.iter_mut().find(|(symbol, _)| *symbol == byte) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((byte, 1)),
+            }
+            self.size += (counts.capacity() - capacity) * mem::size_of::<(u8, u32)>();
+            if counts.iter().map(|&(_, count)| count).sum::<u32>() > MAX_COUNT {
+                for (_, count) in counts.iter_mut() {
+                    *count = count.div_ceil(2);
+                }
+            }
+        }
+        self.history = (self.history << 8) | u64::from(byte);
+        self.len += 1;
+        if self.memory_used() > self.memory {
+            // a cleared table would keep its slots
+            self.contexts = HashMap::new();
+            self.size = 0;
+            self.len = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman;
+
+    fn text(n: u64) -> Vec<u8> {
+        let words = [
+            "the ", "of ", "and ", "a ", "to ", "in ", "is ", "you ", "that ", "it ", "he ",
+            "was ", "for ", "on ", "are ", "as ", "with ", "his ", "they ", "at ",
+        ];
+        (0..n)
+            .flat_map(|i| {
+                let r = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 59;
+                // the first words more often
+                words[(r * r / 52) as usize].bytes()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            vec![7],
+            vec![0; 1000],
+            (0..=255).collect(),
+            (0..3000u64)
+                .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8)
+                .collect(),
+            text(2000),
+        ];
+        for data in inputs.iter() {
+            for order in 0..=MAX_ORDER {
+                let compressed = compress_with(data, order, DEFAULT_MEMORY);
+                assert_eq!(&decompress(&compressed).unwrap(), data, "{}", order);
+            }
+        }
+    }
+
+    #[test]
+    fn ratio() {
+        let data = text(5000);
+        let huffman = huffman::compress(&data).len();
+        let order0 = compress_with(&data, 0, DEFAULT_MEMORY).len();
+        let order2 = compress_with(&data, 2, DEFAULT_MEMORY).len();
+        let order4 = compress(&data).len();
+        assert!(order0 < huffman, "{} {}", order0, huffman);
+        assert!(order2 * 2 < order0, "{} {}", order2, order0);
+        assert!(order4 <= order2, "{} {}", order4, order2);
+    }
+
+    #[test]
+    fn memory() {
+        // statistics reset many times along the way, which codes worse
+        let data = text(3000);
+        let small = compress_with(&data, 3, 4096);
+        assert_eq!(decompress(&small).unwrap(), data);
+        let large = compress_with(&data, 3, DEFAULT_MEMORY);
+        assert!(large.len() < small.len());
+        // too little memory for a single context
+        let tiny = compress_with(&data, 3, 0);
+        assert_eq!(decompress(&tiny).unwrap(), data);
+
+        // the bound holds for the memory allocated
+        let mut model = Ppm::new(4, 1 << 14);
+        let mut resets = 0;
+        for &byte in data.iter() {
+            model.update(byte);
+            let lists: usize = model
+                .contexts
+                .values()
+                .map(|counts| counts.capacity() * mem::size_of::<(u8, u32)>())
+                .sum();
+            assert_eq!(model.size, lists);
+            assert!(model.memory_used() <= 1 << 14);
+            resets += (model.len == 0) as usize;
+        }
+        assert!(resets > 0);
+    }
+
+    #[test]
+    fn errors() {
+        let compressed = compress(b"abc");
+        let err = decompress(&compressed[..1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let mut invalid = compressed.clone();
+        invalid[1] = MAX_ORDER as u8 + 1;
+        let err = decompress(&invalid).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // every byte once at order 0, then an escape past all of them
+        let mut out = Vec::new();
+        varint::encode_u64(257, &mut out);
+        out.push(0);
+        varint::encode_u64(DEFAULT_MEMORY as u64, &mut out);
+        let mut model = Ppm::new(0, DEFAULT_MEMORY);
+        let mut encoder = ArithmeticEncoder::new(BitWriter::new(out));
+        for byte in 0..=256 {
+            let mut excluded = [false; 256];
+            if let Some((symbols, table)) = model.table(0, &excluded) {
+                encoder.encode(&table, symbols.len()).unwrap();
+                for &symbol in symbols.iter() {
+                    excluded[symbol as usize] = true;
+                }
+            }
+            if byte < 256 {
+                encoder.encode(&uniform(&excluded), byte).unwrap();
+                model.update(byte as u8);
+            }
+        }
+        let escapes = encoder.finish().unwrap().finish().unwrap();
+        let err = decompress(&escapes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // which garbage runs out of input before
+        let garbage = [
+            0xff, 0xff, 0xff, 0xff, 0x0f, 4, 0x80, 0x80, 0x80, 0x20, 1, 2, 3,
+        ];
+        assert!(decompress(&garbage).is_err());
+        // a length far too large for the input
+        let mut short = Vec::new();
+        varint::encode_u64(1 << 32, &mut short);
+        short.extend_from_slice(&[2, 0x80, 0x80, 0x01, 0x61]);
+        let err = decompress(&short).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // cut short or with a bit flipped, but never a panic
+        let data = text(300);
+        let compressed = compress(&data);
+        for len in 0..compressed.len() - 16 {
+            let result = decompress(&compressed[..len]);
+            // past the header, the code runs out
+            assert!(result.is_err() || len < 16, "{}", len);
+        }
+        let mut failed = 0;
+        for i in 0..compressed.len() * 8 {
+            let mut flipped = compressed.clone();
+            flipped[i / 8] ^= 1 << (i % 8);
+            failed += decompress(&flipped).is_err() as usize;
+        }
+        // but in the header and the last bytes of the code, which decode
+        // to other data
+        assert!(failed > compressed.len() * 8 * 4 / 5, "{}", failed);
+    }
+}
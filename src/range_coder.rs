@@ -0,0 +1,321 @@
+//! A range coder, the bytewise counterpart of the arithmetic coder, with
+//! the same models.
+//!
+//! The coder of Subbotin keeps the low end of the interval and its width
+//! in 32-bit integers and shifts out a whole byte as soon as the leading
+//! byte of the interval is settled. When the interval gets too narrow while
+//! straddling a byte boundary, it is cut down instead of waiting for a
+//! carry. Renormalizing a byte at a time makes it faster than the
+//! arithmetic coder, at the cost of a little precision.
+//!
+//! [`SchindlerEncoder`] and [`SchindlerDecoder`] never cut the interval.
+//! The encoder holds the bytes a carry could still reach back instead, and
+//! writes them once it is settled.
+//!
+//! [`DivisionFreeEncoder`] and [`DivisionFreeDecoder`] code only with tables
+//! of a power of two total, which saves the divisions for firmware, with
+//! a precision specified exactly. They read and write bytes through the
+//! [`ByteSource`] and [`ByteSink`] traits, so that they are all there is of
+//! the module without the `std` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "std")] {
+//! # use rust_compress::arithmetic::CumulativeFrequencies;
+//! # use rust_compress::range_coder::{RangeDecoder, RangeEncoder};
+//! let table = CumulativeFrequencies::new(&[10, 1, 5]);
+//! let values = [0, 0, 2, 0, 1, 0, 2];
+//! let mut encoder = RangeEncoder::new(Vec::new());
+//! for &value in values.iter() {
+//!     encoder.encode(&table, value).unwrap();
+//! }
+//! let bytes = encoder.finish().unwrap();
+//! let mut decoder = RangeDecoder::new(&bytes[..]).unwrap();
+//! for &value in values.iter() {
+//!     assert_eq!(decoder.decode(&table).unwrap(), value);
+//! }
+//! # }
+//! ```
+
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use crate::model::Model;
+
+mod carry;
+mod division_free;
+#[cfg(feature = "std")]
+mod schindler;
+
+pub use self::division_free::{DivisionFreeDecoder, DivisionFreeEncoder};
+#[cfg(feature = "std")]
+pub use self::schindler::{SchindlerDecoder, SchindlerEncoder};
+
+/// The interval is renormalized once its leading byte is settled, that is
+/// once it fits under this boundary.
+#[cfg(feature = "std")]
+const TOP: u32 = 1 << 24;
+
+/// The narrowest interval, which is no narrower than `MAX_TOTAL`.
+#[cfg(feature = "std")]
+const BOTTOM: u32 = 1 << 16;
+
+/// A writer of the bytes of a range encoder, which needs no standard
+/// library.
+///
+/// Every `io::Write` is one, with its errors.
+pub trait ByteSink {
+    /// The error of a failed write.
+    type Error;
+
+    /// Writes `byte`.
+    fn write_byte(&mut self, byte: u8) -> Result<(), Self::Error>;
+}
+
+/// A reader of the bytes of a range decoder, which needs no standard
+/// library.
+///
+/// Every `io::Read` is one, with its errors.
+pub trait ByteSource {
+    /// The error of a failed read.
+    type Error;
+
+    /// Reads the next byte, or returns `None` at the end of the input.
+    fn read_byte(&mut self) -> Result<Option<u8>, Self::Error>;
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write + ?Sized> ByteSink for W {
+    type Error = io::Error;
+
+    fn write_byte(&mut self, byte: u8) -> io::Result<()> {
+        self.write_all(&[byte])
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read + ?Sized> ByteSource for R {
+    type Error = io::Error;
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// A range encoder writing bytes to `W`.
+///
+/// The bytes written are final: the encoder never goes back to them to
+/// propagate a carry.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct RangeEncoder<W: io::Write> {
+    inner: W,
+    low: u32,
+    range: u32,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> RangeEncoder<W> {
+    pub fn new(inner: W) -> RangeEncoder<W> {
+        RangeEncoder {
+            inner,
+            low: 0,
+            range: !0,
+        }
+    }
+
+    /// Encodes `value` with the frequencies of `table`, leaving it as it
+    /// is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range or has a frequency of zero.
+    pub fn encode<M: Model + ?Sized>(&mut self, table: &M, value: usize) -> io::Result<()> {
+        let (cum_low, cum_high) = table.range(value);
+        assert!(
+            cum_low < cum_high,
+            "value {} has a frequency of zero",
+            value
+        );
+        self.range /= table.total();
+        self.low = self.low.wrapping_add(cum_low * self.range);
+        self.range *= cum_high - cum_low;
+        loop {
+            if (self.low ^ self.low.wrapping_add(self.range)) >= TOP {
+                if self.range >= BOTTOM {
+                    return Ok(());
+                }
+                // cut the interval at the byte boundary it straddles
+                self.range = self.low.wrapping_neg() & (BOTTOM - 1);
+            }
+            self.inner.write_all(&[(self.low >> 24) as u8])?;
+            self.low <<= 8;
+            self.range <<= 8;
+        }
+    }
+
+    /// Encodes `value` with the frequencies of `model`, then updates it.
+    pub fn encode_update<M: Model + ?Sized>(
+        &mut self,
+        model: &mut M,
+        value: usize,
+    ) -> io::Result<()> {
+        self.encode(model, value)?;
+        model.update(value);
+        Ok(())
+    }
+
+    /// Writes the low end of the interval and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&self.low.to_be_bytes())?;
+        Ok(self.inner)
+    }
+}
+
+/// A decoder of the values of a [`RangeEncoder`], reading bytes from `R`.
+///
+/// Bytes past the end of the input read as zeros, so the decoder must be
+/// told the number of values, or decode an end-of-stream value.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct RangeDecoder<R: io::Read> {
+    inner: R,
+    low: u32,
+    range: u32,
+    /// The next 32 bits of the input.
+    code: u32,
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> RangeDecoder<R> {
+    /// Creates a decoder, reading the first four bytes of the input.
+    pub fn new(inner: R) -> io::Result<RangeDecoder<R>> {
+        let mut decoder = RangeDecoder {
+            inner,
+            low: 0,
+            range: !0,
+            code: 0,
+        };
+        for _ in 0..4 {
+            decoder.code = (decoder.code << 8) | u32::from(decoder.read()?);
+        }
+        Ok(decoder)
+    }
+
+    /// Decodes a value with the frequencies of `table`, which must be the
+    /// table it was encoded with.
+    pub fn decode<M: Model + ?Sized>(&mut self, table: &M) -> io::Result<usize> {
+        let total = table.total();
+        self.range /= total;
+        // only input that the encoder did not write goes past the total
+        let cum = (self.code.wrapping_sub(self.low) / self.range).min(total - 1);
+        let value = table.value_at(cum);
+        let (cum_low, cum_high) = table.range(value);
+        self.low = self.low.wrapping_add(cum_low * self.range);
+        self.range *= cum_high - cum_low;
+        loop {
+            if (self.low ^ self.low.wrapping_add(self.range)) >= TOP {
+                if self.range >= BOTTOM {
+                    return Ok(value);
+                }
+                self.range = self.low.wrapping_neg() & (BOTTOM - 1);
+            }
+            self.code = (self.code << 8) | u32::from(self.read()?);
+            self.low <<= 8;
+            self.range <<= 8;
+        }
+    }
+
+    /// Decodes a value with the frequencies of `model`, then updates it.
+    pub fn decode_update<M: Model + ?Sized>(&mut self, model: &mut M) -> io::Result<usize> {
+        let value = self.decode(model)?;
+        model.update(value);
+        Ok(value)
+    }
+
+    /// Returns the reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read(&mut self) -> io::Result<u8> {
+        Ok(self.inner.read_byte()?.unwrap_or(0))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::arithmetic::{ArithmeticEncoder, CumulativeFrequencies, MAX_TOTAL};
+    use crate::bit_vec::BitVec;
+
+    /// Returns `n` pairs of a table of `tables` and a value drawn from it,
+    /// as likely as its frequency says, for the coders to round-trip.
+    pub(super) fn values(tables: &[CumulativeFrequencies], n: u64) -> Vec<(usize, usize)> {
+        (0..n)
+            .map(|i| {
+                let r = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 24;
+                let table = (r % tables.len() as u64) as usize;
+                let cum = ((r >> 8) % u64::from(tables[table].total())) as u32;
+                (table, tables[table].value_at(cum))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let tables = [
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            CumulativeFrequencies::new(&[1; 300]),
+            CumulativeFrequencies::new(&[7]),
+            CumulativeFrequencies::new(&[1, 0, 1000, 3, 0, 20]),
+        ];
+        let values = values(&tables, 20_000);
+        let mut encoder = RangeEncoder::new(Vec::new());
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        let bytes = encoder.finish().unwrap();
+        let mut decoder = RangeDecoder::new(&bytes[..]).unwrap();
+        for &(table, value) in values.iter() {
+            assert_eq!(decoder.decode(&tables[table]).unwrap(), value);
+        }
+
+        // about as short as with the arithmetic coder
+        let mut encoder = ArithmeticEncoder::new(BitVec::new());
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        let bits = encoder.finish().unwrap().len();
+        assert!(
+            bytes.len() * 8 < bits * 101 / 100 + 64,
+            "{} {}",
+            bytes.len(),
+            bits
+        );
+    }
+
+    #[test]
+    fn garbage() {
+        // any input decodes to some values
+        let table = CumulativeFrequencies::new(&[3, 1, 4, 1, 5]);
+        let input: Vec<u8> = (0..100u64)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8)
+            .collect();
+        let mut decoder = RangeDecoder::new(&input[..]).unwrap();
+        for _ in 0..1000 {
+            assert!(decoder.decode(&table).unwrap() < 5);
+        }
+    }
+}
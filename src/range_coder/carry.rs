@@ -0,0 +1,116 @@
+//! The interval of the range coder of Schindler and its renormalization,
+//! shared by the coders that narrow it with a division and without.
+
+use super::{ByteSink, ByteSource};
+
+/// The interval is renormalized once its width falls under this boundary.
+const TOP: u32 = 1 << 24;
+
+/// The interval of an encoder, whose low end has a 33rd bit for the carry
+/// out of it.
+///
+/// A byte shifted out of the low end is held back, with the run of `0xff`
+/// bytes after it which a carry would turn into zeros, until a byte
+/// shifted out shows whether the carry happened.
+#[derive(Debug)]
+pub(super) struct Encoder {
+    low: u64,
+    pub(super) range: u32,
+    /// The byte held back, and the number of bytes held back with it.
+    cache: u8,
+    pending: u64,
+}
+
+impl Encoder {
+    pub(super) fn new() -> Encoder {
+        Encoder {
+            low: 0,
+            range: !0,
+            // a first byte of zero, which the decoder skips, takes the
+            // carries out of the first byte of the interval
+            cache: 0,
+            pending: 1,
+        }
+    }
+
+    /// Narrows the interval to `width` from `start` within it, and
+    /// renormalizes it into `sink`.
+    pub(super) fn narrow<S: ByteSink + ?Sized>(
+        &mut self,
+        start: u32,
+        width: u32,
+        sink: &mut S,
+    ) -> Result<(), S::Error> {
+        self.low += u64::from(start);
+        self.range = width;
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low(sink)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the low end of the interval and the bytes held back.
+    pub(super) fn finish<S: ByteSink + ?Sized>(&mut self, sink: &mut S) -> Result<(), S::Error> {
+        for _ in 0..5 {
+            self.shift_low(sink)?;
+        }
+        Ok(())
+    }
+
+    /// Shifts the leading byte out of the low end of the interval.
+    fn shift_low<S: ByteSink + ?Sized>(&mut self, sink: &mut S) -> Result<(), S::Error> {
+        let carry = (self.low >> 32) as u8;
+        let byte = (self.low >> 24) as u8;
+        // a byte of 0xff with no carry might still take one
+        if byte != 0xff || carry != 0 {
+            sink.write_byte(self.cache.wrapping_add(carry))?;
+            for _ in 1..self.pending {
+                sink.write_byte(0xff_u8.wrapping_add(carry))?;
+            }
+            self.cache = byte;
+            self.pending = 0;
+        }
+        self.pending += 1;
+        self.low = (self.low & 0x00ff_ffff) << 8;
+        Ok(())
+    }
+}
+
+/// The interval of a decoder, as the offset of the input from its low end.
+///
+/// Bytes past the end of the input read as zeros.
+#[derive(Debug)]
+pub(super) struct Decoder {
+    pub(super) range: u32,
+    /// The next 32 bits of the input, less the low end of the interval.
+    pub(super) code: u32,
+}
+
+impl Decoder {
+    /// Reads the first five bytes of the input.
+    pub(super) fn new<S: ByteSource + ?Sized>(source: &mut S) -> Result<Decoder, S::Error> {
+        let mut decoder = Decoder { range: !0, code: 0 };
+        for _ in 0..5 {
+            decoder.code = (decoder.code << 8) | u32::from(source.read_byte()?.unwrap_or(0));
+        }
+        Ok(decoder)
+    }
+
+    /// Narrows the interval to `width` from `start` within it, as the
+    /// encoder did, and renormalizes it from `source`.
+    pub(super) fn narrow<S: ByteSource + ?Sized>(
+        &mut self,
+        start: u32,
+        width: u32,
+        source: &mut S,
+    ) -> Result<(), S::Error> {
+        self.code = self.code.wrapping_sub(start);
+        self.range = width;
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(source.read_byte()?.unwrap_or(0));
+        }
+        Ok(())
+    }
+}
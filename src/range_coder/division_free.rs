@@ -0,0 +1,319 @@
+//! A range coder of tables of a power of two total, which needs no
+//! division.
+
+use super::{carry, ByteSink, ByteSource};
+use crate::model::Model;
+
+/// Returns the base 2 logarithm of the total frequency of `table`.
+///
+/// # Panics
+///
+/// Panics if the total frequency is not a power of two.
+fn scale_bits<M: Model + ?Sized>(table: &M) -> u32 {
+    let total = table.total();
+    assert!(
+        total.is_power_of_two(),
+        "the total frequency {} is not a power of two",
+        total
+    );
+    total.trailing_zeros()
+}
+
+/// A range encoder writing bytes to the [`ByteSink`] `S` with no division,
+/// of tables of a total frequency of a power of two.
+///
+/// The coder takes only shifts, additions, comparisons and multiplications
+/// of 32-bit integers, with a state of fixed size, for processors with no
+/// divider or floating point. It needs neither the standard library nor an
+/// allocator, and shares its carries and renormalization with the
+/// [`SchindlerEncoder`].
+///
+/// # Precision
+///
+/// The coding is specified exactly, so that any two builds of it
+/// interoperate:
+///
+/// - The width of the interval `range` is 32 bits, `2^32 - 1` at first and
+///   at least `2^24` between values. The low end of the interval `low` has
+///   a 33rd bit for the carry out of it.
+/// - The total frequency of a table is `2^k`, for `k` up to 16.
+/// - A value of the cumulative frequencies `[c, c + f)` takes the interval
+///   of `low + r * c` and of width `r * f`, where `r = range >> k`. The
+///   products never overflow 32 bits.
+/// - While the width is under `2^24`, it is shifted left by 8 and the
+///   leading byte of the low end is shifted out, held back as by the
+///   [`SchindlerEncoder`] until no carry can reach it.
+/// - The stream starts with a zero byte, and ends with the five bytes of the
+///   low end and the bytes held back.
+/// - The decoder takes the cumulative frequency `min(code / r, 2^k - 1)` of
+///   the next 32 bits of the input less the low end, `code`, which it finds
+///   bit by bit from the highest with `k` multiplications.
+///
+/// These are the steps of the [`SchindlerEncoder`] when the total is a
+/// power of two, which then writes the same bytes.
+///
+/// [`SchindlerEncoder`]: super::SchindlerEncoder
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// # use rust_compress::arithmetic::CumulativeFrequencies;
+/// # use rust_compress::range_coder::{DivisionFreeDecoder, DivisionFreeEncoder};
+/// let table = CumulativeFrequencies::new(&[10, 1, 5]);
+/// let values = [0, 0, 2, 0, 1, 0, 2];
+/// let mut encoder = DivisionFreeEncoder::new(Vec::new());
+/// for &value in values.iter() {
+///     encoder.encode(&table, value).unwrap();
+/// }
+/// let bytes = encoder.finish().unwrap();
+/// let mut decoder = DivisionFreeDecoder::new(&bytes[..]).unwrap();
+/// for &value in values.iter() {
+///     assert_eq!(decoder.decode(&table).unwrap(), value);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DivisionFreeEncoder<S: ByteSink> {
+    inner: S,
+    state: carry::Encoder,
+}
+
+impl<S: ByteSink> DivisionFreeEncoder<S> {
+    pub fn new(inner: S) -> DivisionFreeEncoder<S> {
+        DivisionFreeEncoder {
+            inner,
+            state: carry::Encoder::new(),
+        }
+    }
+
+    /// Encodes `value` with the frequencies of `table`, leaving it as it
+    /// is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total frequency of `table` is not a power of two, or if
+    /// `value` is out of range or has a frequency of zero.
+    pub fn encode<M: Model + ?Sized>(&mut self, table: &M, value: usize) -> Result<(), S::Error> {
+        let bits = scale_bits(table);
+        let (cum_low, cum_high) = table.range(value);
+        assert!(
+            cum_low < cum_high,
+            "value {} has a frequency of zero",
+            value
+        );
+        let r = self.state.range >> bits;
+        self.state
+            .narrow(cum_low * r, (cum_high - cum_low) * r, &mut self.inner)
+    }
+
+    /// Encodes `value` with the frequencies of `model`, then updates it.
+    pub fn encode_update<M: Model + ?Sized>(
+        &mut self,
+        model: &mut M,
+        value: usize,
+    ) -> Result<(), S::Error> {
+        self.encode(model, value)?;
+        model.update(value);
+        Ok(())
+    }
+
+    /// Writes the low end of the interval and the bytes held back, and
+    /// returns the sink.
+    pub fn finish(mut self) -> Result<S, S::Error> {
+        self.state.finish(&mut self.inner)?;
+        Ok(self.inner)
+    }
+}
+
+/// A decoder of the values of a [`DivisionFreeEncoder`], reading bytes
+/// from the [`ByteSource`] `S`, with no division either.
+///
+/// Bytes past the end of the input read as zeros, so the decoder must be
+/// told the number of values, or decode an end-of-stream value.
+#[derive(Debug)]
+pub struct DivisionFreeDecoder<S: ByteSource> {
+    inner: S,
+    state: carry::Decoder,
+}
+
+impl<S: ByteSource> DivisionFreeDecoder<S> {
+    /// Creates a decoder, reading the first five bytes of the input.
+    pub fn new(mut inner: S) -> Result<DivisionFreeDecoder<S>, S::Error> {
+        let state = carry::Decoder::new(&mut inner)?;
+        Ok(DivisionFreeDecoder { inner, state })
+    }
+
+    /// Decodes a value with the frequencies of `table`, which must be the
+    /// table it was encoded with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total frequency of `table` is not a power of two.
+    pub fn decode<M: Model + ?Sized>(&mut self, table: &M) -> Result<usize, S::Error> {
+        let bits = scale_bits(table);
+        let r = self.state.range >> bits;
+        // the quotient of the code by r, but for input that the encoder did
+        // not write, which goes past the total
+        let mut cum = 0;
+        for bit in (0..bits).rev() {
+            let next = cum | 1 << bit;
+            if next * r <= self.state.code {
+                cum = next;
+            }
+        }
+        let value = table.value_at(cum);
+        let (cum_low, cum_high) = table.range(value);
+        self.state
+            .narrow(cum_low * r, (cum_high - cum_low) * r, &mut self.inner)?;
+        Ok(value)
+    }
+
+    /// Decodes a value with the frequencies of `model`, then updates it.
+    pub fn decode_update<M: Model + ?Sized>(&mut self, model: &mut M) -> Result<usize, S::Error> {
+        let value = self.decode(model)?;
+        model.update(value);
+        Ok(value)
+    }
+
+    /// Returns the source.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A static table of the cumulative frequencies `0, c_1, ..., total`,
+    /// which needs no allocator.
+    struct Table<const N: usize>([u32; N]);
+
+    impl<const N: usize> Model for Table<N> {
+        fn total(&self) -> u32 {
+            self.0[N - 1]
+        }
+
+        fn range(&self, value: usize) -> (u32, u32) {
+            (self.0[value], self.0[value + 1])
+        }
+
+        fn value_at(&self, cum: u32) -> usize {
+            self.0.iter().rposition(|&c| c <= cum).unwrap()
+        }
+
+        fn update(&mut self, _value: usize) {}
+    }
+
+    /// A sink into a buffer of fixed size, as on a microcontroller.
+    struct Buffer {
+        bytes: [u8; 64],
+        len: usize,
+    }
+
+    impl Buffer {
+        fn new() -> Buffer {
+            Buffer {
+                bytes: [0; 64],
+                len: 0,
+            }
+        }
+    }
+
+    impl ByteSink for Buffer {
+        type Error = ();
+
+        fn write_byte(&mut self, byte: u8) -> Result<(), ()> {
+            *self.bytes.get_mut(self.len).ok_or(())? = byte;
+            self.len += 1;
+            Ok(())
+        }
+    }
+
+    /// A source of the bytes of a slice.
+    struct Bytes<'a>(&'a [u8]);
+
+    impl ByteSource for Bytes<'_> {
+        type Error = ();
+
+        fn read_byte(&mut self) -> Result<Option<u8>, ()> {
+            let (&byte, rest) = match self.0.split_first() {
+                Some(split) => split,
+                None => return Ok(None),
+            };
+            self.0 = rest;
+            Ok(Some(byte))
+        }
+    }
+
+    #[test]
+    fn fixed_buffer() {
+        let table = Table([0, 10, 11, 11, 16]);
+        let values: [usize; 100] = core::array::from_fn(|i| {
+            table.value_at(((i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 60) as u32)
+        });
+        let mut encoder = DivisionFreeEncoder::new(Buffer::new());
+        for &value in values.iter() {
+            encoder.encode(&table, value).unwrap();
+        }
+        let buffer = encoder.finish().unwrap();
+        let mut decoder = DivisionFreeDecoder::new(Bytes(&buffer.bytes[..buffer.len])).unwrap();
+        for &value in values.iter() {
+            assert_eq!(decoder.decode(&table).unwrap(), value);
+        }
+
+        // the sink errors out once it is full
+        let mut encoder = DivisionFreeEncoder::new(Buffer::new());
+        let full = values
+            .iter()
+            .cycle()
+            .take(1000)
+            .try_for_each(|&value| encoder.encode(&table, value));
+        assert_eq!(full, Err(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "the total frequency 3 is not a power of two")]
+    fn not_a_power_of_two() {
+        let mut encoder = DivisionFreeEncoder::new(Buffer::new());
+        encoder.encode(&Table([0, 1, 3]), 0).unwrap();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+    use crate::arithmetic::{CumulativeFrequencies, MAX_TOTAL};
+    use crate::range_coder::tests::values;
+    use crate::range_coder::SchindlerEncoder;
+    use crate::rans;
+
+    #[test]
+    fn round_trip() {
+        let tables = [
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            CumulativeFrequencies::new(&[1; 256]),
+            CumulativeFrequencies::new(&[1]),
+            rans::normalize(&[1, 0, 1000, 3, 0, 20], 12),
+        ];
+        let values = values(&tables, 20_000);
+        let mut encoder = DivisionFreeEncoder::new(Vec::new());
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        let bytes = encoder.finish().unwrap();
+        let mut decoder = DivisionFreeDecoder::new(&bytes[..]).unwrap();
+        for &(table, value) in values.iter() {
+            assert_eq!(decoder.decode(&tables[table]).unwrap(), value);
+        }
+
+        // the same code as that of Schindler
+        let mut encoder = SchindlerEncoder::new(Vec::new());
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        assert_eq!(encoder.finish().unwrap(), bytes);
+    }
+}
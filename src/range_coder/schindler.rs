@@ -0,0 +1,224 @@
+//! The range coder of Schindler, which delays its bytes until no carry can
+//! reach them.
+
+use std::io;
+
+use super::carry;
+use crate::model::Model;
+
+/// A range encoder writing bytes to `W`, which resolves carries before the
+/// bytes they reach are written.
+///
+/// The low end of the interval has a 33rd bit for the carry out of it. A
+/// byte shifted out of it is held back, with the run of `0xff` bytes after
+/// it which a carry would turn into zeros, until a byte shifted out shows
+/// whether the carry happened. The interval is never cut, so the code is a
+/// little shorter than that of a [`RangeEncoder`], and the bytes written are
+/// final, as a socket or a pipe needs.
+///
+/// [`RangeEncoder`]: super::RangeEncoder
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::arithmetic::CumulativeFrequencies;
+/// # use rust_compress::range_coder::{SchindlerDecoder, SchindlerEncoder};
+/// let table = CumulativeFrequencies::new(&[10, 1, 5]);
+/// let values = [0, 0, 2, 0, 1, 0, 2];
+/// let mut encoder = SchindlerEncoder::new(Vec::new());
+/// for &value in values.iter() {
+///     encoder.encode(&table, value).unwrap();
+/// }
+/// let bytes = encoder.finish().unwrap();
+/// let mut decoder = SchindlerDecoder::new(&bytes[..]).unwrap();
+/// for &value in values.iter() {
+///     assert_eq!(decoder.decode(&table).unwrap(), value);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SchindlerEncoder<W: io::Write> {
+    inner: W,
+    state: carry::Encoder,
+}
+
+impl<W: io::Write> SchindlerEncoder<W> {
+    pub fn new(inner: W) -> SchindlerEncoder<W> {
+        SchindlerEncoder {
+            inner,
+            state: carry::Encoder::new(),
+        }
+    }
+
+    /// Encodes `value` with the frequencies of `table`, leaving it as it
+    /// is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is out of range or has a frequency of zero.
+    pub fn encode<M: Model + ?Sized>(&mut self, table: &M, value: usize) -> io::Result<()> {
+        let (cum_low, cum_high) = table.range(value);
+        assert!(
+            cum_low < cum_high,
+            "value {} has a frequency of zero",
+            value
+        );
+        let r = self.state.range / table.total();
+        self.state
+            .narrow(cum_low * r, (cum_high - cum_low) * r, &mut self.inner)
+    }
+
+    /// Encodes `value` with the frequencies of `model`, then updates it.
+    pub fn encode_update<M: Model + ?Sized>(
+        &mut self,
+        model: &mut M,
+        value: usize,
+    ) -> io::Result<()> {
+        self.encode(model, value)?;
+        model.update(value);
+        Ok(())
+    }
+
+    /// Writes the low end of the interval and the bytes held back, and
+    /// returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.state.finish(&mut self.inner)?;
+        Ok(self.inner)
+    }
+}
+
+/// A decoder of the values of a [`SchindlerEncoder`], reading bytes from
+/// `R`.
+///
+/// Bytes past the end of the input read as zeros, so the decoder must be
+/// told the number of values, or decode an end-of-stream value.
+#[derive(Debug)]
+pub struct SchindlerDecoder<R: io::Read> {
+    inner: R,
+    state: carry::Decoder,
+}
+
+impl<R: io::Read> SchindlerDecoder<R> {
+    /// Creates a decoder, reading the first five bytes of the input.
+    pub fn new(mut inner: R) -> io::Result<SchindlerDecoder<R>> {
+        let state = carry::Decoder::new(&mut inner)?;
+        Ok(SchindlerDecoder { inner, state })
+    }
+
+    /// Decodes a value with the frequencies of `table`, which must be the
+    /// table it was encoded with.
+    pub fn decode<M: Model + ?Sized>(&mut self, table: &M) -> io::Result<usize> {
+        let total = table.total();
+        let r = self.state.range / total;
+        // only input that the encoder did not write goes past the total
+        let cum = (self.state.code / r).min(total - 1);
+        let value = table.value_at(cum);
+        let (cum_low, cum_high) = table.range(value);
+        self.state
+            .narrow(cum_low * r, (cum_high - cum_low) * r, &mut self.inner)?;
+        Ok(value)
+    }
+
+    /// Decodes a value with the frequencies of `model`, then updates it.
+    pub fn decode_update<M: Model + ?Sized>(&mut self, model: &mut M) -> io::Result<usize> {
+        let value = self.decode(model)?;
+        model.update(value);
+        Ok(value)
+    }
+
+    /// Returns the reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::{CumulativeFrequencies, MAX_TOTAL};
+    use crate::model::AdaptiveModel;
+    use crate::range_coder::tests::values;
+    use crate::range_coder::RangeEncoder;
+
+    /// A writer that takes a byte at a time and cannot go back, as a socket.
+    struct Stream(Vec<u8>);
+
+    impl io::Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match buf.first() {
+                Some(&byte) => {
+                    self.0.push(byte);
+                    Ok(1)
+                }
+                None => Ok(0),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let tables = [
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            CumulativeFrequencies::new(&[1; 300]),
+            CumulativeFrequencies::new(&[7]),
+            CumulativeFrequencies::new(&[1, 0, 1000, 3, 0, 20]),
+        ];
+        let values = values(&tables, 20_000);
+        let mut encoder = SchindlerEncoder::new(Stream(Vec::new()));
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        let bytes = encoder.finish().unwrap().0;
+        let mut decoder = SchindlerDecoder::new(&bytes[..]).unwrap();
+        for &(table, value) in values.iter() {
+            assert_eq!(decoder.decode(&tables[table]).unwrap(), value);
+        }
+
+        // no longer than the code of Subbotin, but for the extra byte
+        let mut encoder = RangeEncoder::new(Vec::new());
+        for &(table, value) in values.iter() {
+            encoder.encode(&tables[table], value).unwrap();
+        }
+        let subbotin = encoder.finish().unwrap().len();
+        assert!(bytes.len() <= subbotin + 1, "{} {}", bytes.len(), subbotin);
+    }
+
+    #[test]
+    fn carries() {
+        // runs of halves between values of thirds, which carry into the
+        // bytes held back thousands of times, at times through a 0xff
+        let tables = [
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[1, 1, 1]),
+        ];
+        let values: Vec<(usize, usize)> = (0..2000u64)
+            .flat_map(|i| {
+                let r = (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62) as usize;
+                std::iter::repeat_n((0, 1), 40 + r).chain(std::iter::once((1, r % 3)))
+            })
+            .collect();
+        let mut model = AdaptiveModel::new(2);
+        let mut encoder = SchindlerEncoder::new(Vec::new());
+        for &(table, value) in values.iter() {
+            if table == 0 {
+                encoder.encode(&tables[0], value).unwrap();
+            } else {
+                encoder.encode_update(&mut model, value % 2).unwrap();
+                encoder.encode(&tables[1], value).unwrap();
+            }
+        }
+        let bytes = encoder.finish().unwrap();
+        let mut model = AdaptiveModel::new(2);
+        let mut decoder = SchindlerDecoder::new(&bytes[..]).unwrap();
+        for &(table, value) in values.iter() {
+            if table == 1 {
+                assert_eq!(decoder.decode_update(&mut model).unwrap(), value % 2);
+            }
+            assert_eq!(decoder.decode(&tables[table]).unwrap(), value);
+        }
+    }
+}
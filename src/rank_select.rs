@@ -0,0 +1,222 @@
+//! Constant-time rank and logarithmic-time select over a `BitVec`.
+
+use crate::bit_order::{BitOrder, LsbFirst};
+use crate::bit_vec::{select_in_word, OrderedBitVec};
+
+const BLOCK: usize = 64;
+const BLOCKS_PER_SUPERBLOCK: usize = 8;
+const SUPERBLOCK: usize = BLOCK * BLOCKS_PER_SUPERBLOCK;
+
+/// A precomputed index answering rank queries in O(1) and select queries in
+/// O(log n) over a borrowed bit vector.
+///
+/// The index stores the number of ones before every 512-bit superblock and,
+/// relative to that, before every 64-bit block, which costs 0.375 extra bits
+/// per bit of the vector.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::BitVec;
+/// # use rust_compress::rank_select::RankSelectIndex;
+/// let v: BitVec = (0..1000).map(|i| i % 10 == 0).collect::<Vec<_>>().into();
+/// let index = RankSelectIndex::build(&v);
+/// assert_eq!(index.rank1(501), 51);
+/// assert_eq!(index.select1(51), Some(510));
+/// assert_eq!(index.select0(0), Some(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RankSelectIndex<'a, O: BitOrder = LsbFirst> {
+    bits: &'a OrderedBitVec<O>,
+    superblocks: Vec<u64>,
+    blocks: Vec<u16>,
+    ones: usize,
+}
+
+impl<'a, O: BitOrder> RankSelectIndex<'a, O> {
+    /// Builds the index for `bits`.
+    pub fn build(bits: &'a OrderedBitVec<O>) -> RankSelectIndex<'a, O> {
+        let nblocks = bits.len().div_ceil(BLOCK);
+        let mut superblocks = Vec::with_capacity(nblocks / BLOCKS_PER_SUPERBLOCK + 1);
+        let mut blocks = Vec::with_capacity(nblocks);
+        let mut ones = 0;
+        for block in 0..nblocks {
+            if block % BLOCKS_PER_SUPERBLOCK == 0 {
+                superblocks.push(ones as u64);
+            }
+            let base = *superblocks.last().unwrap() as usize;
+            blocks.push((ones - base) as u16);
+            let start = block * BLOCK;
+            ones += bits
+                .read_bits(start, BLOCK.min(bits.len() - start))
+                .count_ones() as usize;
+        }
+        RankSelectIndex {
+            bits,
+            superblocks,
+            blocks,
+            ones,
+        }
+    }
+
+    /// Returns the indexed bit vector.
+    pub fn bits(&self) -> &'a OrderedBitVec<O> {
+        self.bits
+    }
+
+    /// Returns the number of elements set to `true`.
+    pub fn count_ones(&self) -> usize {
+        self.ones
+    }
+
+    /// Returns the number of elements set to `false`.
+    pub fn count_zeros(&self) -> usize {
+        self.bits.len() - self.ones
+    }
+
+    /// Returns the number of elements set to `true` in `0..index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the vector.
+    pub fn rank1(&self, index: usize) -> usize {
+        assert!(
+            index <= self.bits.len(),
+            "rank index {} out of range for length {}",
+            index,
+            self.bits.len()
+        );
+        if index == self.bits.len() {
+            return self.ones;
+        }
+        let block = index / BLOCK;
+        let start = block * BLOCK;
+        self.superblocks[block / BLOCKS_PER_SUPERBLOCK] as usize
+            + self.blocks[block] as usize
+            + self.bits.read_bits(start, index - start).count_ones() as usize
+    }
+
+    /// Returns the number of elements set to `false` in `0..index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the vector.
+    pub fn rank0(&self, index: usize) -> usize {
+        index - self.rank1(index)
+    }
+
+    /// Returns the position of the `k`-th (counted from zero) element set to
+    /// `true`, or `None` if there are not that many.
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        if k >= self.count_ones() {
+            return None;
+        }
+        Some(self.select(k, true))
+    }
+
+    /// Returns the position of the `k`-th (counted from zero) element set to
+    /// `false`, or `None` if there are not that many.
+    pub fn select0(&self, k: usize) -> Option<usize> {
+        if k >= self.count_zeros() {
+            return None;
+        }
+        Some(self.select(k, false))
+    }
+
+    fn select(&self, k: usize, value: bool) -> usize {
+        let before_superblock = |s: usize| {
+            let ones = self.superblocks[s] as usize;
+            if value {
+                ones
+            } else {
+                s * SUPERBLOCK - ones
+            }
+        };
+        let before_block = |b: usize| {
+            let ones = self.blocks[b] as usize;
+            if value {
+                ones
+            } else {
+                (b % BLOCKS_PER_SUPERBLOCK) * BLOCK - ones
+            }
+        };
+
+        // the last superblock starting with at most `k` matching elements
+        let (mut lo, mut hi) = (0, self.superblocks.len());
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if before_superblock(mid) <= k {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let k = k - before_superblock(lo);
+
+        let first = lo * BLOCKS_PER_SUPERBLOCK;
+        let last = (first + BLOCKS_PER_SUPERBLOCK).min(self.blocks.len());
+        let block = (first + 1..last)
+            .take_while(|&b| before_block(b) <= k)
+            .last()
+            .unwrap_or(first);
+        let k = k - before_block(block);
+
+        let start = block * BLOCK;
+        let n = BLOCK.min(self.bits.len() - start);
+        let word = O::convert_field(self.bits.read_bits(start, n), n);
+        let word = if value { word } else { !word };
+        start + select_in_word(word, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_vec::{BitVec, MsbBitVec};
+
+    fn check<O: BitOrder>(bits: &[bool]) {
+        let v: OrderedBitVec<O> = bits.into();
+        let index = RankSelectIndex::build(&v);
+        let mut ones = 0;
+        for (i, &b) in bits.iter().enumerate() {
+            assert_eq!(index.rank1(i), ones);
+            assert_eq!(index.rank0(i), i - ones);
+            if b {
+                assert_eq!(index.select1(ones), Some(i));
+                ones += 1;
+            } else {
+                assert_eq!(index.select0(i - ones), Some(i));
+            }
+        }
+        assert_eq!(index.rank1(bits.len()), ones);
+        assert_eq!(index.count_ones(), ones);
+        assert_eq!(index.select1(ones), None);
+        assert_eq!(index.select0(bits.len() - ones), None);
+    }
+
+    #[test]
+    fn rank_select() {
+        check::<LsbFirst>(&[]);
+        let bits: Vec<bool> = (0..3000u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61 == 0 || i > 2000)
+            .collect();
+        check::<LsbFirst>(&bits);
+        check::<crate::bit_order::MsbFirst>(&bits);
+        check::<LsbFirst>(&bits[..1537]);
+        check::<LsbFirst>(&vec![true; 1100]);
+        check::<LsbFirst>(&vec![false; 1100]);
+    }
+
+    #[test]
+    fn matches_naive() {
+        let v: BitVec = (0..777).map(|i| i % 5 < 2).collect::<Vec<_>>().into();
+        let m: MsbBitVec = (0..777).map(|i| i % 5 < 2).collect::<Vec<_>>().into();
+        let index = RankSelectIndex::build(&v);
+        for i in 0..=777 {
+            assert_eq!(index.rank1(i), v.rank1(i));
+            assert_eq!(index.rank1(i), m.rank1(i));
+            assert_eq!(index.select1(i), v.select1(i));
+            assert_eq!(index.select0(i), v.select0(i));
+        }
+    }
+}
@@ -0,0 +1,705 @@
+//! Range asymmetric numeral systems, rANS, with the frequency tables of the
+//! arithmetic coder.
+//!
+//! rANS keeps the whole state of the coder in a single integer, into which
+//! every value is pushed by its frequency, and from which it is popped back
+//! by a multiplication and a table lookup, with no interval to narrow.
+//! Values come out of the state in the reverse of the order they went in,
+//! so the encoder takes all of them at once and codes them backwards.
+//!
+//! The state is 32 bits wide and renormalized a byte at a time, as in the
+//! coder of Giesen. The total frequency of a table must be a power of two,
+//! so that the decoder finds the slot of a value without a division.
+//! Finding the value of the slot takes a binary search through a
+//! [`CumulativeFrequencies`] table, and a single lookup in an
+//! [`AliasTable`], which lays out the slots of the values in a way of its
+//! own; both are [`Slots`] that the coder works with.
+//! [`normalize`] scales counts to such a table, which [`write_table`] and
+//! [`read_table`] store in a few bytes, so that a block of [`compress`]
+//! carries its own table.
+//!
+//! Decoding a value needs the state left by the one before it, which
+//! leaves a modern CPU idle most of the time. [`encode_interleaved`] and
+//! [`decode_interleaved`] code the values by turns in `N` independent
+//! states sharing one stream of bytes, which a CPU can then decode side by
+//! side.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rust_compress::arithmetic::CumulativeFrequencies;
+//! # use rust_compress::rans;
+//! let table = CumulativeFrequencies::new(&[10, 1, 5]);
+//! let values = [0, 0, 2, 0, 1, 0, 2];
+//! let bytes = rans::encode(&table, &values);
+//! assert_eq!(rans::decode(&table, &bytes, values.len()).unwrap(), values);
+//!
+//! let bytes = rans::encode_interleaved::<4>(&table, &values);
+//! assert_eq!(
+//!     rans::decode_interleaved::<4>(&table, &bytes, values.len()).unwrap(),
+//!     values
+//! );
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::arithmetic::{CumulativeFrequencies, MAX_TOTAL};
+use crate::varint;
+
+mod alias;
+
+pub use self::alias::AliasTable;
+
+/// The largest base 2 logarithm of the total frequency of a table.
+pub const MAX_SCALE_BITS: u32 = MAX_TOTAL.trailing_zeros();
+
+/// The base 2 logarithm of the total frequency of the tables of
+/// `compress`.
+const COMPRESS_SCALE_BITS: u32 = 14;
+
+/// The lower bound of a normalized state, which is in `[LOW, LOW << 8)`
+/// between values.
+const LOW: u32 = 1 << 23;
+
+/// A table of frequencies as rANS sees it: every value owns as many of the
+/// `2^scale_bits` slots of the state as its frequency, and is coded by the
+/// rank of one of them among its own.
+///
+/// A [`CumulativeFrequencies`] table gives every value a run of slots, and
+/// finds the value of a slot by a binary search. An [`AliasTable`] spreads
+/// them out so that it finds the value in constant time. The two lay out
+/// the slots differently, so a stream must be decoded with the kind of
+/// table it was encoded with.
+pub trait Slots {
+    /// Returns the base 2 logarithm of the number of slots.
+    fn scale_bits(&self) -> u32;
+
+    /// Returns the frequency of `value`, its number of slots.
+    fn frequency(&self, value: usize) -> u32;
+
+    /// Returns the slot of `value` of the rank `rank`, which is less than
+    /// its frequency.
+    fn slot(&self, value: usize, rank: u32) -> u32;
+
+    /// Returns the value owning `slot` and the rank of the slot among its
+    /// own.
+    fn value_of(&self, slot: u32) -> (usize, u32);
+}
+
+/// The slots of the values in turn, each value owning a run of them.
+impl Slots for CumulativeFrequencies {
+    /// # Panics
+    ///
+    /// Panics if the total frequency is not a power of two.
+    fn scale_bits(&self) -> u32 {
+        let total = self.total();
+        assert!(
+            total.is_power_of_two(),
+            "the total frequency {} is not a power of two",
+            total
+        );
+        total.trailing_zeros()
+    }
+
+    fn frequency(&self, value: usize) -> u32 {
+        CumulativeFrequencies::frequency(self, value)
+    }
+
+    fn slot(&self, value: usize, rank: u32) -> u32 {
+        self.range(value).0 + rank
+    }
+
+    fn value_of(&self, slot: u32) -> (usize, u32) {
+        let value = self.value_at(slot);
+        (value, slot - self.range(value).0)
+    }
+}
+
+/// Encodes `values` with the frequencies of `table` in a single state.
+///
+/// # Panics
+///
+/// Panics if the total frequency of `table` is not a power of two, or if a
+/// value is out of range or has a frequency of zero.
+pub fn encode(table: &(impl Slots + ?Sized), values: &[usize]) -> Vec<u8> {
+    encode_interleaved::<1>(table, values)
+}
+
+/// Decodes `len` values encoded by [`encode`] with `table`.
+///
+/// # Errors
+///
+/// Fails with `UnexpectedEof` if the input is too short, and with
+/// `InvalidData` if it is not the code of `len` values.
+///
+/// # Panics
+///
+/// Panics if the total frequency of `table` is not a power of two.
+pub fn decode(table: &(impl Slots + ?Sized), input: &[u8], len: usize) -> io::Result<Vec<usize>> {
+    decode_interleaved::<1>(table, input, len)
+}
+
+/// Encodes `values` with the frequencies of `table` in `N` states, the
+/// `i`th value in the state `i % N`.
+///
+/// The stream begins with the final states, then the bytes renormalized
+/// out of them in the order the decoder takes them in.
+///
+/// # Panics
+///
+/// Panics if `N` is 0, if the total frequency of `table` is not a power of
+/// two, or if a value is out of range or has a frequency of zero.
+pub fn encode_interleaved<const N: usize>(
+    table: &(impl Slots + ?Sized),
+    values: &[usize],
+) -> Vec<u8> {
+    assert!(N > 0, "there must be at least one state");
+    let bits = table.scale_bits();
+    let mut states = [LOW; N];
+    // the bytes in reverse order
+    let mut out = Vec::with_capacity(values.len() / 2 + 4 * N);
+    for (i, &value) in values.iter().enumerate().rev() {
+        let freq = table.frequency(value);
+        assert!(freq > 0, "value {} has a frequency of zero", value);
+        let x = &mut states[i % N];
+        // the largest state from which the value can be pushed without
+        // leaving the normalized range
+        let max = ((LOW >> bits) << 8) * freq;
+        while *x >= max {
+            out.push(*x as u8);
+            *x >>= 8;
+        }
+        *x = ((*x / freq) << bits) + table.slot(value, *x % freq);
+    }
+    for x in states.iter().rev() {
+        out.extend_from_slice(&x.to_le_bytes());
+    }
+    out.reverse();
+    out
+}
+
+/// Decodes `len` values encoded by [`encode_interleaved`] with `table` and
+/// the same `N`.
+///
+/// # Errors
+///
+/// Fails with `UnexpectedEof` if the input is too short, and with
+/// `InvalidData` if it is not the code of `len` values.
+///
+/// # Panics
+///
+/// Panics if `N` is 0 or if the total frequency of `table` is not a power
+/// of two.
+pub fn decode_interleaved<const N: usize>(
+    table: &(impl Slots + ?Sized),
+    input: &[u8],
+    len: usize,
+) -> io::Result<Vec<usize>> {
+    assert!(N > 0, "there must be at least one state");
+    let bits = table.scale_bits();
+    let mask = (1 << bits) - 1;
+    let mut bytes = input.iter();
+    let mut next = || {
+        bytes
+            .next()
+            .copied()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated rANS stream"))
+    };
+    let mut states = [0; N];
+    for x in states.iter_mut() {
+        for _ in 0..4 {
+            *x = (*x << 8) | u32::from(next()?);
+        }
+    }
+    // values may take much less than a byte each, so the memory is only
+    // reserved for a few of them ahead
+    let mut values = Vec::with_capacity(len.min(64 * input.len()));
+    // rounds of every state in turn, which go through the array of states
+    // in a loop of a constant length, then the states left over
+    for round in 0..=len / N {
+        let n = if round < len / N { N } else { len % N };
+        for x in states[..n].iter_mut() {
+            let slot = *x & mask;
+            let (value, rank) = table.value_of(slot);
+            *x = table.frequency(value) * (*x >> bits) + rank;
+            while *x < LOW {
+                *x = (*x << 8) | u32::from(next()?);
+            }
+            values.push(value);
+        }
+    }
+    // the encoder started every state at the lower bound
+    if states.iter().any(|&x| x != LOW) || next().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid rANS stream",
+        ));
+    }
+    Ok(values)
+}
+
+/// Normalizes `counts` to a table of a total frequency of `2^scale_bits`,
+/// as rANS needs.
+///
+/// The normalization is fully specified, so that it can be done again
+/// elsewhere:
+///
+/// 1. A count of zero has a frequency of zero, and every other count `c`
+///    the frequency `max(1, c * 2^scale_bits / total)`, rounded down.
+/// 2. If the frequencies add up to less than `2^scale_bits`, the rest is
+///    added to the value of the largest count.
+/// 3. If they add up to more, one is taken from the largest frequency, as
+///    long as it is greater than one, until they add up to `2^scale_bits`.
+///
+/// Of values of the same count or frequency, the first is chosen.
+///
+/// # Panics
+///
+/// Panics if `scale_bits` is greater than `MAX_SCALE_BITS`, if all counts
+/// are zero, or if more than `2^scale_bits` of them are not.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::rans;
+/// let table = rans::normalize(&[5, 0, 1, 10], 4);
+/// assert_eq!(table.total(), 16);
+/// let frequencies: Vec<u32> = (0..4).map(|v| table.frequency(v)).collect();
+/// assert_eq!(frequencies, [5, 0, 1, 10]);
+/// ```
+pub fn normalize(counts: &[u64], scale_bits: u32) -> CumulativeFrequencies {
+    assert!(
+        scale_bits <= MAX_SCALE_BITS,
+        "scale of {} bits greater than {}",
+        scale_bits,
+        MAX_SCALE_BITS
+    );
+    let target = 1u64 << scale_bits;
+    let total: u128 = counts.iter().map(|&count| u128::from(count)).sum();
+    assert!(total > 0, "all counts are zero");
+    let used = counts.iter().filter(|&&count| count > 0).count() as u64;
+    assert!(
+        used <= target,
+        "{} values do not fit in a total of {}",
+        used,
+        target
+    );
+    let mut frequencies: Vec<u64> = counts
+        .iter()
+        .map(|&count| match count {
+            0 => 0,
+            _ => ((u128::from(count) * u128::from(target) / total) as u64).max(1),
+        })
+        .collect();
+    let mut sum: u64 = frequencies.iter().sum();
+    if sum < target {
+        // the first of the largest counts
+        let largest = (0..counts.len())
+            .rev()
+            .max_by_key(|&value| counts[value])
+            .unwrap();
+        frequencies[largest] += target - sum;
+    }
+    while sum > target {
+        let largest = (0..counts.len())
+            .rev()
+            .max_by_key(|&value| frequencies[value])
+            .unwrap();
+        frequencies[largest] -= 1;
+        sum -= 1;
+    }
+    let frequencies: Vec<u32> = frequencies.iter().map(|&f| f as u32).collect();
+    CumulativeFrequencies::new(&frequencies)
+}
+
+/// Appends a compact description of `table` to `out`, which `read_table`
+/// reads back.
+///
+/// It is the number of values as a varint, the base 2 logarithm of the
+/// total frequency as a byte, then every frequency as a varint, each run of
+/// zeros written as a single zero followed by the length of the run less
+/// one as a varint.
+///
+/// # Panics
+///
+/// Panics if the total frequency of `table` is not a power of two.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::arithmetic::CumulativeFrequencies;
+/// # use rust_compress::rans;
+/// let mut frequencies = vec![0; 256];
+/// frequencies[b'a' as usize] = 600;
+/// frequencies[b'b' as usize] = 424;
+/// let table = CumulativeFrequencies::new(&frequencies);
+/// let mut out = Vec::new();
+/// rans::write_table(&table, &mut out);
+/// assert!(out.len() < 16);
+/// assert_eq!(rans::read_table(&out, 256).unwrap(), (table, out.len()));
+/// ```
+pub fn write_table(table: &CumulativeFrequencies, out: &mut Vec<u8>) {
+    let bits = table.scale_bits();
+    varint::encode_u64(table.len() as u64, out);
+    out.push(bits as u8);
+    let mut value = 0;
+    while value < table.len() {
+        let frequency = table.frequency(value);
+        varint::encode_u64(u64::from(frequency), out);
+        value += 1;
+        if frequency == 0 {
+            let run = (value..table.len())
+                .take_while(|&v| table.frequency(v) == 0)
+                .count();
+            varint::encode_u64(run as u64, out);
+            value += run;
+        }
+    }
+}
+
+/// Reads a table of at most `max_len` values written by `write_table` from
+/// the start of `input`, returning it with the number of bytes it took.
+///
+/// # Errors
+///
+/// Fails with `UnexpectedEof` if the input ends in the middle of the table,
+/// and with `InvalidData` if it has more than `max_len` values, if the
+/// frequencies do not add up to the power of two they claim, or if there
+/// are more of them than said.
+pub fn read_table(input: &[u8], max_len: usize) -> io::Result<(CumulativeFrequencies, usize)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let (len, mut pos) = varint::decode_u64(input)?;
+    // a run of zeros takes a few bytes for any length, so the length must
+    // be bounded before the table is filled
+    if len > max_len as u64 {
+        return Err(invalid("too many values in the table"));
+    }
+    let bits = u32::from(*input.get(pos).ok_or(io::ErrorKind::UnexpectedEof)?);
+    pos += 1;
+    if bits > MAX_SCALE_BITS {
+        return Err(invalid("total frequency out of range"));
+    }
+    let mut frequencies = Vec::with_capacity(len.min(MAX_TOTAL as u64) as usize);
+    let mut total: u64 = 0;
+    while (frequencies.len() as u64) < len {
+        let (frequency, used) = varint::decode_u64(&input[pos..])?;
+        pos += used;
+        total += frequency;
+        if total > 1 << bits {
+            return Err(invalid("frequencies beyond the total"));
+        }
+        frequencies.push(frequency as u32);
+        if frequency == 0 {
+            let (run, used) = varint::decode_u64(&input[pos..])?;
+            pos += used;
+            if run > len - frequencies.len() as u64 {
+                return Err(invalid("more frequencies than values"));
+            }
+            frequencies.resize(frequencies.len() + run as usize, 0);
+        }
+    }
+    if total != 1 << bits {
+        return Err(invalid("frequencies short of the total"));
+    }
+    Ok((CumulativeFrequencies::new(&frequencies), pos))
+}
+
+/// Compresses `data` into a self-describing stream that `decompress` reads
+/// back.
+///
+/// The stream starts with the length of `data` as a varint, followed by
+/// the table of the bytes of `data` normalized to a total of `2^14` as
+/// `write_table` writes it, and by the code of `encode_interleaved` in four
+/// states with the [`AliasTable`] of it.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::rans;
+/// let data = b"abracadabra, abracadabra, abracadabra";
+/// let compressed = rans::compress(data);
+/// assert_eq!(rans::decompress(&compressed).unwrap(), &data[..]);
+/// ```
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    varint::encode_u64(data.len() as u64, &mut out);
+    if data.is_empty() {
+        return out;
+    }
+    let mut counts = [0; 256];
+    for &byte in data.iter() {
+        counts[byte as usize] += 1;
+    }
+    let table = normalize(&counts, COMPRESS_SCALE_BITS);
+    write_table(&table, &mut out);
+    let table = AliasTable::new(&table);
+    let values: Vec<usize> = data.iter().map(|&byte| byte as usize).collect();
+    out.extend(encode_interleaved::<4>(&table, &values));
+    out
+}
+
+/// Decompresses a stream made by `compress`.
+///
+/// Fails with `ErrorKind::UnexpectedEof` if the stream is cut short, and
+/// with `ErrorKind::InvalidData` if it is malformed, has data left after
+/// the end, or claims more values than its code can hold. Values of a
+/// table of a single value take no bits, so their number is bounded by the
+/// memory only: the output is reserved at once, and fails with
+/// `ErrorKind::OutOfMemory` if it cannot be.
+pub fn decompress(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let (len, mut pos) = varint::decode_u64(compressed)?;
+    if len == 0 {
+        if pos < compressed.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing data after the compressed stream",
+            ));
+        }
+        return Ok(Vec::new());
+    }
+    let (table, used) = read_table(&compressed[pos..], 256)?;
+    pos += used;
+    let code = &compressed[pos..];
+    let len = usize::try_from(len).map_err(|_| too_long())?;
+    let likeliest = (0..table.len())
+        .max_by_key(|&value| table.frequency(value))
+        .unwrap();
+    let frequency = table.frequency(likeliest);
+    let total = table.total();
+    let table = AliasTable::new(&table);
+    if frequency == total {
+        // a single value takes no bits, so any length is the code of as
+        // many, which only the memory bounds
+        decode_interleaved::<4>(&table, code, 0)?;
+        let mut data = Vec::new();
+        data.try_reserve_exact(len).map_err(|_| too_long())?;
+        data.resize(len, likeliest as u8);
+        return Ok(data);
+    }
+    // a value takes at least the bits of the likeliest one but for the
+    // rounding of the states, which takes less than half of them
+    let bits = (f64::from(total) / f64::from(frequency)).log2();
+    if len as f64 * bits / 2.0 > 8.0 * code.len() as f64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "more values than the stream holds",
+        ));
+    }
+    let values = decode_interleaved::<4>(&table, code, len)?;
+    Ok(values.iter().map(|&value| value as u8).collect())
+}
+
+fn too_long() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::OutOfMemory,
+        "too many values to hold in memory",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::MAX_TOTAL;
+
+    fn values(table: &CumulativeFrequencies, n: u64) -> Vec<usize> {
+        (0..n)
+            .map(|i| {
+                let r = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 40;
+                let cum = (r % u64::from(table.total())) as u32;
+                table.value_at(cum)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let tables = [
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            CumulativeFrequencies::new(&[1; 256]),
+            CumulativeFrequencies::new(&[1]),
+            CumulativeFrequencies::new(&[1, 0, 1000, 3, 0, 20]),
+        ];
+        for table in tables.iter() {
+            for &n in [0, 1, 2, 3, 5, 1000].iter() {
+                let values = values(table, n);
+                let bytes = encode(table, &values);
+                assert_eq!(decode(table, &bytes, values.len()).unwrap(), values);
+                let bytes = encode_interleaved::<2>(table, &values);
+                assert_eq!(
+                    decode_interleaved::<2>(table, &bytes, values.len()).unwrap(),
+                    values
+                );
+                let bytes = encode_interleaved::<3>(table, &values);
+                assert_eq!(
+                    decode_interleaved::<3>(table, &bytes, values.len()).unwrap(),
+                    values
+                );
+                let bytes = encode_interleaved::<4>(table, &values);
+                assert_eq!(
+                    decode_interleaved::<4>(table, &bytes, values.len()).unwrap(),
+                    values
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn size() {
+        // close to the entropy, plus the final states
+        let table = CumulativeFrequencies::new(&[200, 40, 10, 6]);
+        let values = values(&table, 10_000);
+        let entropy: f64 = values
+            .iter()
+            .map(|&v| -(f64::from(table.frequency(v)) / 256.0).log2())
+            .sum();
+        for &(len, states) in [
+            (encode(&table, &values).len(), 1),
+            (encode_interleaved::<4>(&table, &values).len(), 4),
+        ]
+        .iter()
+        {
+            assert!(((len - 4 * states) * 8) as f64 <= entropy * 1.002 + 8.0);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let table = CumulativeFrequencies::new(&[3, 1, 4]);
+        let values = values(&table, 100);
+        let bytes = encode_interleaved::<2>(&table, &values);
+        let err = decode_interleaved::<2>(&table, &bytes[..bytes.len() - 1], 100).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = decode_interleaved::<2>(&table, &bytes, 99).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let err = decode_interleaved::<2>(&table, &trailing, 100).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn normalization() {
+        let table = normalize(&[1, 1, 1], 2);
+        assert_eq!(table, CumulativeFrequencies::new(&[2, 1, 1]));
+        // small counts are raised to one, taken from the largest
+        let table = normalize(&[1, 0, 1000, 1, 998], 4);
+        assert_eq!(table, CumulativeFrequencies::new(&[1, 0, 7, 1, 7]));
+        let table = normalize(&[1 << 40, 3], MAX_SCALE_BITS);
+        assert_eq!(table, CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]));
+        let table = normalize(&[0, 5], 0);
+        assert_eq!(table, CumulativeFrequencies::new(&[0, 1]));
+        for n in 1..300u64 {
+            let counts: Vec<u64> = (0..n).map(|i| i * i % 17).collect();
+            if counts.iter().all(|&c| c == 0) {
+                continue;
+            }
+            let table = normalize(&counts, 12);
+            assert_eq!(table.total(), 1 << 12);
+            for (value, &count) in counts.iter().enumerate() {
+                assert_eq!(table.frequency(value) == 0, count == 0);
+            }
+        }
+    }
+
+    #[test]
+    fn tables() {
+        let tables = [
+            CumulativeFrequencies::new(&[1]),
+            CumulativeFrequencies::new(&[0, 0, 1, 0]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            normalize(&[0, 5, 0, 0, 0, 300, 0, 7], 10),
+        ];
+        for table in tables.iter() {
+            let mut out = vec![0xaa];
+            write_table(table, &mut out);
+            out.push(0xbb);
+            let (read, used) = read_table(&out[1..], table.len()).unwrap();
+            assert_eq!(&read, table);
+            assert_eq!(used, out.len() - 2);
+            let err = read_table(&out[1..out.len() - 2], table.len()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+        let invalid: [&[u8]; 5] = [
+            // frequencies of 1 and 2 in a total of 2
+            &[2, 1, 1, 2],
+            // a single frequency of 1 in a total of 2
+            &[2, 1, 1, 0, 0],
+            // a run of zeros past the end
+            &[2, 0, 0, 1, 1],
+            // a total of 2^17
+            &[1, 17, 0x80, 0x80, 0x08],
+            // no values
+            &[0, 0],
+        ];
+        for input in invalid.iter() {
+            let err = read_table(input, 16).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", input);
+        }
+        // 2^40 values in a single run of zeros, which must not be allocated
+        let huge = [
+            128, 128, 128, 128, 128, 32, 1, 0, 255, 255, 255, 255, 255, 31,
+        ];
+        let err = read_table(&huge, 256).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = read_table(&[2, 1, 1, 1], 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut compressed = vec![1];
+        compressed.extend_from_slice(&huge);
+        let err = decompress(&compressed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // 2^62 values of a table of a single value, of no bits, more than
+        // any memory holds
+        let mut single = Vec::new();
+        varint::encode_u64(1 << 62, &mut single);
+        single.extend_from_slice(&[1, 14, 0x80, 0x80, 0x01]);
+        let states = &LOW.to_be_bytes();
+        for _ in 0..4 {
+            single.extend_from_slice(states);
+        }
+        let err = decompress(&single).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+        // and 2^40 values of two, from 16 bytes
+        let mut two = Vec::new();
+        varint::encode_u64(1 << 40, &mut two);
+        two.extend_from_slice(&[2, 14, 0xff, 0x7f, 1]);
+        two.extend_from_slice(&single[single.len() - 16..]);
+        let err = decompress(&two).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            vec![7],
+            vec![0; 1000],
+            (0..=255).collect(),
+            b"mississippi river".to_vec(),
+            (0..5000u64)
+                .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58) as u8)
+                .collect(),
+        ];
+        for data in inputs.iter() {
+            let compressed = compress(data);
+            assert_eq!(&decompress(&compressed).unwrap(), data);
+        }
+        let compressed = compress(b"abracadabra");
+        let mut trailing = compressed.clone();
+        trailing.push(0);
+        let err = decompress(&trailing).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = decompress(&compressed[..compressed.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = decompress(&[0, 0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[should_panic(expected = "the total frequency 6 is not a power of two")]
+    fn not_a_power_of_two() {
+        encode(&CumulativeFrequencies::new(&[1, 2, 3]), &[0]);
+    }
+}
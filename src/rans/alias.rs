@@ -0,0 +1,233 @@
+//! Alias tables, which find the value of a slot of rANS in constant time.
+
+use super::Slots;
+use crate::arithmetic::CumulativeFrequencies;
+
+/// A range of slots shared by at most two values: the first `divider` of
+/// them belong to `primary`, the rest to `alias`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Bucket {
+    divider: u32,
+    primary: usize,
+    alias: usize,
+    /// The differences between a slot and its rank, for either value,
+    /// wrapping around.
+    primary_bias: u32,
+    alias_bias: u32,
+}
+
+/// The frequencies of a [`CumulativeFrequencies`] table with the slots laid
+/// out by the alias method of Walker.
+///
+/// The slots are split into buckets of the same size, a power of two, as
+/// many as the values of nonzero frequency rounded up to a power of two.
+/// Every bucket is shared by at most two values, so that the value of a
+/// slot is found from its bucket and a single comparison, however many
+/// values there are. The ranks of the slots of a value go up with the
+/// slots. The encoder finds the slot of a rank in a table of all slots.
+///
+/// The buckets are filled by the method of Vose. Every value of nonzero
+/// frequency starts with a bucket of its own, in order, and the buckets
+/// left over with none. The bucket of a value of less than a bucket is
+/// filled up by the first value with at least a bucket still to place,
+/// which, if it is then left with less, has its own bucket filled next.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::arithmetic::CumulativeFrequencies;
+/// # use rust_compress::rans::{self, AliasTable};
+/// let table = AliasTable::new(&CumulativeFrequencies::new(&[10, 1, 5]));
+/// let values = [0, 0, 2, 0, 1, 0, 2];
+/// let bytes = rans::encode(&table, &values);
+/// assert_eq!(rans::decode(&table, &bytes, values.len()).unwrap(), values);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AliasTable {
+    scale_bits: u32,
+    /// The base 2 logarithm of the number of slots of a bucket.
+    bucket_bits: u32,
+    buckets: Vec<Bucket>,
+    frequencies: Vec<u32>,
+    /// The slots of every value by rank, the values in turn, and the index
+    /// of the first slot of every value in it.
+    slots: Vec<u32>,
+    starts: Vec<u32>,
+}
+
+impl AliasTable {
+    /// Creates the alias table of `table`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total frequency of `table` is not a power of two.
+    pub fn new(table: &CumulativeFrequencies) -> AliasTable {
+        let scale_bits = table.scale_bits();
+        let frequencies: Vec<u32> = (0..table.len()).map(|v| table.frequency(v)).collect();
+        let used: Vec<usize> = (0..table.len())
+            .filter(|&value| frequencies[value] > 0)
+            .collect();
+        // there are no more values of nonzero frequency than slots, which
+        // are a power of two
+        let bucket_bits = scale_bits - used.len().next_power_of_two().trailing_zeros();
+        let size = 1 << bucket_bits;
+        let mut buckets: Vec<Bucket> = (0..1 << (scale_bits - bucket_bits))
+            .map(|b| Bucket {
+                divider: 0,
+                primary: used.get(b).copied().unwrap_or(0),
+                alias: 0,
+                primary_bias: 0,
+                alias_bias: 0,
+            })
+            .collect();
+
+        // the buckets and the part of a bucket left to fill of their values
+        let mut weights: Vec<u32> = (0..buckets.len())
+            .map(|b| used.get(b).map_or(0, |&value| frequencies[value]))
+            .collect();
+        let mut small: Vec<usize> = (0..buckets.len()).filter(|&b| weights[b] < size).collect();
+        let mut large: Vec<usize> = (0..buckets.len()).filter(|&b| weights[b] >= size).collect();
+        small.reverse();
+        large.reverse();
+        while let (Some(s), Some(&l)) = (small.pop(), large.last()) {
+            buckets[s].divider = weights[s];
+            buckets[s].alias = buckets[l].primary;
+            weights[l] -= size - weights[s];
+            if weights[l] < size {
+                large.pop();
+                small.push(l);
+            }
+        }
+        // the weights add up to the size of all buckets, so those left are
+        // all exactly full
+        for &l in large.iter() {
+            buckets[l].divider = size;
+            buckets[l].alias = buckets[l].primary;
+        }
+
+        let mut assigned = vec![0u32; frequencies.len()];
+        for (b, bucket) in buckets.iter_mut().enumerate() {
+            let first = (b as u32) << bucket_bits;
+            bucket.primary_bias = first.wrapping_sub(assigned[bucket.primary]);
+            assigned[bucket.primary] += bucket.divider;
+            bucket.alias_bias = (first + bucket.divider).wrapping_sub(assigned[bucket.alias]);
+            assigned[bucket.alias] += size - bucket.divider;
+        }
+
+        let starts: Vec<u32> = (0..table.len()).map(|v| table.range(v).0).collect();
+        let mut alias = AliasTable {
+            scale_bits,
+            bucket_bits,
+            buckets,
+            frequencies,
+            slots: vec![0; 1 << scale_bits],
+            starts,
+        };
+        for slot in 0..1 << scale_bits {
+            let (value, rank) = alias.value_of(slot);
+            alias.slots[(alias.starts[value] + rank) as usize] = slot;
+        }
+        alias
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.frequencies.len()
+    }
+
+    /// Returns `true` if there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Slots for AliasTable {
+    fn scale_bits(&self) -> u32 {
+        self.scale_bits
+    }
+
+    fn frequency(&self, value: usize) -> u32 {
+        self.frequencies[value]
+    }
+
+    fn slot(&self, value: usize, rank: u32) -> u32 {
+        self.slots[(self.starts[value] + rank) as usize]
+    }
+
+    fn value_of(&self, slot: u32) -> (usize, u32) {
+        let bucket = &self.buckets[(slot >> self.bucket_bits) as usize];
+        if slot & ((1 << self.bucket_bits) - 1) < bucket.divider {
+            (bucket.primary, slot.wrapping_sub(bucket.primary_bias))
+        } else {
+            (bucket.alias, slot.wrapping_sub(bucket.alias_bias))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arithmetic::MAX_TOTAL;
+    use crate::rans::{self, decode_interleaved, encode_interleaved};
+
+    fn tables() -> Vec<CumulativeFrequencies> {
+        let mut sparse = vec![0; 256];
+        sparse[3] = 5;
+        sparse[200] = 3;
+        vec![
+            CumulativeFrequencies::new(&[1]),
+            CumulativeFrequencies::new(&[0, 1, 0]),
+            CumulativeFrequencies::new(&[1, 1]),
+            CumulativeFrequencies::new(&[MAX_TOTAL - 1, 1]),
+            CumulativeFrequencies::new(&[1; 256]),
+            CumulativeFrequencies::new(&sparse),
+            rans::normalize(&[1, 0, 1000, 3, 0, 20, 7], 12),
+            rans::normalize(&(0..300).map(|i| i * i % 37).collect::<Vec<u64>>(), 16),
+        ]
+    }
+
+    #[test]
+    fn slots() {
+        for table in tables().iter() {
+            let alias = AliasTable::new(table);
+            assert_eq!(alias.len(), table.len());
+            let mut seen = vec![Vec::new(); table.len()];
+            for slot in 0..table.total() {
+                let (value, rank) = alias.value_of(slot);
+                assert!(rank < table.frequency(value), "{} {}", slot, value);
+                assert_eq!(alias.slot(value, rank), slot);
+                seen[value].push(rank);
+            }
+            for (value, ranks) in seen.iter().enumerate() {
+                let expected: Vec<u32> = (0..table.frequency(value)).collect();
+                assert_eq!(ranks, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for table in tables().iter() {
+            let alias = AliasTable::new(table);
+            let values: Vec<usize> = (0..1000u64)
+                .map(|i| {
+                    let r = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 40;
+                    table.value_at((r % u64::from(table.total())) as u32)
+                })
+                .collect();
+            let bytes = encode_interleaved::<4>(&alias, &values);
+            assert_eq!(
+                decode_interleaved::<4>(&alias, &bytes, values.len()).unwrap(),
+                values
+            );
+            // the same cost as the runs of slots of the table
+            assert_eq!(bytes.len(), encode_interleaved::<4>(table, &values).len());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the total frequency 3 is not a power of two")]
+    fn not_a_power_of_two() {
+        AliasTable::new(&CumulativeFrequencies::new(&[1, 2]));
+    }
+}
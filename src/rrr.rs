@@ -0,0 +1,348 @@
+//! A compressed bit vector in the style of Raman, Raman and Rao (RRR)
+//! answering rank and select queries without decompressing.
+
+use std::marker::PhantomData;
+
+use crate::bit_order::{BitOrder, LsbFirst};
+use crate::bit_vec::{select_in_word, OrderedBitVec};
+use crate::packed_int_vec::PackedIntVec;
+
+const BLOCK: usize = 63;
+const BLOCKS_PER_SUPERBLOCK: usize = 32;
+const CLASS_BITS: usize = 6;
+
+const fn binomial_table() -> [[u64; BLOCK + 1]; BLOCK + 1] {
+    let mut table = [[0; BLOCK + 1]; BLOCK + 1];
+    let mut n = 0;
+    while n <= BLOCK {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+/// `BINOMIAL[n][k]` is `n` choose `k`.
+static BINOMIAL: [[u64; BLOCK + 1]; BLOCK + 1] = binomial_table();
+
+/// Returns the number of bits of the offset of a block with `class` ones.
+fn offset_bits(class: usize) -> usize {
+    64 - (BINOMIAL[BLOCK][class] - 1).leading_zeros() as usize
+}
+
+/// Returns the rank of `word` among the blocks with the same number of ones,
+/// in the combinatorial number system.
+fn encode_block(mut word: u64) -> u64 {
+    let mut offset = 0;
+    let mut i = 1;
+    while word != 0 {
+        offset += BINOMIAL[word.trailing_zeros() as usize][i];
+        word &= word - 1;
+        i += 1;
+    }
+    offset
+}
+
+/// The inverse of `encode_block`.
+fn decode_block(class: usize, mut offset: u64) -> u64 {
+    let mut word = 0;
+    let mut pos = BLOCK;
+    for i in (1..=class).rev() {
+        pos -= 1;
+        while BINOMIAL[pos][i] > offset {
+            pos -= 1;
+        }
+        offset -= BINOMIAL[pos][i];
+        word |= 1 << pos;
+    }
+    word
+}
+
+/// A compressed, immutable bit vector with rank and select support.
+///
+/// The bits are split into 63-bit blocks, each stored as its number of ones
+/// (its class) followed by its index among all blocks of that class. Blocks
+/// that are all zeros or all ones take only the 6 bits of the class, so
+/// sparse or skewed vectors shrink well below one bit per element.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::BitVec;
+/// # use rust_compress::rrr::RrrBitVec;
+/// let v: BitVec = (0..10000).map(|i| i % 1000 == 7).collect();
+/// let rrr = RrrBitVec::new(&v);
+/// assert!(rrr.size_in_bits() < v.len() / 5);
+/// assert_eq!(rrr.get(2007), Some(true));
+/// assert_eq!(rrr.rank1(5000), 5);
+/// assert_eq!(rrr.select1(3), Some(3007));
+/// assert_eq!(rrr.to_bit_vec(), v);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RrrBitVec<O: BitOrder = LsbFirst> {
+    len: usize,
+    ones: usize,
+    classes: PackedIntVec,
+    offsets: OrderedBitVec<LsbFirst>,
+    /// The number of ones before every superblock.
+    superblock_ones: Vec<u64>,
+    /// The position in `offsets` of the first offset of every superblock.
+    superblock_offsets: Vec<u64>,
+    order: PhantomData<O>,
+}
+
+impl<O: BitOrder> RrrBitVec<O> {
+    /// Compresses `bits`.
+    pub fn new(bits: &OrderedBitVec<O>) -> RrrBitVec<O> {
+        let nblocks = bits.len().div_ceil(BLOCK);
+        let mut classes = PackedIntVec::with_capacity(CLASS_BITS, nblocks);
+        let mut offsets = OrderedBitVec::new();
+        let mut superblock_ones = Vec::with_capacity(nblocks / BLOCKS_PER_SUPERBLOCK + 1);
+        let mut superblock_offsets = Vec::with_capacity(nblocks / BLOCKS_PER_SUPERBLOCK + 1);
+        let mut ones = 0;
+        for block in 0..nblocks {
+            if block % BLOCKS_PER_SUPERBLOCK == 0 {
+                superblock_ones.push(ones as u64);
+                superblock_offsets.push(offsets.len() as u64);
+            }
+            let start = block * BLOCK;
+            let n = BLOCK.min(bits.len() - start);
+            let word = O::convert_field(bits.read_bits(start, n), n);
+            let class = word.count_ones() as usize;
+            classes.push(class as u64);
+            offsets.push_bits(encode_block(word), offset_bits(class));
+            ones += class;
+        }
+        RrrBitVec {
+            len: bits.len(),
+            ones,
+            classes,
+            offsets,
+            superblock_ones,
+            superblock_offsets,
+            order: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements set to `true`.
+    pub fn count_ones(&self) -> usize {
+        self.ones
+    }
+
+    /// Returns the number of elements set to `false`.
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.ones
+    }
+
+    /// Returns the size of the compressed representation in bits, including
+    /// the rank and select samples.
+    pub fn size_in_bits(&self) -> usize {
+        self.classes.as_bit_vec().len()
+            + self.offsets.len()
+            + 64 * (self.superblock_ones.len() + self.superblock_offsets.len())
+    }
+
+    /// Returns the element at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        let (_, word) = self.locate(index / BLOCK);
+        Some((word >> (index % BLOCK)) & 1 == 1)
+    }
+
+    /// Returns the number of elements set to `true` in `0..index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    pub fn rank1(&self, index: usize) -> usize {
+        assert!(
+            index <= self.len,
+            "rank index {} out of range for length {}",
+            index,
+            self.len
+        );
+        if index == self.len {
+            return self.ones;
+        }
+        let (ones, word) = self.locate(index / BLOCK);
+        let rest = index % BLOCK;
+        ones + (word & ((1 << rest) - 1)).count_ones() as usize
+    }
+
+    /// Returns the number of elements set to `false` in `0..index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length.
+    pub fn rank0(&self, index: usize) -> usize {
+        index - self.rank1(index)
+    }
+
+    /// Returns the position of the `k`-th (counted from zero) element set to
+    /// `true`, or `None` if there are not that many.
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        if k >= self.count_ones() {
+            return None;
+        }
+        Some(self.select(k, true))
+    }
+
+    /// Returns the position of the `k`-th (counted from zero) element set to
+    /// `false`, or `None` if there are not that many.
+    pub fn select0(&self, k: usize) -> Option<usize> {
+        if k >= self.count_zeros() {
+            return None;
+        }
+        Some(self.select(k, false))
+    }
+
+    /// Decompresses the vector.
+    pub fn to_bit_vec(&self) -> OrderedBitVec<O> {
+        let mut bits = OrderedBitVec::with_capacity(self.len);
+        let mut pos = 0;
+        for (block, class) in self.classes.iter().enumerate() {
+            let class = class as usize;
+            let width = offset_bits(class);
+            let word = decode_block(class, self.offsets.read_bits(pos, width));
+            pos += width;
+            let n = BLOCK.min(self.len - block * BLOCK);
+            bits.push_bits(O::convert_field(word, n), n);
+        }
+        bits
+    }
+
+    /// Returns the number of ones before `block` and the decoded block.
+    fn locate(&self, block: usize) -> (usize, u64) {
+        let superblock = block / BLOCKS_PER_SUPERBLOCK;
+        let mut ones = self.superblock_ones[superblock] as usize;
+        let mut pos = self.superblock_offsets[superblock] as usize;
+        for b in superblock * BLOCKS_PER_SUPERBLOCK..block {
+            let class = self.class(b);
+            ones += class;
+            pos += offset_bits(class);
+        }
+        let class = self.class(block);
+        let offset = self.offsets.read_bits(pos, offset_bits(class));
+        (ones, decode_block(class, offset))
+    }
+
+    fn class(&self, block: usize) -> usize {
+        self.classes.get(block).unwrap() as usize
+    }
+
+    fn select(&self, k: usize, value: bool) -> usize {
+        let before_superblock = |s: usize| {
+            let ones = self.superblock_ones[s] as usize;
+            if value {
+                ones
+            } else {
+                s * BLOCKS_PER_SUPERBLOCK * BLOCK - ones
+            }
+        };
+
+        // the last superblock starting with at most `k` matching elements
+        let (mut lo, mut hi) = (0, self.superblock_ones.len());
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if before_superblock(mid) <= k {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let mut k = k - before_superblock(lo);
+
+        let mut block = lo * BLOCKS_PER_SUPERBLOCK;
+        let mut pos = self.superblock_offsets[lo] as usize;
+        loop {
+            let class = self.class(block);
+            let matching = if value { class } else { BLOCK - class };
+            if k < matching {
+                let offset = self.offsets.read_bits(pos, offset_bits(class));
+                let word = decode_block(class, offset);
+                let word = if value { word } else { !word };
+                return block * BLOCK + select_in_word(word, k);
+            }
+            k -= matching;
+            pos += offset_bits(class);
+            block += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_order::MsbFirst;
+    use crate::bit_vec::BitVec;
+
+    #[test]
+    fn blocks() {
+        for &word in [
+            0u64,
+            1,
+            0b1011,
+            1 << 62,
+            (1 << 63) - 1,
+            0x2545_f491_4f6c_dd1d,
+        ]
+        .iter()
+        {
+            let class = word.count_ones() as usize;
+            let offset = encode_block(word);
+            assert!(offset < BINOMIAL[BLOCK][class]);
+            assert_eq!(decode_block(class, offset), word);
+        }
+        assert_eq!(offset_bits(0), 0);
+        assert_eq!(offset_bits(1), 6);
+        assert_eq!(offset_bits(63), 0);
+    }
+
+    fn check<O: BitOrder>(v: &OrderedBitVec<O>) {
+        let rrr = RrrBitVec::new(v);
+        assert_eq!(rrr.len(), v.len());
+        assert_eq!(&rrr.to_bit_vec(), v);
+        for i in 0..=v.len() {
+            assert_eq!(rrr.get(i), v.get(i));
+            assert_eq!(rrr.rank1(i), v.rank1(i));
+            assert_eq!(rrr.select1(i), v.select1(i));
+            assert_eq!(rrr.select0(i), v.select0(i));
+        }
+    }
+
+    #[test]
+    fn matches_naive() {
+        check(&BitVec::new());
+        let dense: BitVec = (0..5000u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 62 != 0 || i > 4000)
+            .collect();
+        check(&dense);
+        check(&dense.slice(..4033).to_bit_vec());
+        let sparse: OrderedBitVec<MsbFirst> = (0..5000).map(|i| i % 777 == 3).collect();
+        check(&sparse);
+        check(&BitVec::from(vec![true; 2016]));
+    }
+
+    #[test]
+    fn sparse_size() {
+        let v: BitVec = (0..100_000).map(|i| i % 100 == 0).collect();
+        let rrr = RrrBitVec::new(&v);
+        assert!(rrr.size_in_bits() * 4 < v.len());
+    }
+}
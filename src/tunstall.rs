@@ -0,0 +1,374 @@
+//! Tunstall codes, which map strings of values of varying length to
+//! codewords of a fixed number of bits.
+//!
+//! Where a Huffman code gives frequent values short codes, a Tunstall code
+//! gives them long strings in its dictionary, so that every codeword stands
+//! for about the same probability. The input is cut into the strings of the
+//! dictionary, which is complete: every input starts with exactly one of
+//! them. Fixed-width codewords make decoding a simple table lookup.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::io;
+
+use crate::bit_io::{BitSink, BitSource};
+
+#[derive(Clone, Copy, Debug)]
+enum ParseNode {
+    /// The end of the string of a codeword.
+    Leaf(usize),
+    /// A proper prefix of strings of the dictionary, with the position of
+    /// the first of its children, one for every value.
+    Internal(usize),
+}
+
+/// A leaf of the parse tree in the heap of those that can be expanded.
+struct Candidate {
+    probability: f64,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Candidate) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        // pop the most probable leaf first, and the oldest of equal ones
+        self.probability
+            .total_cmp(&other.probability)
+            .then(other.node.cmp(&self.node))
+    }
+}
+
+/// A Tunstall code of the values less than `alphabet_size`, whose
+/// codewords are `bits` bits long.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::BitVec;
+/// # use rust_compress::tunstall::TunstallCode;
+/// let code = TunstallCode::new(&[7, 2, 1], 3);
+/// assert_eq!(code.len(), 7);
+/// assert_eq!(code.word(0), [0, 0, 0]);
+/// let values = [0, 0, 0, 1, 0, 2, 0, 0];
+/// let mut bits = BitVec::new();
+/// code.encode(&values, &mut bits).unwrap();
+/// assert_eq!(bits.len(), 4 * 3);
+/// let decoded = code.decode(&mut bits.iter(), values.len()).unwrap();
+/// assert_eq!(decoded, values);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TunstallCode {
+    bits: usize,
+    alphabet_size: usize,
+    /// The parse tree of the dictionary, the root first.
+    nodes: Vec<ParseNode>,
+    /// The parent of every node, from which the strings are rebuilt, as
+    /// storing them all would take the square of their length for skewed
+    /// counts.
+    parents: Vec<usize>,
+    /// The leaf of every codeword, in lexicographic order of their strings.
+    leaves: Vec<usize>,
+}
+
+impl TunstallCode {
+    /// Builds the Tunstall code of `counts`, the number of occurrences of
+    /// every value, with codewords of `bits` bits.
+    ///
+    /// The dictionary starts with the single values, and its most probable
+    /// string is replaced by its extensions by every value as long as the
+    /// codewords suffice. A value whose count is zero is never extended,
+    /// but gets a string for the input to be parsed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two counts, if `bits` is greater than
+    /// 32 or not less than the width of a `usize`, or if there are more than
+    /// `2^bits` counts.
+    pub fn new(counts: &[u128], bits: usize) -> TunstallCode {
+        let n = counts.len();
+        assert!(n >= 2, "Tunstall codes need at least two values");
+        assert!(
+            bits <= 32 && bits < usize::BITS as usize,
+            "codewords of {} bits are too long",
+            bits
+        );
+        assert!(
+            n <= 1 << bits,
+            "{} values do not fit in codewords of {} bits",
+            n,
+            bits
+        );
+        let total: u128 = counts.iter().sum();
+        let probabilities: Vec<f64> = counts
+            .iter()
+            .map(|&count| {
+                if total == 0 {
+                    1.0 / n as f64
+                } else {
+                    count as f64 / total as f64
+                }
+            })
+            .collect();
+
+        let mut nodes = vec![ParseNode::Internal(1)];
+        let mut parents = vec![0];
+        let mut heap = BinaryHeap::new();
+        for (value, &probability) in probabilities.iter().enumerate() {
+            nodes.push(ParseNode::Leaf(0));
+            parents.push(0);
+            heap.push(Candidate {
+                probability,
+                node: 1 + value,
+            });
+        }
+        let mut leaves = n;
+        while leaves + n - 1 <= 1 << bits {
+            let leaf = match heap.pop() {
+                Some(leaf) if leaf.probability > 0.0 => leaf,
+                _ => break,
+            };
+            let first = nodes.len();
+            nodes[leaf.node] = ParseNode::Internal(first);
+            for (value, &probability) in probabilities.iter().enumerate() {
+                nodes.push(ParseNode::Leaf(0));
+                parents.push(leaf.node);
+                heap.push(Candidate {
+                    probability: leaf.probability * probability,
+                    node: first + value,
+                });
+            }
+            leaves += n - 1;
+        }
+
+        // the leaves numbered in lexicographic order of their strings, by a
+        // walk of the tree with a stack of its own, however deep it gets
+        let mut order = Vec::with_capacity(leaves);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            match nodes[node] {
+                ParseNode::Leaf(_) => {
+                    nodes[node] = ParseNode::Leaf(order.len());
+                    order.push(node);
+                }
+                ParseNode::Internal(first) => stack.extend((first..first + n).rev()),
+            }
+        }
+        TunstallCode {
+            bits,
+            alphabet_size: n,
+            nodes,
+            parents,
+            leaves: order,
+        }
+    }
+
+    /// Appends the string of `codeword` to `values` in reverse order.
+    fn push_reversed(&self, codeword: usize, values: &mut Vec<usize>) {
+        let mut node = self.leaves[codeword];
+        while node != 0 {
+            let parent = self.parents[node];
+            match self.nodes[parent] {
+                ParseNode::Internal(first) => values.push(node - first),
+                ParseNode::Leaf(_) => unreachable!(),
+            }
+            node = parent;
+        }
+    }
+
+    /// Returns the number of bits of every codeword.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Returns the number of distinct values.
+    pub fn alphabet_size(&self) -> usize {
+        self.alphabet_size
+    }
+
+    /// Returns the number of strings in the dictionary, at most `2^bits`.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns `true` if the dictionary is empty, which it never is.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns the string of `codeword`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `codeword` is not less than `len()`.
+    pub fn word(&self, codeword: usize) -> Vec<usize> {
+        let mut word = Vec::new();
+        self.push_reversed(codeword, &mut word);
+        word.reverse();
+        word
+    }
+
+    /// Writes the codewords of the strings that `values` parses into.
+    ///
+    /// If the values end in the middle of a string, it is completed with
+    /// zeros, which the decoder drops as it is told the number of values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a value is not less than the alphabet size.
+    pub fn encode<S: BitSink>(&self, values: &[usize], sink: &mut S) -> io::Result<()> {
+        let mut node = 0;
+        for &value in values.iter() {
+            assert!(
+                value < self.alphabet_size,
+                "value {} out of range for alphabet of size {}",
+                value,
+                self.alphabet_size
+            );
+            node = match self.nodes[node] {
+                ParseNode::Internal(first) => first + value,
+                ParseNode::Leaf(_) => unreachable!(),
+            };
+            if let ParseNode::Leaf(codeword) = self.nodes[node] {
+                sink.write_bits(codeword as u64, self.bits)?;
+                node = 0;
+            }
+        }
+        while let ParseNode::Internal(first) = self.nodes[node] {
+            if node == 0 {
+                return Ok(());
+            }
+            node = first;
+        }
+        match self.nodes[node] {
+            ParseNode::Leaf(codeword) => sink.write_bits(codeword as u64, self.bits),
+            ParseNode::Internal(_) => unreachable!(),
+        }
+    }
+
+    /// Decodes `n` values.
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if the input ends first, and
+    /// with `ErrorKind::InvalidData` on a codeword that is not in the
+    /// dictionary.
+    pub fn decode<S: BitSource>(&self, input: &mut S, n: usize) -> io::Result<Vec<usize>> {
+        let mut values = Vec::with_capacity(n);
+        while values.len() < n {
+            let codeword = input.read_bits(self.bits)? as usize;
+            if codeword >= self.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid Tunstall codeword",
+                ));
+            }
+            let start = values.len();
+            self.push_reversed(codeword, &mut values);
+            values[start..].reverse();
+            values.truncate(n);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_vec::BitVec;
+
+    #[test]
+    fn dictionary() {
+        let sets: [&[u128]; 4] = [&[7, 2, 1], &[1, 1], &[5, 0, 3, 1], &[0, 0, 0]];
+        for counts in sets.iter() {
+            for bits in 2..8 {
+                let code = TunstallCode::new(counts, bits);
+                let n = counts.len();
+                assert!(code.len() <= 1 << bits);
+                if counts.iter().all(|&c| c > 0) {
+                    assert!(code.len() + n - 1 > 1 << bits);
+                }
+                assert_eq!((code.len() - 1) % (n - 1), 0);
+                // prefix-free, complete and sorted
+                for codeword in 1..code.len() {
+                    let (a, b) = (code.word(codeword - 1), code.word(codeword));
+                    assert!(a < b && !b.starts_with(&a));
+                }
+                let mut kraft = 0.0;
+                for codeword in 0..code.len() {
+                    kraft += (n as f64).powi(-(code.word(codeword).len() as i32));
+                }
+                assert!((kraft - 1.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let values: Vec<usize> = (0..5000u64)
+            .map(|i| match i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 60 {
+                0..=11 => 0,
+                12..=14 => 1,
+                _ => 2,
+            })
+            .collect();
+        let mut counts = [0; 3];
+        for &value in values.iter() {
+            counts[value] += 1;
+        }
+        let code = TunstallCode::new(&counts, 8);
+        for len in [0, 1, 2, 3, 100, values.len()].iter() {
+            let mut bits = BitVec::new();
+            code.encode(&values[..*len], &mut bits).unwrap();
+            assert_eq!(bits.len() % 8, 0);
+            let decoded = code.decode(&mut bits.iter(), *len).unwrap();
+            assert_eq!(decoded, &values[..*len]);
+        }
+        // much shorter than two bits per value
+        let mut bits = BitVec::new();
+        code.encode(&values, &mut bits).unwrap();
+        assert!(bits.len() < values.len() * 3 / 2);
+
+        let err = code
+            .decode(&mut bits.iter(), values.len() + 100)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let code = TunstallCode::new(&[3, 1], 2);
+        assert_eq!(code.len(), 4);
+        let code = TunstallCode::new(&[3, 1, 1], 2);
+        assert_eq!(code.len(), 3);
+        let bits: BitVec = "11".parse().unwrap();
+        let err = code.decode(&mut bits.iter(), 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn skewed() {
+        // strings of up to 2^16 - 1 values, which the tree holds in linear
+        // memory and walks without recursion
+        let code = TunstallCode::new(&[1 << 40, 1], 16);
+        assert_eq!(code.len(), 1 << 16);
+        assert_eq!(code.word(0).len(), (1 << 16) - 1);
+        let values = vec![0; 100_000];
+        let mut bits = BitVec::new();
+        code.encode(&values, &mut bits).unwrap();
+        assert_eq!(bits.len(), 2 * 16);
+        assert_eq!(code.decode(&mut bits.iter(), values.len()).unwrap(), values);
+    }
+
+    #[test]
+    #[should_panic(expected = "codewords of 33 bits are too long")]
+    fn too_long() {
+        TunstallCode::new(&[1, 1], 33);
+    }
+}
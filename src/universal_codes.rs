@@ -0,0 +1,306 @@
+//! Universal codes for positive integers: Elias gamma and delta, and
+//! Fibonacci codes.
+//!
+//! For the Elias codes, a number `n` with `N + 1` significant bits is
+//! written with an `N`-bit length prefix followed by a one and the low `N`
+//! bits of `n` as an integer field. In an MSB-first stream this is exactly
+//! the classic code, in which the one is the leading bit of `n`; in an
+//! LSB-first stream the low bits are written least significant first, like
+//! any other field.
+//!
+//! The codes work on any [`BitSink`] or [`BitSource`]. Decoders fail with
+//! `ErrorKind::UnexpectedEof` if the bits run out and with
+//! `ErrorKind::InvalidData` if they do not hold a code for a value that fits
+//! in a `u64`.
+
+use std::io;
+
+use crate::bit_io::{BitSink, BitSource};
+
+/// Returns `floor(log2(n))` for non-zero `n`.
+fn log2(n: u64) -> usize {
+    63 - n.leading_zeros() as usize
+}
+
+fn write_low_bits<S: BitSink>(sink: &mut S, n: u64, nbits: usize) -> io::Result<()> {
+    let mask = if nbits == 64 { !0 } else { (1 << nbits) - 1 };
+    sink.write_bits(n & mask, nbits)
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "encoded value overflows u64")
+}
+
+/// Writes the Elias gamma code of `n`: `floor(log2(n))` zeros followed by
+/// the significant bits of `n`.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::MsbBitVec;
+/// # use rust_compress::universal_codes::{decode_gamma, encode_gamma};
+/// let mut v = MsbBitVec::new();
+/// encode_gamma(&mut v, 1).unwrap();
+/// encode_gamma(&mut v, 9).unwrap();
+/// assert_eq!(v.to_string(), "10001001");
+/// let mut iter = v.iter();
+/// assert_eq!(decode_gamma(&mut iter).unwrap(), 1);
+/// assert_eq!(decode_gamma(&mut iter).unwrap(), 9);
+/// assert!(decode_gamma(&mut iter).is_err());
+/// ```
+pub fn encode_gamma<S: BitSink>(sink: &mut S, n: u64) -> io::Result<()> {
+    assert!(n != 0, "the gamma code is not defined for zero");
+    let len = log2(n);
+    sink.write_bits(0, len)?;
+    sink.write_bit(true)?;
+    write_low_bits(sink, n, len)
+}
+
+/// Reads an Elias gamma code.
+pub fn decode_gamma<S: BitSource>(source: &mut S) -> io::Result<u64> {
+    let mut len = 0;
+    while !source.read_bit()? {
+        len += 1;
+        if len > 63 {
+            return Err(too_large());
+        }
+    }
+    Ok((1 << len) | source.read_bits(len)?)
+}
+
+/// Writes the Elias delta code of `n`: the gamma code of the number of
+/// significant bits of `n`, followed by those bits without the leading one.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::MsbBitVec;
+/// # use rust_compress::universal_codes::{decode_delta, encode_delta};
+/// let mut v = MsbBitVec::new();
+/// encode_delta(&mut v, 1).unwrap();
+/// encode_delta(&mut v, 9).unwrap();
+/// assert_eq!(v.to_string(), "100100001");
+/// let mut iter = v.iter();
+/// assert_eq!(decode_delta(&mut iter).unwrap(), 1);
+/// assert_eq!(decode_delta(&mut iter).unwrap(), 9);
+/// ```
+pub fn encode_delta<S: BitSink>(sink: &mut S, n: u64) -> io::Result<()> {
+    assert!(n != 0, "the delta code is not defined for zero");
+    let len = log2(n);
+    encode_gamma(sink, len as u64 + 1)?;
+    write_low_bits(sink, n, len)
+}
+
+/// Reads an Elias delta code.
+pub fn decode_delta<S: BitSource>(source: &mut S) -> io::Result<u64> {
+    let len = decode_gamma(source)? - 1;
+    if len > 63 {
+        return Err(too_large());
+    }
+    let len = len as usize;
+    Ok((1 << len) | source.read_bits(len)?)
+}
+
+/// The number of Fibonacci numbers `F(2), F(3), ...` that fit in a `u64`.
+const FIBONACCI_LEN: usize = 92;
+
+const fn fibonacci_table() -> [u64; FIBONACCI_LEN] {
+    let mut table = [1; FIBONACCI_LEN];
+    table[1] = 2;
+    let mut i = 2;
+    while i < FIBONACCI_LEN {
+        table[i] = table[i - 1] + table[i - 2];
+        i += 1;
+    }
+    table
+}
+
+/// `FIBONACCI[i]` is the Fibonacci number `F(i + 2)`.
+static FIBONACCI: [u64; FIBONACCI_LEN] = fibonacci_table();
+
+/// Writes the Fibonacci code of `n`: its Zeckendorf representation, a bit
+/// for each of `1, 2, 3, 5, 8, ...` up to the largest term used, followed by
+/// a one.
+///
+/// The representation never has two adjacent ones, so the final `11` marks
+/// the end of every code and a decoder can resynchronize after a corrupted
+/// bit.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::bit_vec::BitVec;
+/// # use rust_compress::universal_codes::{decode_fibonacci, encode_fibonacci};
+/// let mut v = BitVec::new();
+/// encode_fibonacci(&mut v, 1).unwrap();
+/// encode_fibonacci(&mut v, 11).unwrap(); // 3 + 8
+/// assert_eq!(v.to_string(), "11001011");
+/// let mut iter = v.iter();
+/// assert_eq!(decode_fibonacci(&mut iter).unwrap(), 1);
+/// assert_eq!(decode_fibonacci(&mut iter).unwrap(), 11);
+/// ```
+pub fn encode_fibonacci<S: BitSink>(sink: &mut S, n: u64) -> io::Result<()> {
+    assert!(n != 0, "the fibonacci code is not defined for zero");
+    let top = FIBONACCI.iter().rposition(|&f| f <= n).unwrap();
+    // bit `i` of `code` is the coefficient of `FIBONACCI[i]`
+    let mut code: u128 = 0;
+    let mut rest = n;
+    for i in (0..=top).rev() {
+        if FIBONACCI[i] <= rest {
+            rest -= FIBONACCI[i];
+            code |= 1 << i;
+        }
+    }
+    code |= 1 << (top + 1);
+    for i in 0..top + 2 {
+        sink.write_bit((code >> i) & 1 == 1)?;
+    }
+    Ok(())
+}
+
+/// Reads a Fibonacci code.
+pub fn decode_fibonacci<S: BitSource>(source: &mut S) -> io::Result<u64> {
+    let mut n: u64 = 0;
+    let mut prev = false;
+    for i in 0.. {
+        let bit = source.read_bit()?;
+        if bit && prev {
+            return Ok(n);
+        }
+        if bit {
+            let f = FIBONACCI.get(i).ok_or_else(too_large)?;
+            n = n.checked_add(*f).ok_or_else(too_large)?;
+        }
+        prev = bit;
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_io::{BitReader, BitWriter};
+    use crate::bit_order::{BitOrder, LsbFirst, MsbFirst};
+    use crate::bit_vec::{BitVec, OrderedBitVec};
+
+    fn values() -> Vec<u64> {
+        let mut values = vec![1, 2, 3, 4, 5, 7, 8, 9, 1000];
+        for shift in 1..64 {
+            values.push((1 << shift) - 1);
+            values.push(1 << shift);
+            values.push((1 << shift) + 1);
+        }
+        values.push(u64::MAX - 1);
+        values.push(u64::MAX);
+        values
+    }
+
+    fn check<O: BitOrder>() {
+        let values = values();
+        let mut gamma = OrderedBitVec::<O>::new();
+        let mut delta = OrderedBitVec::<O>::new();
+        for &n in values.iter() {
+            let (g, d) = (gamma.len(), delta.len());
+            encode_gamma(&mut gamma, n).unwrap();
+            encode_delta(&mut delta, n).unwrap();
+            let len = log2(n);
+            assert_eq!(gamma.len() - g, 2 * len + 1);
+            assert_eq!(delta.len() - d, len + 2 * log2(len as u64 + 1) + 1);
+        }
+        let mut g = gamma.iter();
+        let mut d = delta.iter();
+        for &n in values.iter() {
+            assert_eq!(decode_gamma(&mut g).unwrap(), n);
+            assert_eq!(decode_delta(&mut d).unwrap(), n);
+        }
+        assert_eq!(g.len(), 0);
+        assert_eq!(d.len(), 0);
+
+        let mut fibonacci = OrderedBitVec::<O>::new();
+        for &n in values.iter() {
+            encode_fibonacci(&mut fibonacci, n).unwrap();
+        }
+        let mut iter = fibonacci.iter();
+        for &n in values.iter() {
+            assert_eq!(decode_fibonacci(&mut iter).unwrap(), n);
+        }
+        assert_eq!(iter.len(), 0);
+    }
+
+    #[test]
+    fn fibonacci() {
+        assert_eq!(FIBONACCI[FIBONACCI_LEN - 1], 12_200_160_415_121_876_738);
+        let code = |n| {
+            let mut v = BitVec::new();
+            encode_fibonacci(&mut v, n).unwrap();
+            v.to_string()
+        };
+        assert_eq!(code(1), "11");
+        assert_eq!(code(2), "011");
+        assert_eq!(code(4), "1011");
+        assert_eq!(code(12), "101011");
+        assert_eq!(code(u64::MAX).len(), FIBONACCI_LEN + 1);
+
+        // a flipped bit corrupts at most the codes around it
+        let mut v = BitVec::new();
+        for n in 1..=20 {
+            encode_fibonacci(&mut v, n).unwrap();
+        }
+        v.set(20, !v.get(20).unwrap());
+        let decoded: Vec<u64> = {
+            let mut iter = v.iter();
+            std::iter::from_fn(|| decode_fibonacci(&mut iter).ok()).collect()
+        };
+        assert_eq!(decoded[..4], [1, 2, 3, 4]);
+        assert!(decoded.ends_with(&[10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]));
+    }
+
+    #[test]
+    fn round_trip() {
+        check::<LsbFirst>();
+        check::<MsbFirst>();
+    }
+
+    #[test]
+    fn streaming() {
+        let mut w = BitWriter::new(Vec::new());
+        for n in 1..100 {
+            encode_gamma(&mut w, n).unwrap();
+            encode_delta(&mut w, n).unwrap();
+            encode_fibonacci(&mut w, n).unwrap();
+        }
+        let bytes = w.finish().unwrap();
+        let mut r = BitReader::new(&bytes[..]);
+        for n in 1..100 {
+            assert_eq!(decode_gamma(&mut r).unwrap(), n);
+            assert_eq!(decode_delta(&mut r).unwrap(), n);
+            assert_eq!(decode_fibonacci(&mut r).unwrap(), n);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let truncated: BitVec = "0001".parse().unwrap();
+        let err = decode_gamma(&mut truncated.iter()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let too_long: BitVec = std::iter::repeat_n(false, 64).chain(Some(true)).collect();
+        let err = decode_gamma(&mut too_long.iter()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // gamma(65) announces a 65-bit value
+        let mut v = BitVec::new();
+        encode_gamma(&mut v, 65).unwrap();
+        assert!(decode_delta(&mut v.iter()).is_err());
+    }
+}
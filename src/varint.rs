@@ -0,0 +1,222 @@
+//! LEB128 variable-length integers, unsigned and zigzag-signed.
+//!
+//! An unsigned value is written seven bits at a time, least significant
+//! group first, with the high bit of each byte set on all bytes but the
+//! last. Signed values are first mapped to unsigned ones by zigzag encoding,
+//! so that numbers of small magnitude get short codes whatever their sign.
+
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// The longest encoding of a `u64`.
+pub const MAX_LEN: usize = 10;
+
+/// Maps a signed value to an unsigned one: 0, -1, 1, -2, 2, ... become
+/// 0, 1, 2, 3, 4, ...
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::varint::{zigzag_decode, zigzag_encode};
+/// assert_eq!(zigzag_encode(-3), 5);
+/// assert_eq!(zigzag_encode(i64::MIN), u64::MAX);
+/// assert_eq!(zigzag_decode(6), 3);
+/// ```
+pub fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// The inverse of `zigzag_encode`.
+pub fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Returns the number of bytes in the encoding of `value`.
+pub fn encoded_len(value: u64) -> usize {
+    (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
+
+/// Appends the encoding of `value` to `out`.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::varint::encode_u64;
+/// let mut out = Vec::new();
+/// encode_u64(300, &mut out);
+/// assert_eq!(out, vec![0xac, 0x02]);
+/// ```
+pub fn encode_u64(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Appends the zigzag encoding of `value` to `out`.
+pub fn encode_i64(value: i64, out: &mut Vec<u8>) {
+    encode_u64(zigzag_encode(value), out)
+}
+
+/// Decodes an unsigned value from the start of `bytes`, returning it with
+/// the number of bytes it took.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_compress::varint::{decode_u64, DecodeError};
+/// assert_eq!(decode_u64(&[0xac, 0x02, 0x07]), Ok((300, 2)));
+/// assert_eq!(decode_u64(&[0xac]), Err(DecodeError::UnexpectedEnd));
+/// ```
+pub fn decode_u64(bytes: &[u8]) -> Result<(u64, usize), DecodeError> {
+    let mut value = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if accumulate(&mut value, i, byte)? {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(DecodeError::UnexpectedEnd)
+}
+
+/// Decodes a zigzag-encoded signed value from the start of `bytes`,
+/// returning it with the number of bytes it took.
+pub fn decode_i64(bytes: &[u8]) -> Result<(i64, usize), DecodeError> {
+    decode_u64(bytes).map(|(value, len)| (zigzag_decode(value), len))
+}
+
+/// Writes the encoding of `value`.
+pub fn write_u64<W: Write>(mut writer: W, value: u64) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(MAX_LEN);
+    encode_u64(value, &mut buf);
+    writer.write_all(&buf)
+}
+
+/// Writes the zigzag encoding of `value`.
+pub fn write_i64<W: Write>(writer: W, value: i64) -> io::Result<()> {
+    write_u64(writer, zigzag_encode(value))
+}
+
+/// Reads an unsigned value, one byte at a time so that nothing past its end
+/// is consumed.
+///
+/// Fails with `ErrorKind::UnexpectedEof` if the input ends early and with
+/// `ErrorKind::InvalidData` if the value overflows a `u64`.
+pub fn read_u64<R: Read>(mut reader: R) -> io::Result<u64> {
+    let mut value = 0;
+    for i in 0.. {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        if accumulate(&mut value, i, byte[0])? {
+            break;
+        }
+    }
+    Ok(value)
+}
+
+/// Reads a zigzag-encoded signed value, like `read_u64`.
+pub fn read_i64<R: Read>(reader: R) -> io::Result<i64> {
+    read_u64(reader).map(zigzag_decode)
+}
+
+/// Adds the `i`-th byte of an encoding to `value`, returning whether it was
+/// the last one.
+fn accumulate(value: &mut u64, i: usize, byte: u8) -> Result<bool, DecodeError> {
+    let shift = 7 * i;
+    if shift == 63 && byte > 1 || shift > 63 {
+        return Err(DecodeError::Overflow);
+    }
+    *value |= ((byte & 0x7f) as u64) << shift;
+    Ok(byte & 0x80 == 0)
+}
+
+/// An error returned when decoding a varint fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The input ends in the middle of a value.
+    UnexpectedEnd,
+    /// The value does not fit in 64 bits.
+    Overflow,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "varint ends unexpectedly"),
+            DecodeError::Overflow => write!(f, "varint overflows u64"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+impl From<DecodeError> for io::Error {
+    fn from(e: DecodeError) -> io::Error {
+        match e {
+            DecodeError::UnexpectedEnd => io::ErrorKind::UnexpectedEof.into(),
+            DecodeError::Overflow => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut values = vec![0, 1, 127, 128, 255, 300, 16383, 16384];
+        for shift in 7..64 {
+            values.push((1 << shift) - 1);
+            values.push(1 << shift);
+        }
+        values.push(u64::MAX);
+        let mut out = Vec::new();
+        for &value in values.iter() {
+            let start = out.len();
+            encode_u64(value, &mut out);
+            assert_eq!(out.len() - start, encoded_len(value));
+            assert!(encoded_len(value) <= MAX_LEN);
+        }
+        let mut rest = &out[..];
+        for &value in values.iter() {
+            let (decoded, len) = decode_u64(rest).unwrap();
+            assert_eq!(decoded, value);
+            assert_eq!(read_u64(&mut &rest[..len]).unwrap(), value);
+            rest = &rest[len..];
+        }
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn signed() {
+        let values = [0, -1, 1, -64, 64, i64::MIN, i64::MAX];
+        for &v in values.iter() {
+            assert_eq!(zigzag_decode(zigzag_encode(v)), v);
+            let mut out = Vec::new();
+            write_i64(&mut out, v).unwrap();
+            assert_eq!(decode_i64(&out), Ok((v, out.len())));
+            assert_eq!(read_i64(&out[..]).unwrap(), v);
+        }
+        assert_eq!(encoded_len(zigzag_encode(-64)), 1);
+    }
+
+    #[test]
+    fn malformed() {
+        let max = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(decode_u64(&max), Ok((u64::MAX, 10)));
+        let mut over = max;
+        over[9] = 0x02;
+        assert_eq!(decode_u64(&over), Err(DecodeError::Overflow));
+        let mut long = max.to_vec();
+        long[9] = 0x81;
+        long.push(0x00);
+        assert_eq!(decode_u64(&long), Err(DecodeError::Overflow));
+        assert_eq!(decode_u64(&[]), Err(DecodeError::UnexpectedEnd));
+        let err = read_u64(&over[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = read_u64(&[0x80][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}